@@ -0,0 +1,210 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Fields, LitStr, Type};
+
+/// Derives `allowed_fields()` and `schema()` from the fields of a struct, see
+/// `query::schema::QuerySchema`.
+///
+/// The type of each field is derived from its Rust type, eg `i64` is an `Int` and `Uuid` a
+/// `Uuid`, and unknown types are `Text`. Fields can be customised with:
+///
+/// - `#[query(rename = "userId")]`: the name in the url query, the field being the column;
+/// - `#[query(skip)]`: leave the field out of the schema;
+/// - `#[query(ops(eq, ge, le))]`: only allow these operators on the field.
+#[proc_macro_derive(Queryable, attributes(query))]
+pub fn derive_queryable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// A field of the struct, as declared in the schema.
+struct QueryField {
+    name: String,
+    column: Option<String>,
+    field_type: &'static str,
+    operators: Option<Vec<&'static str>>,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => Err(syn::Error::new_spanned(
+                &input.ident,
+                "Queryable can only be derived for structs with named fields",
+            ))?,
+        },
+        _ => Err(syn::Error::new_spanned(
+            &input.ident,
+            "Queryable can only be derived for structs",
+        ))?,
+    };
+
+    let mut query_fields = Vec::new();
+    for field in fields {
+        let ident = match field.ident {
+            Some(ref ident) => ident.to_string(),
+            None => continue,
+        };
+
+        let mut skip = false;
+        let mut rename = None;
+        let mut operators = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("query"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    skip = true;
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("rename") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    rename = Some(name.value());
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("ops") {
+                    let mut ops = Vec::new();
+                    meta.parse_nested_meta(|op| {
+                        let operator = match op.path.get_ident().map(|ident| ident.to_string()) {
+                            Some(op) => operator(&op),
+                            None => None,
+                        };
+                        let operator = operator
+                            .ok_or_else(|| op.error(format!("expected one of {}", OPERATORS)))?;
+                        ops.push(operator);
+
+                        Ok(())
+                    })?;
+                    operators = Some(ops);
+                    return Ok(());
+                }
+
+                Err(meta.error("expected `rename`, `skip` or `ops`"))
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let column = rename.as_ref().map(|_| ident.clone());
+        query_fields.push(QueryField {
+            name: rename.unwrap_or(ident),
+            column,
+            field_type: field_type(&field.ty),
+            operators,
+        });
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let len = query_fields.len();
+    let names: Vec<&str> = query_fields
+        .iter()
+        .map(|field| field.name.as_str())
+        .collect();
+    let schema_fields = query_fields.iter().map(|field| {
+        let name = &field.name;
+        let field_type = format_ident!("{}", field.field_type);
+
+        let mut tokens = quote! {
+            ::query::schema::Field::new(#name, ::query::value::FieldType::#field_type)
+        };
+        if let Some(ref column) = field.column {
+            tokens.extend(quote! { .column(#column) });
+        }
+        if let Some(ref operators) = field.operators {
+            let operators = operators.iter().map(|op| format_ident!("{}", op));
+            tokens.extend(quote! {
+                .operators([#(::query::filter::Operator::#operators),*])
+            });
+        }
+
+        tokens
+    });
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Returns the names of the fields of the schema, to pass to `UrlQuery::new`.
+            pub fn allowed_fields() -> [&'static str; #len] {
+                [#(#names),*]
+            }
+
+            /// Returns the schema derived from the fields of the struct.
+            pub fn schema() -> &'static ::query::schema::QuerySchema {
+                static SCHEMA: ::std::sync::OnceLock<::query::schema::QuerySchema> =
+                    ::std::sync::OnceLock::new();
+
+                SCHEMA.get_or_init(|| {
+                    ::query::schema::QuerySchema::new()
+                        #(.field(#schema_fields))*
+                })
+            }
+        }
+    })
+}
+
+/// The operators `ops` accepts, as they appear in a `filter[]` query parameter.
+const OPERATORS: &str = "eq, ne, gt, ge, lt, le, range, cont, has, overlaps, like, contains, \
+    startswith, endswith";
+
+/// Returns the variant of `query::filter::Operator` for an operator of `ops`.
+fn operator(op: &str) -> Option<&'static str> {
+    match op {
+        "eq" => Some("EQ"),
+        "ne" => Some("NE"),
+        "gt" => Some("GT"),
+        "ge" => Some("GE"),
+        "lt" => Some("LT"),
+        "le" => Some("LE"),
+        "range" => Some("RANGE"),
+        "cont" => Some("CONT"),
+        "has" => Some("HAS"),
+        "overlaps" => Some("OVERLAPS"),
+        "like" => Some("LIKE"),
+        "contains" => Some("CONTAINS"),
+        "startswith" => Some("STARTSWITH"),
+        "endswith" => Some("ENDSWITH"),
+        _ => None,
+    }
+}
+
+/// Returns the variant of `query::value::FieldType` for a Rust type, looking through `Option`.
+fn field_type(ty: &Type) -> &'static str {
+    let segment = match ty {
+        Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => segment,
+            None => return "Text",
+        },
+        _ => return "Text",
+    };
+
+    if segment.ident == "Option" {
+        if let syn::PathArguments::AngleBracketed(ref args) = segment.arguments {
+            if let Some(syn::GenericArgument::Type(ref inner)) = args.args.first() {
+                return field_type(inner);
+            }
+        }
+    }
+
+    match segment.ident.to_string().as_str() {
+        "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize" => "Int",
+        "f32" | "f64" | "Decimal" | "BigDecimal" => "Float",
+        "bool" => "Bool",
+        "Uuid" => "Uuid",
+        "NaiveDate" | "Date" => "Date",
+        "NaiveDateTime" | "DateTime" | "OffsetDateTime" | "PrimitiveDateTime" => "DateTime",
+        _ => "Text",
+    }
+}
@@ -0,0 +1,736 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlx::{
+    query::{Query, QueryAs, QueryScalar},
+    Database, Encode, Execute, IntoArguments, Type,
+};
+use uuid::Uuid;
+
+use crate::{
+    dialect::Dialect,
+    filter::{Filter, Operator},
+    safety::{verify_bind_coverage, CoverageError},
+    sql::{self, Arg},
+    value::parse_bool,
+    UrlQuery,
+};
+
+/// The type the values of a field are parsed as and bound with, see `bind_all`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BindType {
+    I32,
+    I64,
+    F64,
+    /// `true` or `false`, or another spelling recognised by `value::parse_bool`.
+    Bool,
+    String,
+    Uuid,
+    /// A `chrono::NaiveDate`, eg `2024-05-01`.
+    NaiveDate,
+    /// A `chrono::NaiveDateTime`, eg `2024-05-01T10:00:00`.
+    NaiveDateTime,
+    /// A `chrono::DateTime<Utc>`, from an RFC 3339 timestamp, eg `2024-05-01T10:00:00+02:00`.
+    DateTime,
+    /// A `Vec` of the element type, from a comma-separated list, eg `BindType::Vec(&BindType::I64)`
+    /// for the `1,2,3` of `filter[]=ids-overlaps-1,2,3`. The args of `has` filters are bound with
+    /// the element type. Only Postgres has arrays, see `BindDatabase`.
+    Vec(&'static BindType),
+}
+
+impl BindType {
+    /// Returns the name of the Rust type, eg `i64`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BindType::I32 => "i32",
+            BindType::I64 => "i64",
+            BindType::F64 => "f64",
+            BindType::Bool => "bool",
+            BindType::String => "String",
+            BindType::Uuid => "Uuid",
+            BindType::NaiveDate => "NaiveDate",
+            BindType::NaiveDateTime => "NaiveDateTime",
+            BindType::DateTime => "DateTime<Utc>",
+            BindType::Vec(element) => match element {
+                BindType::I32 => "Vec<i32>",
+                BindType::I64 => "Vec<i64>",
+                BindType::F64 => "Vec<f64>",
+                BindType::Bool => "Vec<bool>",
+                BindType::String => "Vec<String>",
+                BindType::Uuid => "Vec<Uuid>",
+                BindType::NaiveDate => "Vec<NaiveDate>",
+                BindType::NaiveDateTime => "Vec<NaiveDateTime>",
+                BindType::DateTime => "Vec<DateTime<Utc>>",
+                BindType::Vec(_) => "Vec<Vec>",
+            },
+        }
+    }
+}
+
+/// The type of the args of each field, eg `TypeMap::from([("userId", BindType::I64)])`.
+pub type TypeMap<'a> = HashMap<&'a str, BindType>;
+
+/// The column of each field, eg `ColumnMap::from([("userId", "user_id")])`. Fields which aren't
+/// mapped are used as is.
+pub type ColumnMap<'a> = HashMap<&'a str, &'a str>;
+
+/// The problems found by `bind_all`.
+#[derive(Debug, PartialEq)]
+pub enum BindError {
+    /// The field of an arg has no type, eg because of a typo in the `TypeMap`.
+    UnknownField(String),
+    /// The value of an arg isn't valid for the type of its field.
+    InvalidValue {
+        field: String,
+        value: String,
+        bind_type: BindType,
+    },
+    /// The placeholders of the SQL don't match the args, see `safety::verify_bind_coverage`.
+    Coverage(CoverageError),
+    /// A filter can't be pushed with one bind, eg `overlaps`, see `UrlQuery::push_conditions`.
+    UnsupportedOperator { field: String, operator: Operator },
+    /// The database can't bind the type, eg `BindType::Vec` on MySQL, see `BindDatabase`.
+    UnsupportedType { field: String, bind_type: BindType },
+}
+
+impl std::fmt::Display for BindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindError::UnknownField(field) => write!(f, "no type to bind field {} with", field),
+            BindError::InvalidValue {
+                field,
+                value,
+                bind_type,
+            } => write!(
+                f,
+                "invalid value {} for field {}, expected {}",
+                value,
+                field,
+                bind_type.as_str()
+            ),
+            BindError::Coverage(err) => write!(f, "{}", err),
+            BindError::UnsupportedOperator { field, operator } => write!(
+                f,
+                "operator {} on field {} can't be pushed",
+                operator.as_query_str(),
+                field
+            ),
+            BindError::UnsupportedType { field, bind_type } => write!(
+                f,
+                "field {} can't be bound as {} on the database",
+                field,
+                bind_type.as_str()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BindError {}
+
+/// An sqlx query which values can be bound to: `sqlx::query`, `sqlx::query_as`,
+/// `sqlx::query_scalar` and `sqlx::QueryBuilder`.
+pub trait BindQuery<'q, DB: Database>: Sized {
+    fn bind_value<T: 'q + Encode<'q, DB> + Type<DB>>(self, value: T) -> Self;
+}
+
+impl<'q, DB> BindQuery<'q, DB> for Query<'q, DB, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    <DB as Database>::Arguments<'q>: IntoArguments<'q, DB>,
+{
+    fn bind_value<T: 'q + Encode<'q, DB> + Type<DB>>(self, value: T) -> Self {
+        self.bind(value)
+    }
+}
+
+impl<'q, DB, O: Send> BindQuery<'q, DB> for QueryAs<'q, DB, O, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    <DB as Database>::Arguments<'q>: IntoArguments<'q, DB>,
+{
+    fn bind_value<T: 'q + Encode<'q, DB> + Type<DB>>(self, value: T) -> Self {
+        self.bind(value)
+    }
+}
+
+impl<'q, DB, O: Send> BindQuery<'q, DB> for QueryScalar<'q, DB, O, <DB as Database>::Arguments<'q>>
+where
+    DB: Database,
+    <DB as Database>::Arguments<'q>: IntoArguments<'q, DB>,
+{
+    fn bind_value<T: 'q + Encode<'q, DB> + Type<DB>>(self, value: T) -> Self {
+        self.bind(value)
+    }
+}
+
+impl<'q, DB: Database> BindQuery<'q, DB> for &mut sqlx::QueryBuilder<'q, DB> {
+    fn bind_value<T: 'q + Encode<'q, DB> + Type<DB>>(self, value: T) -> Self {
+        self.push_bind(value)
+    }
+}
+
+/// A database `bind_all` binds to: Postgres, MySQL or SQLite, with the `sqlx-postgres`,
+/// `sqlx-mysql` or `sqlx-sqlite` feature. Only Postgres binds `BindType::Vec` as an array, the
+/// others fail with `BindError::UnsupportedType`.
+pub trait BindDatabase: Database {
+    /// Parses the comma-separated list of the field with the element type and binds it to the
+    /// query as a `Vec`.
+    fn bind_vec<'q, Q: BindQuery<'q, Self>>(
+        query: Q,
+        field: &str,
+        value: &str,
+        element: BindType,
+    ) -> Result<Q, BindError> {
+        let _ = (query, value);
+        Err(BindError::UnsupportedType {
+            field: field.to_owned(),
+            bind_type: vec_type(element),
+        })
+    }
+}
+
+#[cfg(feature = "sqlx-postgres")]
+impl BindDatabase for sqlx::Postgres {
+    fn bind_vec<'q, Q: BindQuery<'q, Self>>(
+        query: Q,
+        field: &str,
+        value: &str,
+        element: BindType,
+    ) -> Result<Q, BindError> {
+        let query = match element {
+            BindType::I32 => {
+                query.bind_value(parse_vec(field, value, element, |v| v.parse::<i32>().ok())?)
+            }
+            BindType::I64 => {
+                query.bind_value(parse_vec(field, value, element, |v| v.parse::<i64>().ok())?)
+            }
+            BindType::F64 => {
+                query.bind_value(parse_vec(field, value, element, |v| v.parse::<f64>().ok())?)
+            }
+            BindType::Bool => query.bind_value(parse_vec(field, value, element, parse_bool)?),
+            BindType::String => {
+                query.bind_value(parse_vec(field, value, element, |v| Some(v.to_owned()))?)
+            }
+            BindType::Uuid => query.bind_value(parse_vec(field, value, element, |v| {
+                Uuid::parse_str(v).ok()
+            })?),
+            BindType::NaiveDate => {
+                query.bind_value(parse_vec(field, value, element, parse_naive_date)?)
+            }
+            BindType::NaiveDateTime => query.bind_value(parse_vec(field, value, element, |v| {
+                v.parse::<NaiveDateTime>().ok()
+            })?),
+            BindType::DateTime => {
+                query.bind_value(parse_vec(field, value, element, parse_datetime)?)
+            }
+            BindType::Vec(_) => Err(BindError::UnsupportedType {
+                field: field.to_owned(),
+                bind_type: vec_type(element),
+            })?,
+        };
+
+        Ok(query)
+    }
+}
+
+#[cfg(feature = "sqlx-mysql")]
+impl BindDatabase for sqlx::MySql {}
+
+#[cfg(feature = "sqlx-sqlite")]
+impl BindDatabase for sqlx::Sqlite {}
+
+/// Parses each arg with the type of its field and binds it to the query, in order. Unlike
+/// `sqlx_bind!`, an arg whose field has no type isn't skipped, which would leave its placeholder
+/// unbound, but returns `BindError::UnknownField`. `limit` and `offset` are `i64` unless typed.
+///
+/// The SQL of the query is checked with `safety::verify_bind_coverage` first, for Postgres, MySQL,
+/// SQLite and SQL Server.
+///
+/// # Examples
+///
+/// ```ignore
+/// use query::bind::{bind_all, BindType, TypeMap};
+///
+/// let types = TypeMap::from([("id", BindType::Uuid), ("userId", BindType::I64)]);
+///
+/// let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+///
+/// let orders: Vec<Order> = bind_all(sqlx::query_as(&sql), &args, &types)?
+///     .fetch_all(pool)
+///     .await?;
+/// ```
+pub fn bind_all<'q, DB, Q>(mut query: Q, args: &[Arg], types: &TypeMap) -> Result<Q, BindError>
+where
+    DB: BindDatabase,
+    Q: BindQuery<'q, DB> + Execute<'q, DB>,
+    i32: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    bool: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    Uuid: Encode<'q, DB> + Type<DB>,
+    NaiveDate: Encode<'q, DB> + Type<DB>,
+    NaiveDateTime: Encode<'q, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+{
+    if let Some(database) = database(DB::NAME) {
+        let fields: Vec<&str> = args.iter().map(|arg| arg.field.as_str()).collect();
+        verify_bind_coverage(query.sql(), &fields, args, &database).map_err(BindError::Coverage)?;
+    }
+
+    for arg in args {
+        query = match (bind_type(types, &arg.field)?, arg.comparator) {
+            (BindType::Vec(element), Some(Operator::HAS)) => {
+                bind_value(query, &arg.field, &arg.value, *element)?
+            }
+            (BindType::Vec(element), _) => DB::bind_vec(query, &arg.field, &arg.value, *element)?,
+            (bind_type, _) => bind_value(query, &arg.field, &arg.value, bind_type)?,
+        };
+    }
+
+    Ok(query)
+}
+
+impl UrlQuery {
+    /// Pushes the filters of the url query to an `sqlx::QueryBuilder`, each as `" AND "` and the
+    /// condition, binding the values with `push_bind` so sqlx numbers the placeholders. The values
+    /// are parsed with the type of their field, like `bind_all`, and LIKE patterns and `cont`
+    /// values are bound as strings. `overlaps` filters fail with `BindError::UnsupportedOperator`.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let mut qb = sqlx::QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE tenant_id = ");
+    /// qb.push_bind(tenant_id);
+    ///
+    /// parsed.push_conditions(&mut qb, &ColumnMap::from([("userId", "user_id")]), &types)?;
+    /// parsed.push_sorts(&mut qb, &columns);
+    /// parsed.push_limit_offset(&mut qb);
+    ///
+    /// let orders: Vec<Order> = qb.build_query_as().fetch_all(pool).await?;
+    /// ```
+    pub fn push_conditions<'q, DB>(
+        &self,
+        qb: &mut sqlx::QueryBuilder<'q, DB>,
+        columns: &ColumnMap,
+        types: &TypeMap,
+    ) -> Result<(), BindError>
+    where
+        DB: Database,
+        i32: Encode<'q, DB> + Type<DB>,
+        i64: Encode<'q, DB> + Type<DB>,
+        f64: Encode<'q, DB> + Type<DB>,
+        bool: Encode<'q, DB> + Type<DB>,
+        String: Encode<'q, DB> + Type<DB>,
+        Uuid: Encode<'q, DB> + Type<DB>,
+        NaiveDate: Encode<'q, DB> + Type<DB>,
+        NaiveDateTime: Encode<'q, DB> + Type<DB>,
+        DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+    {
+        let database = database(DB::NAME).unwrap_or(sql::Database::Postgres);
+        let placeholder = database.placeholder(1);
+
+        for filter in self.filters.iter().flat_map(Filter::expand_range) {
+            let (value, bind_type) = match filter.operator {
+                Operator::OVERLAPS => {
+                    return Err(BindError::UnsupportedOperator {
+                        field: filter.field.to_owned(),
+                        operator: filter.operator,
+                    })
+                }
+                Operator::CONT => (filter.value.to_owned(), BindType::String),
+                Operator::LIKE | Operator::CONTAINS | Operator::STARTSWITH | Operator::ENDSWITH => {
+                    let pattern = filter.operator.like_pattern(&filter.value);
+                    (
+                        pattern.unwrap_or_else(|| filter.value.to_owned()),
+                        BindType::String,
+                    )
+                }
+                _ => match bind_type(types, &filter.field)? {
+                    BindType::Vec(element) if filter.operator == Operator::HAS => {
+                        (filter.value.to_owned(), *element)
+                    }
+                    bind_type => (filter.value.to_owned(), bind_type),
+                },
+            };
+
+            let column = column(columns, &filter.field);
+            let condition = filter.to_sql_expression(1, column, &database);
+            let (before, after) = condition
+                .split_once(&placeholder)
+                .unwrap_or((&condition, ""));
+
+            qb.push(" AND ").push(before);
+            bind_value(&mut *qb, &filter.field, &value, bind_type)?;
+            qb.push(after);
+        }
+
+        Ok(())
+    }
+
+    /// Pushes the sorts of the url query to an `sqlx::QueryBuilder` as an `ORDER BY` clause, with
+    /// a leading space. Does nothing without sorts.
+    pub fn push_sorts<DB: Database>(
+        &self,
+        qb: &mut sqlx::QueryBuilder<'_, DB>,
+        columns: &ColumnMap,
+    ) {
+        if self.sorts.is_empty() {
+            return;
+        }
+
+        let database = database(DB::NAME).unwrap_or(sql::Database::Postgres);
+        let sorts: Vec<String> = self
+            .sorts
+            .iter()
+            .map(|sort| sort.to_sql_expression(column(columns, &sort.field), &database))
+            .collect();
+
+        qb.push(" ORDER BY ").push(sorts.join(", "));
+    }
+
+    /// Pushes the limit and offset of the url query to an `sqlx::QueryBuilder`, with a leading
+    /// space, binding them as `i64`.
+    pub fn push_limit_offset<'q, DB>(&self, qb: &mut sqlx::QueryBuilder<'q, DB>)
+    where
+        DB: Database,
+        i64: Encode<'q, DB> + Type<DB>,
+    {
+        let database = database(DB::NAME).unwrap_or(sql::Database::Postgres);
+        let (limit, offset) = self.limit_offset;
+        let offset = offset.filter(|_| limit.is_some() || database.supports_offset_without_limit());
+
+        // The placeholders are rendered as markers, replaced by the binds when pushing.
+        let sql = database.render_limit_offset(limit.map(|_| "\u{1}"), offset.map(|_| "\u{2}"));
+
+        let mut rest = sql.as_str();
+        while let Some(i) = rest.find(['\u{1}', '\u{2}']) {
+            qb.push(&rest[..i]);
+            let value = match rest.as_bytes()[i] {
+                1 => limit,
+                _ => offset,
+            };
+            qb.push_bind(i64::try_from(value.unwrap_or(0)).unwrap_or(i64::MAX));
+            rest = &rest[i + 1..];
+        }
+        qb.push(rest);
+    }
+}
+
+/// Returns the type of the args of the field. `limit` and `offset` are `i64` unless typed.
+fn bind_type(types: &TypeMap, field: &str) -> Result<BindType, BindError> {
+    match (types.get(field), field) {
+        (Some(bind_type), _) => Ok(*bind_type),
+        (None, "limit" | "offset") => Ok(BindType::I64),
+        (None, _) => Err(BindError::UnknownField(field.to_owned())),
+    }
+}
+
+/// Parses the value of the field with the type and binds it to the query.
+fn bind_value<'q, DB, Q>(
+    query: Q,
+    field: &str,
+    value: &str,
+    bind_type: BindType,
+) -> Result<Q, BindError>
+where
+    DB: Database,
+    Q: BindQuery<'q, DB>,
+    i32: Encode<'q, DB> + Type<DB>,
+    i64: Encode<'q, DB> + Type<DB>,
+    f64: Encode<'q, DB> + Type<DB>,
+    bool: Encode<'q, DB> + Type<DB>,
+    String: Encode<'q, DB> + Type<DB>,
+    Uuid: Encode<'q, DB> + Type<DB>,
+    NaiveDate: Encode<'q, DB> + Type<DB>,
+    NaiveDateTime: Encode<'q, DB> + Type<DB>,
+    DateTime<Utc>: Encode<'q, DB> + Type<DB>,
+{
+    let invalid = || BindError::InvalidValue {
+        field: field.to_owned(),
+        value: value.to_owned(),
+        bind_type,
+    };
+
+    let query = match bind_type {
+        BindType::I32 => query.bind_value(value.parse::<i32>().map_err(|_| invalid())?),
+        BindType::I64 => query.bind_value(value.parse::<i64>().map_err(|_| invalid())?),
+        BindType::F64 => query.bind_value(value.parse::<f64>().map_err(|_| invalid())?),
+        BindType::Bool => query.bind_value(parse_bool(value).ok_or_else(invalid)?),
+        BindType::String => query.bind_value(value.to_owned()),
+        BindType::Uuid => query.bind_value(Uuid::parse_str(value).map_err(|_| invalid())?),
+        BindType::NaiveDate => query.bind_value(parse_naive_date(value).ok_or_else(invalid)?),
+        BindType::NaiveDateTime => {
+            query.bind_value(value.parse::<NaiveDateTime>().map_err(|_| invalid())?)
+        }
+        BindType::DateTime => query.bind_value(parse_datetime(value).ok_or_else(invalid)?),
+        BindType::Vec(_) => Err(BindError::UnsupportedType {
+            field: field.to_owned(),
+            bind_type,
+        })?,
+    };
+
+    Ok(query)
+}
+
+/// Parses each element of the comma-separated list with `parse`.
+#[cfg(feature = "sqlx-postgres")]
+fn parse_vec<T>(
+    field: &str,
+    value: &str,
+    element: BindType,
+    parse: impl Fn(&str) -> Option<T>,
+) -> Result<Vec<T>, BindError> {
+    let invalid = || BindError::InvalidValue {
+        field: field.to_owned(),
+        value: value.to_owned(),
+        bind_type: vec_type(element),
+    };
+
+    value
+        .split(',')
+        .map(|v| parse(v).ok_or_else(invalid))
+        .collect()
+}
+
+/// Returns the `BindType::Vec` of the element type.
+fn vec_type(element: BindType) -> BindType {
+    match element {
+        BindType::I32 => BindType::Vec(&BindType::I32),
+        BindType::I64 => BindType::Vec(&BindType::I64),
+        BindType::F64 => BindType::Vec(&BindType::F64),
+        BindType::Bool => BindType::Vec(&BindType::Bool),
+        BindType::String => BindType::Vec(&BindType::String),
+        BindType::Uuid => BindType::Vec(&BindType::Uuid),
+        BindType::NaiveDate => BindType::Vec(&BindType::NaiveDate),
+        BindType::NaiveDateTime => BindType::Vec(&BindType::NaiveDateTime),
+        BindType::DateTime => BindType::Vec(&BindType::DateTime),
+        BindType::Vec(_) => element,
+    }
+}
+
+/// Parses a `YYYY-MM-DD` date.
+fn parse_naive_date(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// Parses an RFC 3339 timestamp into UTC.
+fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let datetime = DateTime::parse_from_rfc3339(value).ok()?;
+
+    Some(datetime.with_timezone(&Utc))
+}
+
+/// Returns the column of the field.
+fn column<'a>(columns: &ColumnMap<'a>, field: &'a str) -> &'a str {
+    columns.get(field).copied().unwrap_or(field)
+}
+
+/// Returns the database of the name of an sqlx driver.
+fn database(name: &str) -> Option<sql::Database> {
+    match name {
+        "PostgreSQL" => Some(sql::Database::Postgres),
+        "MySQL" => Some(sql::Database::MySQL),
+        "SQLite" => Some(sql::Database::Sqlite),
+        "MSSQL" => Some(sql::Database::Mssql),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::{Arguments, Execute, Postgres};
+
+    use super::{BindError, BindType, ColumnMap, TypeMap};
+    use crate::{filter::Operator, UrlQuery};
+
+    #[test]
+    #[cfg(feature = "sqlx-postgres")]
+    fn test_bind_all() {
+        use super::bind_all;
+        use crate::{safety::CoverageError, sql::QueryBuilder};
+
+        let query = "id=8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e&userId=1&isPaid=yes\
+        &filter[]=createdAt-ge-2024-05-01T10:00:00Z&limit=10";
+        let allowed = ["id", "userId", "isPaid", "createdAt"];
+
+        let mut types = TypeMap::from([
+            ("id", BindType::Uuid),
+            ("userId", BindType::I64),
+            ("isPaid", BindType::Bool),
+            ("createdAt", BindType::DateTime),
+        ]);
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .bind_pagination(true)
+            .build();
+
+        let mut bound = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).unwrap();
+        let arguments = bound.take_arguments().unwrap().unwrap();
+        assert_eq!(arguments.len(), 5);
+
+        let bound = bind_all(sqlx::query_as::<Postgres, (i64,)>(&sql), &args, &types);
+        assert!(bound.is_ok());
+
+        types.insert("userId", BindType::Uuid);
+        let result = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).map(|_| ());
+        assert_eq!(
+            result,
+            Err(BindError::InvalidValue {
+                field: "userId".to_owned(),
+                value: "1".to_owned(),
+                bind_type: BindType::Uuid,
+            })
+        );
+
+        types.remove("isPaid");
+        types.insert("userId", BindType::I64);
+        let result = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).map(|_| ());
+        assert_eq!(result, Err(BindError::UnknownField("isPaid".to_owned())));
+
+        types.insert("isPaid", BindType::Bool);
+        let sql = format!("{} AND region = $6", sql);
+        let result = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).map(|_| ());
+        assert_eq!(
+            result,
+            Err(BindError::Coverage(CoverageError::PlaceholderCount {
+                placeholders: 6,
+                binds: 5
+            }))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sqlx-postgres")]
+    fn test_bind_vec() {
+        use super::bind_all;
+        use crate::sql::QueryBuilder;
+
+        let query = "filter[]=ids-overlaps-1,2,3&filter[]=tags-has-rust";
+        let parsed = UrlQuery::new(query, ["ids", "tags"]).unwrap();
+
+        let mut types = TypeMap::from([
+            ("ids", BindType::Vec(&BindType::I64)),
+            ("tags", BindType::Vec(&BindType::String)),
+        ]);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .array_column("ids")
+            .array_column("tags")
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE ids && $1 AND $2 = ANY(tags)"
+        );
+
+        let mut bound = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).unwrap();
+        let arguments = bound.take_arguments().unwrap().unwrap();
+        assert_eq!(arguments.len(), 2);
+
+        types.insert("ids", BindType::Vec(&BindType::Uuid));
+        let result = bind_all(sqlx::query::<Postgres>(&sql), &args, &types).map(|_| ());
+        assert_eq!(
+            result,
+            Err(BindError::InvalidValue {
+                field: "ids".to_owned(),
+                value: "1,2,3".to_owned(),
+                bind_type: BindType::Vec(&BindType::Uuid),
+            })
+        );
+        assert_eq!(BindType::Vec(&BindType::Uuid).as_str(), "Vec<Uuid>");
+    }
+
+    #[test]
+    #[cfg(feature = "sqlx-mysql")]
+    fn test_bind_all_mysql() {
+        use sqlx::MySql;
+
+        use super::bind_all;
+        use crate::sql::{Database, QueryBuilder};
+
+        let query = "userId=1&isPaid=yes&filter[]=price-ge-10.5&limit=10";
+        let parsed = UrlQuery::new(query, ["userId", "isPaid", "price"]).unwrap();
+
+        let mut types = TypeMap::from([
+            ("userId", BindType::I64),
+            ("isPaid", BindType::Bool),
+            ("price", BindType::F64),
+        ]);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::MySQL)
+            .bind_pagination(true)
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE userId = ? AND isPaid = ? AND price >= ? LIMIT ?"
+        );
+
+        let mut bound = bind_all(sqlx::query::<MySql>(&sql), &args, &types).unwrap();
+        let arguments = bound.take_arguments().unwrap().unwrap();
+        assert_eq!(arguments.len(), 4);
+
+        // MySQL has no arrays.
+        types.insert("userId", BindType::Vec(&BindType::I64));
+        let result = bind_all(sqlx::query::<MySql>(&sql), &args, &types).map(|_| ());
+        assert_eq!(
+            result,
+            Err(BindError::UnsupportedType {
+                field: "userId".to_owned(),
+                bind_type: BindType::Vec(&BindType::I64),
+            })
+        );
+    }
+
+    #[test]
+    fn test_push_conditions() {
+        let query = "userId=1&filter[]=price-range-100..500&filter[]=name-contains-50%\
+        &filter[]=tags-has-rust&sort=price-desc,userId&limit=10&offset=20";
+        let allowed = ["userId", "price", "name", "tags"];
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+
+        let columns = ColumnMap::from([("userId", "user_id")]);
+        let types = TypeMap::from([
+            ("userId", BindType::I64),
+            ("price", BindType::F64),
+            ("tags", BindType::String),
+        ]);
+
+        let mut qb = sqlx::QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE tenant_id = ");
+        qb.push_bind(7);
+        parsed.push_conditions(&mut qb, &columns, &types).unwrap();
+        parsed.push_sorts(&mut qb, &columns);
+        parsed.push_limit_offset(&mut qb);
+
+        assert_eq!(
+            qb.sql(),
+            "SELECT * FROM orders WHERE tenant_id = $1 AND user_id = $2 AND price >= $3 \
+            AND price <= $4 AND name LIKE $5 ESCAPE '\\' AND $6 = ANY(tags) \
+            ORDER BY price DESC, user_id ASC LIMIT $7 OFFSET $8"
+        );
+        let arguments = qb.build().take_arguments().unwrap().unwrap();
+        assert_eq!(arguments.len(), 8);
+
+        let mut qb = sqlx::QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE true");
+        let types = TypeMap::from([("userId", BindType::Uuid)]);
+        assert_eq!(
+            parsed.push_conditions(&mut qb, &columns, &types),
+            Err(BindError::InvalidValue {
+                field: "userId".to_owned(),
+                value: "1".to_owned(),
+                bind_type: BindType::Uuid,
+            })
+        );
+
+        let parsed = UrlQuery::new("filter[]=tags-overlaps-a,b", ["tags"]).unwrap();
+        let mut qb = sqlx::QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE true");
+        assert_eq!(
+            parsed.push_conditions(&mut qb, &columns, &TypeMap::new()),
+            Err(BindError::UnsupportedOperator {
+                field: "tags".to_owned(),
+                operator: Operator::OVERLAPS,
+            })
+        );
+    }
+}
@@ -0,0 +1,265 @@
+/// The case to convert field names to when rendering them as columns.
+///
+/// Names are split into words on `_`, `-` and spaces, on lowercase to uppercase transitions
+/// (`userId`), before the last letter of an acronym (`HTTPServer`) and between letters and
+/// digits (`address2Line`). This matches the default behaviour of `convert_case` for ASCII
+/// identifiers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameCase {
+    /// `user_id`
+    Snake,
+    /// `userId`
+    Camel,
+    /// `UserId`
+    Pascal,
+    /// `user-id`
+    Kebab,
+    /// Leave names unchanged.
+    None,
+}
+
+impl NameCase {
+    /// Converts a name to this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::case::NameCase;
+    ///
+    /// assert_eq!(NameCase::Snake.convert("createdAt"), "created_at");
+    /// assert_eq!(NameCase::Camel.convert("created_at"), "createdAt");
+    /// ```
+    pub fn convert(&self, name: &str) -> String {
+        let words = split_words(name);
+
+        match self {
+            NameCase::Snake => join_lower(&words, "_"),
+            NameCase::Kebab => join_lower(&words, "-"),
+            NameCase::Camel => {
+                let mut converted = String::new();
+                for (i, word) in words.iter().enumerate() {
+                    match i {
+                        0 => converted.push_str(&word.to_lowercase()),
+                        _ => push_capitalized(&mut converted, word),
+                    }
+                }
+                converted
+            }
+            NameCase::Pascal => {
+                let mut converted = String::new();
+                for word in words.iter() {
+                    push_capitalized(&mut converted, word);
+                }
+                converted
+            }
+            NameCase::None => name.to_owned(),
+        }
+    }
+}
+
+/// A `convert_case::Case` without an equivalent `NameCase`, eg `Case::Title`.
+#[cfg(feature = "legacy")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsupportedCase(pub convert_case::Case);
+
+#[cfg(feature = "legacy")]
+impl std::fmt::Display for UnsupportedCase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "case {:?} has no NameCase equivalent", self.0)
+    }
+}
+
+#[cfg(feature = "legacy")]
+impl std::error::Error for UnsupportedCase {}
+
+/// Fails with `UnsupportedCase` for the cases other than snake, camel, pascal and kebab case.
+#[cfg(feature = "legacy")]
+impl TryFrom<convert_case::Case> for NameCase {
+    type Error = UnsupportedCase;
+
+    fn try_from(case: convert_case::Case) -> Result<Self, Self::Error> {
+        use convert_case::Case;
+
+        match case {
+            Case::Snake => Ok(NameCase::Snake),
+            Case::Camel => Ok(NameCase::Camel),
+            Case::Pascal | Case::UpperCamel => Ok(NameCase::Pascal),
+            Case::Kebab => Ok(NameCase::Kebab),
+            case => Err(UnsupportedCase(case)),
+        }
+    }
+}
+
+fn split_words(name: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = name.char_indices().collect();
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    for (i, &(idx, c)) in chars.iter().enumerate() {
+        if matches!(c, '_' | '-' | ' ') {
+            words.push(&name[start..idx]);
+            start = idx + c.len_utf8();
+            continue;
+        }
+
+        let prev = match i {
+            0 => None,
+            _ => Some(chars[i - 1].1),
+        };
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+
+        let boundary = match prev {
+            Some(prev) if idx != start => {
+                (prev.is_lowercase() && c.is_uppercase())
+                    || (prev.is_uppercase()
+                        && c.is_uppercase()
+                        && next.is_some_and(|next| next.is_lowercase()))
+                    || (prev.is_alphabetic() && c.is_ascii_digit())
+                    || (prev.is_ascii_digit() && c.is_alphabetic())
+            }
+            _ => false,
+        };
+
+        if boundary {
+            words.push(&name[start..idx]);
+            start = idx;
+        }
+    }
+    words.push(&name[start..]);
+
+    words.retain(|word| !word.is_empty());
+    words
+}
+
+fn join_lower(words: &[&str], separator: &str) -> String {
+    words
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn push_capitalized(converted: &mut String, word: &str) {
+    let mut chars = word.chars();
+    if let Some(first) = chars.next() {
+        converted.extend(first.to_uppercase());
+        converted.push_str(&chars.as_str().to_lowercase());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::NameCase;
+
+    // Recorded from convert_case 0.6: (name, snake, camel, pascal, kebab)
+    const EXPECTED: [(&str, &str, &str, &str, &str); 21] = [
+        ("userId", "user_id", "userId", "UserId", "user-id"),
+        ("user_id", "user_id", "userId", "UserId", "user-id"),
+        ("orderId", "order_id", "orderId", "OrderId", "order-id"),
+        (
+            "createdAt",
+            "created_at",
+            "createdAt",
+            "CreatedAt",
+            "created-at",
+        ),
+        ("price", "price", "price", "Price", "price"),
+        ("Price", "price", "price", "Price", "price"),
+        ("UserID", "user_id", "userId", "UserId", "user-id"),
+        (
+            "HTTPServer",
+            "http_server",
+            "httpServer",
+            "HttpServer",
+            "http-server",
+        ),
+        (
+            "address2Line",
+            "address_2_line",
+            "address2Line",
+            "Address2Line",
+            "address-2-line",
+        ),
+        ("line2", "line_2", "line2", "Line2", "line-2"),
+        ("v2Api", "v_2_api", "v2Api", "V2Api", "v-2-api"),
+        (
+            "user-name",
+            "user_name",
+            "userName",
+            "UserName",
+            "user-name",
+        ),
+        (
+            "user name",
+            "user_name",
+            "userName",
+            "UserName",
+            "user-name",
+        ),
+        ("ID", "id", "id", "Id", "id"),
+        ("userIDs", "user_i_ds", "userIDs", "UserIDs", "user-i-ds"),
+        ("_private", "private", "private", "Private", "private"),
+        ("a__b", "a_b", "aB", "AB", "a-b"),
+        (
+            "already_snake_case",
+            "already_snake_case",
+            "alreadySnakeCase",
+            "AlreadySnakeCase",
+            "already-snake-case",
+        ),
+        (
+            "XMLHttpRequest",
+            "xml_http_request",
+            "xmlHttpRequest",
+            "XmlHttpRequest",
+            "xml-http-request",
+        ),
+        ("x1y2", "x_1_y_2", "x1Y2", "X1Y2", "x-1-y-2"),
+        (
+            "ABC123def",
+            "abc_123_def",
+            "abc123Def",
+            "Abc123Def",
+            "abc-123-def",
+        ),
+    ];
+
+    #[test]
+    fn test_convert() {
+        for (name, snake, camel, pascal, kebab) in EXPECTED {
+            assert_eq!(NameCase::Snake.convert(name), snake, "{}", name);
+            assert_eq!(NameCase::Camel.convert(name), camel, "{}", name);
+            assert_eq!(NameCase::Pascal.convert(name), pascal, "{}", name);
+            assert_eq!(NameCase::Kebab.convert(name), kebab, "{}", name);
+            assert_eq!(NameCase::None.convert(name), name);
+        }
+    }
+
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn test_convert_matches_convert_case() {
+        use convert_case::{Case, Casing};
+
+        for (name, ..) in EXPECTED {
+            for case in [Case::Snake, Case::Camel, Case::Pascal, Case::Kebab] {
+                let converted = NameCase::try_from(case).unwrap().convert(name);
+                assert_eq!(converted, name.to_case(case));
+            }
+        }
+    }
+
+    #[cfg(feature = "legacy")]
+    #[test]
+    fn test_unsupported_case() {
+        use super::UnsupportedCase;
+        use convert_case::Case;
+
+        for case in [Case::Title, Case::Upper, Case::ScreamingSnake, Case::Flat] {
+            assert_eq!(NameCase::try_from(case), Err(UnsupportedCase(case)));
+        }
+        assert_eq!(
+            UnsupportedCase(Case::Title).to_string(),
+            "case Title has no NameCase equivalent"
+        );
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::BTreeMap;
+
+const CLAUSES: [&str; 6] = ["WHERE", "GROUP BY", "HAVING", "ORDER BY", "LIMIT", "OFFSET"];
+
+/// How the SQL rendered for a golden query changed.
+#[derive(Debug, PartialEq)]
+pub enum ChangeKind {
+    /// Only whitespace differs.
+    WhitespaceOnly,
+    /// The same clauses are present, but at least one of them changed.
+    Structural,
+    /// Clauses were added and/or removed, eg a new `LIMIT`.
+    Clauses {
+        added: Vec<&'static str>,
+        removed: Vec<&'static str>,
+    },
+    /// The query is no longer rendered.
+    Removed,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct SqlChange {
+    /// The key of the golden query, eg `postgres:userId=1&sort=id-desc`.
+    pub key: String,
+    pub kind: ChangeKind,
+    pub old: String,
+    pub new: Option<String>,
+}
+
+/// Renders every key with the current version of the crate, producing a golden corpus to store
+/// before upgrading. Keys are typically a dialect plus a url query, and `render` builds the SQL
+/// for a key the same way the application does.
+///
+/// # Examples
+///
+/// ```
+/// use query::{compat, sql::QueryBuilder, UrlQuery};
+///
+/// let render = |key: &str| {
+///     let parsed = UrlQuery::new(key, ["userId"]).ok()?;
+///     Some(QueryBuilder::from_str("SELECT * FROM orders", parsed).build().0)
+/// };
+///
+/// let golden = compat::export(["userId=1", "userId=1&limit=10"], render);
+///
+/// assert!(compat::diff(&golden, render).is_empty());
+/// ```
+pub fn export<'a>(
+    keys: impl IntoIterator<Item = &'a str>,
+    render: impl Fn(&str) -> Option<String>,
+) -> BTreeMap<String, String> {
+    keys.into_iter()
+        .filter_map(|key| render(key).map(|sql| (key.to_owned(), sql)))
+        .collect()
+}
+
+/// Re-renders a golden corpus created by `export` and reports the keys whose SQL changed, in key
+/// order.
+pub fn diff(
+    old_outputs: &BTreeMap<String, String>,
+    render: impl Fn(&str) -> Option<String>,
+) -> Vec<SqlChange> {
+    let mut changes = Vec::new();
+
+    for (key, old) in old_outputs {
+        let new = render(key);
+        let kind = match new {
+            Some(ref new) => match classify(old, new) {
+                Some(kind) => kind,
+                None => continue,
+            },
+            None => ChangeKind::Removed,
+        };
+
+        changes.push(SqlChange {
+            key: key.to_owned(),
+            kind,
+            old: old.to_owned(),
+            new,
+        });
+    }
+
+    changes
+}
+
+/// Classifies the change between two SQL statements, or returns `None` if they are identical.
+pub fn classify(old: &str, new: &str) -> Option<ChangeKind> {
+    if old == new {
+        return None;
+    }
+
+    let (old, new) = (normalize(old), normalize(new));
+    if old == new {
+        return Some(ChangeKind::WhitespaceOnly);
+    }
+
+    let (old_clauses, new_clauses) = (clauses(&old), clauses(&new));
+    let added: Vec<_> = new_clauses
+        .iter()
+        .filter(|c| !old_clauses.contains(c))
+        .copied()
+        .collect();
+    let removed: Vec<_> = old_clauses
+        .iter()
+        .filter(|c| !new_clauses.contains(c))
+        .copied()
+        .collect();
+
+    if added.is_empty() && removed.is_empty() {
+        return Some(ChangeKind::Structural);
+    }
+
+    Some(ChangeKind::Clauses { added, removed })
+}
+
+/// Collapses runs of whitespace into single spaces.
+fn normalize(sql: &str) -> String {
+    sql.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Returns the clauses present in normalized SQL, outside of parentheses and quotes.
+fn clauses(sql: &str) -> Vec<&'static str> {
+    let mut found = Vec::new();
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, b) in sql.bytes().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"' | b'`') => quote = Some(b),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth -= 1,
+            (None, b' ') if depth == 0 => {
+                for clause in CLAUSES {
+                    let rest = &sql[i + 1..];
+                    let matches = rest
+                        .get(..clause.len())
+                        .is_some_and(|word| word.eq_ignore_ascii_case(clause));
+                    let boundary = rest.as_bytes().get(clause.len()).is_none_or(|b| *b == b' ');
+                    if matches && boundary && !found.contains(&clause) {
+                        found.push(clause);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use super::{classify, diff, ChangeKind, SqlChange};
+
+    #[test]
+    fn test_classify() {
+        let old = "SELECT * FROM orders WHERE id = $1 ORDER BY id DESC";
+
+        assert_eq!(classify(old, old), None);
+        assert_eq!(
+            classify(
+                old,
+                "SELECT *  FROM orders\n WHERE id = $1 ORDER BY id DESC"
+            ),
+            Some(ChangeKind::WhitespaceOnly)
+        );
+        assert_eq!(
+            classify(
+                old,
+                "SELECT * FROM orders WHERE orders.id = $1 ORDER BY id DESC"
+            ),
+            Some(ChangeKind::Structural)
+        );
+        assert_eq!(
+            classify(old, "SELECT * FROM orders WHERE id = $1 LIMIT 10"),
+            Some(ChangeKind::Clauses {
+                added: vec!["LIMIT"],
+                removed: vec!["ORDER BY"],
+            })
+        );
+        assert_eq!(
+            classify(
+                "SELECT (SELECT 1 FROM a LIMIT 1) FROM orders",
+                "SELECT (SELECT 1 FROM a LIMIT 2) FROM orders"
+            ),
+            Some(ChangeKind::Structural)
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = BTreeMap::from([
+            ("a".to_owned(), "SELECT * FROM orders".to_owned()),
+            (
+                "b".to_owned(),
+                "SELECT * FROM orders WHERE id = $1".to_owned(),
+            ),
+            ("c".to_owned(), "SELECT * FROM users".to_owned()),
+        ]);
+
+        let render = |key: &str| match key {
+            "a" => Some("SELECT * FROM orders".to_owned()),
+            "b" => Some("SELECT * FROM orders WHERE id = ?".to_owned()),
+            _ => None,
+        };
+
+        assert_eq!(
+            diff(&old, render),
+            vec![
+                SqlChange {
+                    key: "b".into(),
+                    kind: ChangeKind::Structural,
+                    old: "SELECT * FROM orders WHERE id = $1".into(),
+                    new: Some("SELECT * FROM orders WHERE id = ?".into()),
+                },
+                SqlChange {
+                    key: "c".into(),
+                    kind: ChangeKind::Removed,
+                    old: "SELECT * FROM users".into(),
+                    new: None,
+                },
+            ]
+        );
+    }
+}
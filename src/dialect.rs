@@ -0,0 +1,571 @@
+use std::time::Duration;
+
+use crate::{
+    sort::{Nulls, SortBy},
+    sql::{Database, LockMode},
+};
+
+/// How SQL is rendered for a database. `Database` implements it for the built-in databases,
+/// implement it to support another one and pass it to `QueryBuilder::set_dialect`.
+///
+/// # Examples
+///
+/// ```
+/// use query::{dialect::Dialect, sql::QueryBuilder, UrlQuery};
+///
+/// struct ClickHouse;
+///
+/// impl Dialect for ClickHouse {
+///     fn placeholder(&self, _index: usize) -> String {
+///         "?".to_owned()
+///     }
+///
+///     fn quote_ident(&self, ident: &str) -> String {
+///         format!("`{}`", ident)
+///     }
+/// }
+///
+/// let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+///
+/// let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+///     .set_dialect(Box::new(ClickHouse))
+///     .build();
+///
+/// assert_eq!(sql, "SELECT * FROM orders WHERE userId = ? LIMIT 10");
+/// ```
+pub trait Dialect {
+    /// Returns the placeholder for the bind parameter with the given (1-based) index.
+    fn placeholder(&self, index: usize) -> String;
+
+    /// Returns the prefix of numbered placeholders, eg `$` for `$1`, or `None` if the
+    /// placeholders are the same for every index, like `?`. Used to find the placeholders in the
+    /// built SQL, eg for `QueryBuilder::placeholder_style`.
+    fn placeholder_prefix(&self) -> Option<&str> {
+        None
+    }
+
+    /// Whether a numbered placeholder can be used more than once, with the same type each time,
+    /// see `QueryBuilder::dedupe_binds`.
+    fn supports_shared_placeholders(&self) -> bool {
+        false
+    }
+
+    /// Returns the quoted identifier, eg `"users"`. Dotted identifiers are quoted part by part.
+    fn quote_ident(&self, ident: &str) -> String;
+
+    /// Returns the pagination clause, with a leading space. `limit` and `offset` are either
+    /// literals or placeholders. The offset is only passed without a limit if
+    /// `supports_offset_without_limit` returns true.
+    fn render_limit_offset(&self, limit: Option<&str>, offset: Option<&str>) -> String {
+        limit_offset(limit, offset)
+    }
+
+    /// Whether `render_limit_offset` renders the offset before the limit, so bind parameters are
+    /// numbered in that order.
+    fn offset_before_limit(&self) -> bool {
+        false
+    }
+
+    /// Whether an offset is rendered when no limit is applied.
+    fn supports_offset_without_limit(&self) -> bool {
+        false
+    }
+
+    /// Whether pagination is only allowed after an ORDER BY. Without a sort, `ORDER BY (SELECT
+    /// NULL)` is rendered before the pagination.
+    fn pagination_requires_order_by(&self) -> bool {
+        false
+    }
+
+    /// Whether the database has a case-insensitive `ILIKE`.
+    fn supports_ilike(&self) -> bool {
+        false
+    }
+
+    /// Returns the ORDER BY term sorting by `expression`, eg `price DESC NULLS LAST`.
+    fn render_sort(&self, expression: &str, sort_by: SortBy, nulls: Option<Nulls>) -> String {
+        let mut sort = String::from(expression);
+        sort.push(' ');
+        sort.push_str(sort_by.as_str());
+        if let Some(nulls) = nulls {
+            sort.push(' ');
+            sort.push_str(nulls.as_str());
+        }
+
+        sort
+    }
+
+    /// Returns the value as a string literal, eg `'O''Brien'`. Only used by
+    /// `QueryBuilder::debug_sql`.
+    fn quote_literal(&self, value: &str) -> String {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+
+    /// Returns the text at `path` in the JSON `column`, eg `metadata->'size'->>'width'`. The
+    /// path is made of identifiers, see `QueryBuilder::json_column`.
+    fn json_path(&self, column: &str, path: &[&str]) -> String {
+        postgres_json_path(column, path)
+    }
+
+    /// Returns the expression cast to a number, to compare the text of a JSON path numerically.
+    fn cast_numeric(&self, expression: &str) -> String {
+        format!("CAST({} AS NUMERIC)", expression)
+    }
+
+    /// Returns the condition that the text of `columns` matches the search bound to
+    /// `placeholder`, see `QueryBuilder::full_text_search`.
+    fn full_text_search(&self, columns: &[String], config: &str, placeholder: &str) -> String {
+        postgres_full_text_search(columns, config, placeholder)
+    }
+
+    /// Returns the rank of a row matching the search bound to `placeholder`, to sort by
+    /// relevance, or `None` if the database can't rank without binding the search again.
+    fn full_text_rank(
+        &self,
+        columns: &[String],
+        config: &str,
+        placeholder: &str,
+    ) -> Option<String> {
+        Some(postgres_full_text_rank(columns, config, placeholder))
+    }
+
+    /// Returns the condition that `expression` matches the LIKE pattern bound to `placeholder`,
+    /// with a backslash escaping the wildcards, see `filter::escape_like`.
+    fn render_like(&self, expression: &str, placeholder: &str) -> String {
+        format!("{} LIKE {} ESCAPE '\\'", expression, placeholder)
+    }
+
+    /// Whether the database has array columns, for `Operator::HAS` and `Operator::OVERLAPS`.
+    fn supports_arrays(&self) -> bool {
+        false
+    }
+
+    /// Whether the database has a full-text search, see `full_text_search`.
+    fn supports_full_text_search(&self) -> bool {
+        true
+    }
+
+    /// Returns the condition that the JSON `column` contains the JSON bound to `placeholder`,
+    /// see `Operator::CONT`.
+    fn json_contains(&self, column: &str, placeholder: &str) -> String {
+        format!("{} @> {}::jsonb", column, placeholder)
+    }
+
+    /// Whether the database has a JSON containment, see `json_contains`.
+    fn supports_json_contains(&self) -> bool {
+        true
+    }
+
+    /// Whether rows can be compared as a whole, eg `(created_at, id) > ($1, $2)`, for cursors
+    /// sorted in a single direction. Otherwise each key is compared in turn.
+    fn supports_row_values(&self) -> bool {
+        false
+    }
+
+    /// Returns the alias of a subquery, with a leading space, eg ` AS count_query`.
+    fn render_subquery_alias(&self, alias: &str) -> String {
+        format!(" AS {}", alias)
+    }
+
+    /// Returns the row-locking clause, or `None` if the database doesn't lock rows that way.
+    /// `legacy_mysql` is set by `QueryBuilder::legacy_mysql`.
+    fn render_lock(&self, lock: LockMode, legacy_mysql: bool) -> Option<String> {
+        Some(lock.to_sql(legacy_mysql).to_owned())
+    }
+
+    /// Returns the statement to execute before the query to apply the timeout, see
+    /// `QueryBuilder::timeout_statement`.
+    fn timeout_statement(&self, _timeout: Duration) -> Option<String> {
+        None
+    }
+
+    /// Returns the optimizer hint inserted after SELECT to apply the timeout, eg
+    /// `/*+ MAX_EXECUTION_TIME(500) */`.
+    fn timeout_hint(&self, _timeout: Duration) -> Option<String> {
+        None
+    }
+
+    /// Whether DELETE and UPDATE accept ORDER BY and LIMIT, see `QueryBuilder::delete`.
+    fn supports_mutation_limit(&self) -> bool {
+        false
+    }
+}
+
+impl Dialect for Database {
+    fn placeholder(&self, index: usize) -> String {
+        match self {
+            Database::Postgres => format!("${}", index),
+            Database::Mssql => format!("@p{}", index),
+            Database::Oracle => format!(":{}", index),
+            Database::MySQL | Database::Sqlite => "?".to_owned(),
+        }
+    }
+
+    fn placeholder_prefix(&self) -> Option<&str> {
+        match self {
+            Database::Postgres => Some("$"),
+            Database::Mssql => Some("@p"),
+            Database::Oracle => Some(":"),
+            Database::MySQL | Database::Sqlite => None,
+        }
+    }
+
+    fn supports_shared_placeholders(&self) -> bool {
+        matches!(self, Database::Postgres)
+    }
+
+    fn quote_ident(&self, ident: &str) -> String {
+        match self {
+            Database::Postgres | Database::Sqlite | Database::Oracle => format!("\"{}\"", ident),
+            Database::MySQL => format!("`{}`", ident),
+            Database::Mssql => format!("[{}]", ident),
+        }
+    }
+
+    /// SQL Server and Oracle render `OFFSET n ROWS FETCH NEXT m ROWS ONLY`, and SQLite renders
+    /// `LIMIT -1` for an offset without a limit.
+    fn render_limit_offset(&self, limit: Option<&str>, offset: Option<&str>) -> String {
+        match self {
+            Database::Mssql | Database::Oracle => {
+                let mut sql = format!(" OFFSET {} ROWS", offset.unwrap_or("0"));
+                if let Some(limit) = limit {
+                    sql.push_str(" FETCH NEXT ");
+                    sql.push_str(limit);
+                    sql.push_str(" ROWS ONLY");
+                }
+
+                sql
+            }
+            // SQLite needs a LIMIT before an OFFSET, -1 is no limit.
+            Database::Sqlite if limit.is_none() && offset.is_some() => {
+                format!(" LIMIT -1 OFFSET {}", offset.unwrap_or_default())
+            }
+            _ => limit_offset(limit, offset),
+        }
+    }
+
+    fn offset_before_limit(&self) -> bool {
+        matches!(self, Database::Mssql | Database::Oracle)
+    }
+
+    fn supports_offset_without_limit(&self) -> bool {
+        matches!(self, Database::Sqlite | Database::Mssql | Database::Oracle)
+    }
+
+    fn pagination_requires_order_by(&self) -> bool {
+        matches!(self, Database::Mssql)
+    }
+
+    fn supports_ilike(&self) -> bool {
+        matches!(self, Database::Postgres)
+    }
+
+    fn supports_arrays(&self) -> bool {
+        matches!(self, Database::Postgres)
+    }
+
+    fn supports_full_text_search(&self) -> bool {
+        matches!(self, Database::Postgres | Database::MySQL)
+    }
+
+    /// MySQL treats backslashes in string literals as escapes, so the escape character is
+    /// written `'\\'` there.
+    fn render_like(&self, expression: &str, placeholder: &str) -> String {
+        match self {
+            Database::MySQL => format!("{} LIKE {} ESCAPE '\\\\'", expression, placeholder),
+            _ => format!("{} LIKE {} ESCAPE '\\'", expression, placeholder),
+        }
+    }
+
+    /// MySQL has no NULLS FIRST/LAST, so there the nulls ordering is emulated with a leading
+    /// `IS NULL` key, eg `shipped_at IS NULL, shipped_at DESC` for NULLS LAST. SQL Server has no
+    /// boolean expressions either, so it gets a `CASE` instead.
+    fn render_sort(&self, expression: &str, sort_by: SortBy, nulls: Option<Nulls>) -> String {
+        let sort_by = sort_by.as_str();
+        match (self, nulls) {
+            (Database::MySQL, Some(nulls)) => {
+                let is_null = match nulls {
+                    Nulls::First => "IS NOT NULL",
+                    Nulls::Last => "IS NULL",
+                };
+                format!("{} {}, {} {}", expression, is_null, expression, sort_by)
+            }
+            (Database::Mssql, Some(nulls)) => {
+                let (null, not_null) = match nulls {
+                    Nulls::First => (0, 1),
+                    Nulls::Last => (1, 0),
+                };
+                format!(
+                    "CASE WHEN {} IS NULL THEN {} ELSE {} END, {} {}",
+                    expression, null, not_null, expression, sort_by
+                )
+            }
+            (_, Some(nulls)) => format!("{} {} {}", expression, sort_by, nulls.as_str()),
+            (_, None) => format!("{} {}", expression, sort_by),
+        }
+    }
+
+    /// MySQL treats backslashes in string literals as escapes, and SQL Server needs `N'...'` for
+    /// non-ASCII text.
+    fn quote_literal(&self, value: &str) -> String {
+        let value = value.replace('\'', "''");
+        match self {
+            Database::MySQL => format!("'{}'", value.replace('\\', "\\\\")),
+            Database::Mssql => format!("N'{}'", value),
+            Database::Postgres | Database::Sqlite | Database::Oracle => format!("'{}'", value),
+        }
+    }
+
+    /// MySQL renders `metadata->>'$.size.width'`, SQLite `json_extract(...)` and SQL Server and
+    /// Oracle `JSON_VALUE(...)`, with array indexes in brackets, eg `tags->>'$[0]'`.
+    fn json_path(&self, column: &str, path: &[&str]) -> String {
+        let json_path = || sql_json_path(path);
+        match self {
+            Database::Postgres => postgres_json_path(column, path),
+            Database::MySQL => format!("{}->>{}", column, json_path()),
+            Database::Sqlite => format!("json_extract({}, {})", column, json_path()),
+            Database::Mssql | Database::Oracle => {
+                format!("JSON_VALUE({}, {})", column, json_path())
+            }
+        }
+    }
+
+    /// SQLite compares the numbers of `json_extract` as is.
+    fn cast_numeric(&self, expression: &str) -> String {
+        match self {
+            Database::Postgres => format!("({})::numeric", expression),
+            Database::MySQL => format!("CAST({} AS DECIMAL(65, 30))", expression),
+            Database::Sqlite => expression.to_owned(),
+            Database::Mssql => format!("CAST({} AS FLOAT)", expression),
+            Database::Oracle => format!("TO_NUMBER({})", expression),
+        }
+    }
+
+    /// MySQL renders `MATCH(title, description) AGAINST (? IN NATURAL LANGUAGE MODE)`, which
+    /// needs a FULLTEXT index on the columns. The other databases have no full-text search, see
+    /// `supports_full_text_search`.
+    fn full_text_search(&self, columns: &[String], config: &str, placeholder: &str) -> String {
+        match self {
+            Database::MySQL => format!(
+                "MATCH({}) AGAINST ({} IN NATURAL LANGUAGE MODE)",
+                columns.join(", "),
+                placeholder
+            ),
+            _ => postgres_full_text_search(columns, config, placeholder),
+        }
+    }
+
+    /// MySQL can't reuse the `?` of the search, so it doesn't rank.
+    fn full_text_rank(
+        &self,
+        columns: &[String],
+        config: &str,
+        placeholder: &str,
+    ) -> Option<String> {
+        match self {
+            Database::MySQL => None,
+            _ => Some(postgres_full_text_rank(columns, config, placeholder)),
+        }
+    }
+
+    /// MySQL renders `JSON_CONTAINS(column, ?)`. The other databases have no containment
+    /// operator, see `supports_json_contains`.
+    fn json_contains(&self, column: &str, placeholder: &str) -> String {
+        match self {
+            Database::MySQL => format!("JSON_CONTAINS({}, {})", column, placeholder),
+            _ => format!("{} @> {}::jsonb", column, placeholder),
+        }
+    }
+
+    fn supports_json_contains(&self) -> bool {
+        matches!(self, Database::Postgres | Database::MySQL)
+    }
+
+    fn supports_row_values(&self) -> bool {
+        matches!(self, Database::Postgres)
+    }
+
+    /// Oracle doesn't accept AS before a table alias.
+    fn render_subquery_alias(&self, alias: &str) -> String {
+        match self {
+            Database::Oracle => format!(" {}", alias),
+            _ => format!(" AS {}", alias),
+        }
+    }
+
+    /// SQLite locks the whole database instead and SQL Server locks with table hints, so they
+    /// have no clause. Oracle has no FOR SHARE, readers don't block writers there anyway.
+    fn render_lock(&self, lock: LockMode, legacy_mysql: bool) -> Option<String> {
+        match (self, lock) {
+            (Database::Sqlite | Database::Mssql, _) => None,
+            (Database::Oracle, LockMode::ForShare { .. }) => None,
+            (Database::MySQL, _) => Some(lock.to_sql(legacy_mysql).to_owned()),
+            _ => Some(lock.to_sql(false).to_owned()),
+        }
+    }
+
+    /// Postgres renders `SET LOCAL statement_timeout = <ms>`.
+    fn timeout_statement(&self, timeout: Duration) -> Option<String> {
+        match self {
+            Database::Postgres => Some(format!(
+                "SET LOCAL statement_timeout = {}",
+                timeout.as_millis()
+            )),
+            _ => None,
+        }
+    }
+
+    /// MySQL renders `/*+ MAX_EXECUTION_TIME(<ms>) */`.
+    fn timeout_hint(&self, timeout: Duration) -> Option<String> {
+        match self {
+            Database::MySQL => Some(format!(
+                "/*+ MAX_EXECUTION_TIME({}) */",
+                timeout.as_millis()
+            )),
+            _ => None,
+        }
+    }
+
+    fn supports_mutation_limit(&self) -> bool {
+        matches!(self, Database::MySQL)
+    }
+}
+
+/// Returns `document @@ plainto_tsquery('english', $1)`, see `ts_document`.
+fn postgres_full_text_search(columns: &[String], config: &str, placeholder: &str) -> String {
+    let config = format!("'{}'", config.replace('\'', "''"));
+
+    format!(
+        "{} @@ plainto_tsquery({}, {})",
+        ts_document(columns, &config),
+        config,
+        placeholder
+    )
+}
+
+/// Returns `ts_rank(document, plainto_tsquery('english', $1))`, see `ts_document`.
+fn postgres_full_text_rank(columns: &[String], config: &str, placeholder: &str) -> String {
+    let config = format!("'{}'", config.replace('\'', "''"));
+
+    format!(
+        "ts_rank({}, plainto_tsquery({}, {}))",
+        ts_document(columns, &config),
+        config,
+        placeholder
+    )
+}
+
+/// Returns `to_tsvector('english', coalesce(title, '') || ' ' || coalesce(description, ''))`, the
+/// `config` being quoted.
+fn ts_document(columns: &[String], config: &str) -> String {
+    let columns: Vec<String> = columns
+        .iter()
+        .map(|column| format!("coalesce({}, '')", column))
+        .collect();
+
+    format!("to_tsvector({}, {})", config, columns.join(" || ' ' || "))
+}
+
+/// Returns `column->'a'->>'b'`, leaving array indexes unquoted, eg `tags->>0`.
+fn postgres_json_path(column: &str, path: &[&str]) -> String {
+    let mut sql = String::from(column);
+    for (i, segment) in path.iter().enumerate() {
+        match i + 1 == path.len() {
+            true => sql.push_str("->>"),
+            false => sql.push_str("->"),
+        }
+        match segment.chars().all(|c| c.is_ascii_digit()) {
+            true => sql.push_str(segment),
+            false => sql.push_str(&format!("'{}'", segment)),
+        }
+    }
+
+    sql
+}
+
+/// Returns the SQL/JSON path literal, eg `'$.items[0].sku'`.
+fn sql_json_path(path: &[&str]) -> String {
+    let mut sql = String::from("'$");
+    for segment in path.iter() {
+        match segment.chars().all(|c| c.is_ascii_digit()) {
+            true => sql.push_str(&format!("[{}]", segment)),
+            false => sql.push_str(&format!(".{}", segment)),
+        }
+    }
+    sql.push('\'');
+
+    sql
+}
+
+/// Returns ` LIMIT limit OFFSET offset`, leaving out what is `None`.
+fn limit_offset(limit: Option<&str>, offset: Option<&str>) -> String {
+    let mut sql = String::new();
+    if let Some(limit) = limit {
+        sql.push_str(" LIMIT ");
+        sql.push_str(limit);
+    }
+    if let Some(offset) = offset {
+        sql.push_str(" OFFSET ");
+        sql.push_str(offset);
+    }
+
+    sql
+}
+
+#[cfg(test)]
+mod test {
+    use super::Dialect;
+    use crate::sql::Database;
+
+    #[test]
+    fn test_render_limit_offset() {
+        let cases = [
+            (
+                Database::Postgres,
+                Some("10"),
+                Some("20"),
+                " LIMIT 10 OFFSET 20",
+            ),
+            (Database::MySQL, Some("?"), None, " LIMIT ?"),
+            (Database::Sqlite, None, Some("20"), " LIMIT -1 OFFSET 20"),
+            (
+                Database::Mssql,
+                Some("10"),
+                None,
+                " OFFSET 0 ROWS FETCH NEXT 10 ROWS ONLY",
+            ),
+            (Database::Oracle, None, Some(":3"), " OFFSET :3 ROWS"),
+        ];
+
+        for (database, limit, offset, expected) in cases {
+            assert_eq!(
+                database.render_limit_offset(limit, offset),
+                expected,
+                "{:?}",
+                database
+            );
+        }
+    }
+
+    #[test]
+    fn test_json_path() {
+        let cases = [
+            (Database::Postgres, "metadata->'size'->>'width'"),
+            (Database::MySQL, "metadata->>'$.size.width'"),
+            (Database::Sqlite, "json_extract(metadata, '$.size.width')"),
+            (Database::Mssql, "JSON_VALUE(metadata, '$.size.width')"),
+        ];
+
+        for (database, expected) in cases {
+            let path = database.json_path("metadata", &["size", "width"]);
+            assert_eq!(path, expected, "{:?}", database);
+        }
+
+        assert_eq!(Database::Postgres.json_path("tags", &["0"]), "tags->>0");
+        assert_eq!(Database::MySQL.json_path("tags", &["0"]), "tags->>'$[0]'");
+        assert_eq!(
+            Database::Sqlite.json_path("metadata", &["items", "0", "sku"]),
+            "json_extract(metadata, '$.items[0].sku')"
+        );
+    }
+}
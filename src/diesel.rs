@@ -0,0 +1,296 @@
+use std::str::FromStr;
+
+use diesel::{
+    backend::Backend,
+    dsl,
+    expression::{expression_types::NotSelectable, AsExpression, BoxableExpression},
+    query_dsl::methods::{FilterDsl, LimitDsl, OffsetDsl, ThenOrderDsl},
+    sql_types::{Bool, SqlType},
+    BoolExpressionMethods, ExpressionMethods,
+};
+
+use crate::{
+    filter::{Filter, Operator},
+    sort::SortBy,
+    sql::BuildError,
+    ParseError, UrlQuery,
+};
+
+/// A condition of a boxed query on the table `QS`, eg `users.id = ?`.
+pub type BoxedCondition<QS, DB> = Box<dyn BoxableExpression<QS, DB, SqlType = Bool>>;
+
+/// An ordering of a boxed query on the table `QS`, eg `users.name DESC`.
+pub type BoxedOrder<QS, DB> = Box<dyn BoxableExpression<QS, DB, SqlType = NotSelectable>>;
+
+type FilterFn<QS, DB> = dyn Fn(&Filter) -> Result<BoxedCondition<QS, DB>, BuildError>;
+
+/// The Diesel column of a field, with the Rust type its values are parsed as, see
+/// `DieselFields`. Fields can be filtered with `eq`, `ne`, `gt`, `ge`, `lt`, `le` and `range`, and
+/// sorted on.
+pub struct DieselColumn<QS, DB> {
+    filter: Box<FilterFn<QS, DB>>,
+    asc: BoxedOrder<QS, DB>,
+    desc: BoxedOrder<QS, DB>,
+}
+
+impl<QS, DB: Backend> DieselColumn<QS, DB> {
+    /// Declares the column of a field, parsing its values as `T`, eg
+    /// `DieselColumn::new::<_, i32>(users::id)`.
+    pub fn new<C, T>(column: C) -> Self
+    where
+        C: ExpressionMethods + Copy + 'static,
+        C::SqlType: SqlType,
+        T: FromStr + AsExpression<C::SqlType> + 'static,
+        dsl::Eq<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::NotEq<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::Gt<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::GtEq<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::Lt<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::LtEq<C, T>: BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+        dsl::Asc<C>: BoxableExpression<QS, DB, SqlType = NotSelectable> + 'static,
+        dsl::Desc<C>: BoxableExpression<QS, DB, SqlType = NotSelectable> + 'static,
+    {
+        let filter = move |filter: &Filter| -> Result<BoxedCondition<QS, DB>, BuildError> {
+            let value = || {
+                filter.value.parse::<T>().map_err(|_| {
+                    BuildError::Parse(ParseError::InvalidValue {
+                        field: filter.field.to_owned(),
+                        value: filter.value.to_owned(),
+                        raw: filter.raw.clone(),
+                    })
+                })
+            };
+
+            let condition: BoxedCondition<QS, DB> = match filter.operator {
+                Operator::EQ => Box::new(column.eq(value()?)),
+                Operator::NE => Box::new(column.ne(value()?)),
+                Operator::GT => Box::new(column.gt(value()?)),
+                Operator::GE => Box::new(column.ge(value()?)),
+                Operator::LT => Box::new(column.lt(value()?)),
+                Operator::LE => Box::new(column.le(value()?)),
+                operator => {
+                    return Err(BuildError::UnsupportedOperator {
+                        field: filter.field.to_owned(),
+                        operator,
+                    })
+                }
+            };
+
+            Ok(condition)
+        };
+
+        Self {
+            filter: Box::new(filter),
+            asc: Box::new(column.asc()),
+            desc: Box::new(column.desc()),
+        }
+    }
+}
+
+/// Declares the Diesel column of each field of a url query, once for its filters and its sorts.
+///
+/// # Examples
+///
+/// ```ignore
+/// use query::diesel::{DieselColumn, DieselFields};
+///
+/// struct UserFields;
+///
+/// impl DieselFields<users::table, Sqlite> for UserFields {
+///     fn column(&self, field: &str) -> Option<DieselColumn<users::table, Sqlite>> {
+///         match field {
+///             "id" => Some(DieselColumn::new::<_, i32>(users::id)),
+///             "name" => Some(DieselColumn::new::<_, String>(users::name)),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let parsed = UrlQuery::new("filter[]=id-ge-10&sort=name&limit=20", ["id", "name"])?;
+/// let query = query::diesel::apply(users::table.into_boxed(), &parsed, &UserFields)?;
+/// let users: Vec<User> = query.load(&mut conn)?;
+/// ```
+pub trait DieselFields<QS, DB: Backend> {
+    /// Returns the column of the field, or `None` if it can't be filtered or sorted on.
+    fn column(&self, field: &str) -> Option<DieselColumn<QS, DB>>;
+}
+
+/// Returns the filters of the url query ANDed into one boxed condition, or `None` without filters.
+/// Fails with `BuildError::UnmappedField` for a field without a column, `BuildError::Parse` for a
+/// value which isn't valid for its column, and `BuildError::UnsupportedOperator` for operators
+/// other than `eq`, `ne`, `gt`, `ge`, `lt`, `le` and `range`.
+pub fn condition<QS, DB>(
+    url_query: &UrlQuery,
+    fields: &impl DieselFields<QS, DB>,
+) -> Result<Option<BoxedCondition<QS, DB>>, BuildError>
+where
+    DB: Backend,
+    dsl::And<BoxedCondition<QS, DB>, BoxedCondition<QS, DB>>:
+        BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+{
+    let mut condition: Option<BoxedCondition<QS, DB>> = None;
+    for filter in url_query.filters.iter().flat_map(Filter::expand_range) {
+        let column = fields
+            .column(&filter.field)
+            .ok_or_else(|| BuildError::UnmappedField {
+                field: filter.field.to_owned(),
+                clause: "WHERE",
+            })?;
+        let next = (column.filter)(&filter)?;
+
+        condition = Some(match condition {
+            Some(condition) => Box::new(condition.and(next)),
+            None => next,
+        });
+    }
+
+    Ok(condition)
+}
+
+/// Returns the sorts of the url query as boxed orderings. Fails with `BuildError::UnmappedField`
+/// for a field without a column. `-nullsfirst` and `-nullslast` are ignored.
+pub fn orderings<QS, DB>(
+    url_query: &UrlQuery,
+    fields: &impl DieselFields<QS, DB>,
+) -> Result<Vec<BoxedOrder<QS, DB>>, BuildError>
+where
+    DB: Backend,
+{
+    let mut orderings = Vec::new();
+    for sort in &url_query.sorts {
+        let column = fields
+            .column(&sort.field)
+            .ok_or_else(|| BuildError::UnmappedField {
+                field: sort.field.to_owned(),
+                clause: "ORDER BY",
+            })?;
+
+        orderings.push(match sort.sort_by {
+            SortBy::ASC => column.asc,
+            SortBy::DESC => column.desc,
+        });
+    }
+
+    Ok(orderings)
+}
+
+/// Adds the filters, sorts, limit and offset of the url query to a boxed query, eg
+/// `users::table.into_boxed()`.
+pub fn apply<Q, QS, DB>(
+    mut query: Q,
+    url_query: &UrlQuery,
+    fields: &impl DieselFields<QS, DB>,
+) -> Result<Q, BuildError>
+where
+    DB: Backend,
+    Q: FilterDsl<BoxedCondition<QS, DB>, Output = Q>
+        + ThenOrderDsl<BoxedOrder<QS, DB>, Output = Q>
+        + LimitDsl<Output = Q>
+        + OffsetDsl<Output = Q>,
+    dsl::And<BoxedCondition<QS, DB>, BoxedCondition<QS, DB>>:
+        BoxableExpression<QS, DB, SqlType = Bool> + 'static,
+{
+    if let Some(condition) = condition(url_query, fields)? {
+        query = query.filter(condition);
+    }
+
+    for ordering in orderings(url_query, fields)? {
+        query = query.then_order_by(ordering);
+    }
+
+    let (limit, offset) = url_query.limit_offset;
+    if let Some(limit) = limit {
+        query = query.limit(i64::try_from(limit).unwrap_or(i64::MAX));
+    }
+    if let Some(offset) = offset {
+        query = query.offset(i64::try_from(offset).unwrap_or(i64::MAX));
+    }
+
+    Ok(query)
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::{debug_query, sqlite::Sqlite, QueryDsl};
+
+    use crate::{filter::Operator, sql::BuildError, ParseError, UrlQuery};
+
+    use super::{apply, DieselColumn, DieselFields};
+
+    diesel::table! {
+        users (id) {
+            id -> Integer,
+            name -> Text,
+            age -> Integer,
+        }
+    }
+
+    struct UserFields;
+
+    impl DieselFields<users::table, Sqlite> for UserFields {
+        fn column(&self, field: &str) -> Option<DieselColumn<users::table, Sqlite>> {
+            match field {
+                "id" => Some(DieselColumn::new::<_, i32>(users::id)),
+                "name" => Some(DieselColumn::new::<_, String>(users::name)),
+                "age" => Some(DieselColumn::new::<_, i32>(users::age)),
+                _ => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let query = "name=bob&filter[]=age-ge-18&filter[]=id-range-10..20\
+        &sort=age-desc,name&limit=10&offset=20";
+        let parsed = UrlQuery::new(query, ["id", "name", "age"]).unwrap();
+
+        let query = apply(users::table.into_boxed(), &parsed, &UserFields).unwrap();
+
+        assert_eq!(
+            debug_query::<Sqlite, _>(&query).to_string(),
+            "SELECT `users`.`id`, `users`.`name`, `users`.`age` FROM `users` \
+            WHERE ((((`users`.`name` = ?) AND (`users`.`age` >= ?)) AND (`users`.`id` >= ?)) \
+            AND (`users`.`id` <= ?)) \
+            ORDER BY `users`.`age` DESC, `users`.`name` ASC LIMIT ? OFFSET ? \
+            -- binds: [\"bob\", 18, 10, 20, 10, 20]"
+        );
+    }
+
+    #[test]
+    fn test_apply_errors() {
+        let apply = |query: &str| {
+            let parsed = UrlQuery::new(query, ["id", "email"]).unwrap();
+            apply(users::table.into_boxed(), &parsed, &UserFields).map(|_| ())
+        };
+
+        assert_eq!(
+            apply("email=a@b.c"),
+            Err(BuildError::UnmappedField {
+                field: "email".to_owned(),
+                clause: "WHERE"
+            })
+        );
+        assert_eq!(
+            apply("sort=email"),
+            Err(BuildError::UnmappedField {
+                field: "email".to_owned(),
+                clause: "ORDER BY"
+            })
+        );
+        assert_eq!(
+            apply("id=abc"),
+            Err(BuildError::Parse(ParseError::InvalidValue {
+                field: "id".to_owned(),
+                value: "abc".to_owned(),
+                raw: Some("id=abc".to_owned()),
+            }))
+        );
+        assert_eq!(
+            apply("filter[]=id-contains-1"),
+            Err(BuildError::UnsupportedOperator {
+                field: "id".to_owned(),
+                operator: Operator::CONTAINS
+            })
+        );
+    }
+}
@@ -1,89 +1,245 @@
 use std::str::FromStr;
 
-use convert_case::{Case, Casing};
+use crate::case::NameCase;
 
-use crate::{sql::Database, ParseError};
+use crate::{dialect::Dialect, ParseError};
 
-#[derive(Debug, PartialEq)]
-pub enum Condition {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
     EQ,
     NE,
     GT,
     GE,
     LT,
     LE,
+    /// `price-range-100..500`, between two bounds included, or from or up to a bound with
+    /// `100..` and `..500`. Compares with each bound, see `Filter::expand_range`.
+    RANGE,
+    /// `attributes-cont-{"size":"xl"}`, a JSON column containing the JSON value, eg
+    /// `attributes @> $1::jsonb` on Postgres. Only allowed on `Options::json_fields`.
+    CONT,
+    /// `tags-has-rust`, an array column containing the value: `$1 = ANY(tags)`. Postgres only,
+    /// on the fields passed to `QueryBuilder::array_column`.
+    HAS,
+    /// `tags-overlaps-rust,sql`, an array column sharing an element with the comma separated
+    /// list: `tags && $1`. The list is bound as one arg, as is, see `Filter::values`.
+    OVERLAPS,
+    /// `name-like-bo%`, a LIKE pattern with the wildcards of the client, see
+    /// `QueryBuilder::escape_like`.
+    LIKE,
+    /// `name-contains-bob`, matching `%bob%` with the wildcards of the value escaped, see
+    /// `escape_like`.
+    CONTAINS,
+    /// `name-startswith-bob`, matching `bob%`.
+    STARTSWITH,
+    /// `name-endswith-bob`, matching `%bob`.
+    ENDSWITH,
 }
 
-impl FromStr for Condition {
+impl FromStr for Operator {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "eq" => Ok(Condition::EQ),
-            "ne" => Ok(Condition::NE),
-            "gt" => Ok(Condition::GT),
-            "ge" => Ok(Condition::GE),
-            "lt" => Ok(Condition::LT),
-            "le" => Ok(Condition::LE),
-            _ => Err(ParseError::InvalidCondition),
+            "eq" => Ok(Operator::EQ),
+            "ne" => Ok(Operator::NE),
+            "gt" => Ok(Operator::GT),
+            "ge" => Ok(Operator::GE),
+            "lt" => Ok(Operator::LT),
+            "le" => Ok(Operator::LE),
+            "range" => Ok(Operator::RANGE),
+            "cont" => Ok(Operator::CONT),
+            "has" => Ok(Operator::HAS),
+            "overlaps" => Ok(Operator::OVERLAPS),
+            "like" => Ok(Operator::LIKE),
+            "contains" => Ok(Operator::CONTAINS),
+            "startswith" => Ok(Operator::STARTSWITH),
+            "endswith" => Ok(Operator::ENDSWITH),
+            _ => Err(ParseError::InvalidOperator),
         }
     }
 }
 
-impl Condition {
-    pub fn as_str(&self) -> &str {
+impl Operator {
+    /// Returns the operator as it appears in a `filter[]` query parameter.
+    pub fn as_query_str(&self) -> &'static str {
         match self {
-            Condition::EQ => "=",
-            Condition::NE => "!=",
-            Condition::GT => ">",
-            Condition::GE => ">=",
-            Condition::LT => "<",
-            Condition::LE => "<=",
+            Operator::EQ => "eq",
+            Operator::NE => "ne",
+            Operator::GT => "gt",
+            Operator::GE => "ge",
+            Operator::LT => "lt",
+            Operator::LE => "le",
+            Operator::RANGE => "range",
+            Operator::CONT => "cont",
+            Operator::HAS => "has",
+            Operator::OVERLAPS => "overlaps",
+            Operator::LIKE => "like",
+            Operator::CONTAINS => "contains",
+            Operator::STARTSWITH => "startswith",
+            Operator::ENDSWITH => "endswith",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::EQ => "=",
+            Operator::NE => "!=",
+            Operator::GT => ">",
+            Operator::GE => ">=",
+            Operator::LT => "<",
+            Operator::LE => "<=",
+            Operator::RANGE => "BETWEEN",
+            Operator::CONT => "@>",
+            Operator::HAS => "= ANY",
+            Operator::OVERLAPS => "&&",
+            Operator::LIKE | Operator::CONTAINS | Operator::STARTSWITH | Operator::ENDSWITH => {
+                "LIKE"
+            }
+        }
+    }
+
+    /// Returns the LIKE pattern of the value of a `contains`, `startswith` or `endswith` filter,
+    /// with the wildcards of the value escaped, or `None` for the other operators.
+    pub fn like_pattern(&self, value: &str) -> Option<String> {
+        match self {
+            Operator::CONTAINS => Some(format!("%{}%", escape_like(value))),
+            Operator::STARTSWITH => Some(format!("{}%", escape_like(value))),
+            Operator::ENDSWITH => Some(format!("%{}", escape_like(value))),
+            _ => None,
         }
     }
 }
 
+/// Escapes the wildcards `%` and `_` and the escape character `\` of a value with a backslash, so
+/// a LIKE pattern matches it literally. LIKE conditions are rendered with `ESCAPE '\'`, see
+/// `Dialect::render_like`.
+///
+/// # Examples
+///
+/// ```
+/// use query::filter::escape_like;
+///
+/// assert_eq!(escape_like(r"100%_off\"), r"100\%\_off\\");
+/// ```
+pub fn escape_like(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if let '%' | '_' | '\\' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
 // filter[]=field-gr-0 -> some_value > 0
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Filter {
     pub field: String,
-    pub condition: Condition,
+    pub operator: Operator,
     pub value: String,
+    /// The decoded `key=value` pair the filter was parsed from, eg `filter[]=price-ge-200`.
+    /// Not compared by `PartialEq`.
+    pub raw: Option<String>,
+}
+
+impl PartialEq for Filter {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field && self.operator == other.operator && self.value == other.value
+    }
 }
 
 impl Filter {
     pub fn new(str: &str) -> Result<Self, ParseError> {
-        let (field, rest) = match str.split_once("-") {
+        let (field, rest) = match str.split_once('-') {
             Some(s) => s,
             None => Err(ParseError::InvalidFilter)?,
         };
 
-        let (condition, value) = match rest.split_once("-") {
+        let (operator, value) = match rest.split_once('-') {
             Some(s) => s,
             None => Err(ParseError::InvalidFilter)?,
         };
 
+        let operator = operator.parse()?;
+        if operator == Operator::RANGE {
+            match value.split_once("..") {
+                Some(("", "")) | None => Err(ParseError::InvalidFilter)?,
+                Some(_) => (),
+            }
+        }
+
+        if operator == Operator::OVERLAPS && value.split(',').any(str::is_empty) {
+            Err(ParseError::InvalidFilter)?
+        }
+
+        #[cfg(feature = "serde_json")]
+        if operator == Operator::CONT && serde_json::from_str::<serde_json::Value>(value).is_err() {
+            Err(ParseError::InvalidFormat {
+                field: field.to_owned(),
+                value: value.to_owned(),
+                format: "JSON",
+                raw: None,
+            })?
+        }
+
         Ok(Self {
             field: field.into(),
-            condition: condition.parse()?,
+            operator,
             value: value.into(),
+            raw: None,
         })
     }
 
-    pub fn from_key_value(key: &str, value: &str, condition: Condition) -> Self {
+    pub fn from_key_value(key: &str, value: &str, operator: Operator) -> Self {
         Self {
             field: key.into(),
-            condition,
+            operator,
             value: value.into(),
+            raw: None,
+        }
+    }
+
+    /// Returns the filters comparing with each bound of a range filter, eg `price >= 100` and
+    /// `price <= 500` for `price-range-100..500`, or the filter itself.
+    pub fn expand_range(&self) -> Vec<Filter> {
+        let (start, end) = match (self.operator, self.value.split_once("..")) {
+            (Operator::RANGE, Some(bounds)) => bounds,
+            _ => return vec![self.clone()],
+        };
+
+        [(start, Operator::GE), (end, Operator::LE)]
+            .into_iter()
+            .filter(|(bound, _)| !bound.is_empty())
+            .map(|(bound, operator)| Filter {
+                raw: self.raw.clone(),
+                ..Filter::from_key_value(&self.field, bound, operator)
+            })
+            .collect()
+    }
+
+    /// Returns the elements of the list of an `overlaps` filter, or the value.
+    pub fn values(&self) -> Vec<&str> {
+        match self.operator {
+            Operator::OVERLAPS => self.value.split(',').collect(),
+            _ => vec![self.value.as_str()],
         }
     }
 
-    pub fn to_string(&self) -> String {
+    /// Returns the text the client sent for this filter, if it was kept when parsing.
+    pub fn raw(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    /// Returns the filter in the `field-operator-value` form used by `filter[]`.
+    pub fn to_query_string(&self) -> String {
         let mut filter = String::new();
         filter.push_str(&self.field);
-        filter.push_str(" ");
-        filter.push_str(self.condition.as_str());
-        filter.push_str(" ");
+        filter.push('-');
+        filter.push_str(self.operator.as_query_str());
+        filter.push('-');
         filter.push_str(&self.value);
 
         filter
@@ -93,29 +249,46 @@ impl Filter {
         &self,
         mut filter: String,
         idx: usize,
-        case: Option<Case>,
-        database: &Database,
+        case: Option<NameCase>,
+        database: &dyn Dialect,
     ) -> String {
         // Check if we need to convert case
         match case {
-            Some(case) => filter.push_str(&self.field.to_case(case)),
+            Some(case) => filter.push_str(&case.convert(&self.field)),
             None => filter.push_str(&self.field),
         }
 
-        // Push the comparison operator
-        filter.push_str(" ");
-        filter.push_str(self.condition.as_str());
-        filter.push_str(" ");
+        self.to_sql_expression(idx, &filter, database)
+    }
 
-        // Push the parameters
-        match database {
-            Database::Postgres => {
-                filter.push_str("$");
-                filter.push_str(&idx.to_string());
+    /// Returns the filter comparing `expression` instead of the field, eg `LOWER(email) = $1`.
+    pub fn to_sql_expression(
+        &self,
+        idx: usize,
+        expression: &str,
+        database: &dyn Dialect,
+    ) -> String {
+        match self.operator {
+            Operator::CONT => {
+                return database.json_contains(expression, &database.placeholder(idx))
             }
-            Database::MySQL => filter.push_str("?"),
+            Operator::HAS => return format!("{} = ANY({})", database.placeholder(idx), expression),
+            Operator::LIKE | Operator::CONTAINS | Operator::STARTSWITH | Operator::ENDSWITH => {
+                return database.render_like(expression, &database.placeholder(idx))
+            }
+            _ => (),
         }
 
+        let mut filter = String::from(expression);
+
+        // Push the comparison operator
+        filter.push(' ');
+        filter.push_str(self.operator.as_str());
+        filter.push(' ');
+
+        // Push the parameters
+        filter.push_str(&database.placeholder(idx));
+
         filter
     }
 
@@ -123,22 +296,35 @@ impl Filter {
         &self,
         idx: usize,
         table: Option<&&str>,
-        case: Option<Case>,
-        database: &Database,
+        case: Option<NameCase>,
+        database: &dyn Dialect,
     ) -> String {
         let mut filter = String::new();
         if let Some(table) = table {
             filter.push_str(table);
-            filter.push_str(".")
+            filter.push('.')
         }
 
-        self.to_sql(filter, idx, case, &database)
+        self.to_sql(filter, idx, case, database)
+    }
+}
+
+impl std::fmt::Display for Filter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            self.field,
+            self.operator.as_str(),
+            self.value
+        )
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::Filter;
+    use super::{escape_like, Filter, Operator};
+    use crate::ParseError;
 
     #[test]
     fn test_new_uuid() {
@@ -146,4 +332,104 @@ mod test {
 
         assert_eq!(filter.value, "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e");
     }
+
+    #[test]
+    fn test_operator_from_str() {
+        for operator in [
+            Operator::EQ,
+            Operator::NE,
+            Operator::GT,
+            Operator::GE,
+            Operator::LT,
+            Operator::LE,
+            Operator::RANGE,
+            Operator::CONT,
+            Operator::HAS,
+            Operator::OVERLAPS,
+            Operator::LIKE,
+            Operator::CONTAINS,
+            Operator::STARTSWITH,
+            Operator::ENDSWITH,
+        ] {
+            assert_eq!(operator.as_query_str().parse(), Ok(operator));
+        }
+
+        assert_eq!("gr".parse::<Operator>(), Err(ParseError::InvalidOperator));
+    }
+
+    #[test]
+    fn test_expand_range() {
+        let cases = [
+            (
+                "price-range-100..500",
+                vec![("100", Operator::GE), ("500", Operator::LE)],
+            ),
+            ("price-range-..500", vec![("500", Operator::LE)]),
+            ("price-range-100..", vec![("100", Operator::GE)]),
+            (
+                "createdAt-range-2024-01-01..2024-02-01",
+                vec![("2024-01-01", Operator::GE), ("2024-02-01", Operator::LE)],
+            ),
+            ("price-ge-100..500", vec![("100..500", Operator::GE)]),
+        ];
+
+        for (query, expected) in cases {
+            let filters = Filter::new(query).unwrap().expand_range();
+            let bounds: Vec<_> = filters
+                .iter()
+                .map(|filter| (filter.value.as_str(), filter.operator))
+                .collect();
+            assert_eq!(bounds, expected, "{}", query);
+        }
+
+        for query in ["price-range-..", "price-range-100", "price-range-"] {
+            assert_eq!(
+                Filter::new(query),
+                Err(ParseError::InvalidFilter),
+                "{}",
+                query
+            );
+        }
+    }
+
+    /// Returns whether the text matches the LIKE pattern, with `\` as the escape character.
+    fn like(pattern: &str, text: &str) -> bool {
+        let (pattern, text): (Vec<char>, Vec<char>) =
+            (pattern.chars().collect(), text.chars().collect());
+
+        fn matches(pattern: &[char], text: &[char]) -> bool {
+            match pattern {
+                [] => text.is_empty(),
+                ['%', rest @ ..] => (0..=text.len()).any(|i| matches(rest, &text[i..])),
+                ['_', rest @ ..] => !text.is_empty() && matches(rest, &text[1..]),
+                ['\\', c, rest @ ..] | [c, rest @ ..] => {
+                    text.first() == Some(c) && matches(rest, &text[1..])
+                }
+            }
+        }
+
+        matches(&pattern, &text)
+    }
+
+    #[test]
+    fn test_like_pattern() {
+        let cases = [
+            (Operator::CONTAINS, "50%", "save 50% today", true),
+            (Operator::CONTAINS, "50%", "save 500 today", false),
+            (Operator::STARTSWITH, "a_b", "a_bc", true),
+            (Operator::STARTSWITH, "a_b", "axbc", false),
+            (Operator::ENDSWITH, r"C:\", r"dir C:\", true),
+            (Operator::ENDSWITH, r"C:\", "dir C:", false),
+            (Operator::CONTAINS, "%", "%", true),
+            (Operator::CONTAINS, "%", "anything", false),
+        ];
+
+        for (operator, value, text, expected) in cases {
+            let pattern = operator.like_pattern(value).unwrap();
+            assert_eq!(like(&pattern, text), expected, "{} {}", pattern, text);
+        }
+
+        assert_eq!(escape_like("plain"), "plain");
+        assert_eq!(Operator::LIKE.like_pattern("a%"), None);
+    }
 }
@@ -1,31 +1,288 @@
-#[macro_use]
-
+#[cfg(feature = "sqlx")]
+pub mod bind;
+pub mod case;
+pub mod compat;
+pub mod cursor;
+pub mod dialect;
+#[cfg(feature = "diesel")]
+pub mod diesel;
 pub mod filter;
+pub mod safety;
+pub mod schema;
+#[cfg(feature = "sea-query")]
+pub mod sea;
 pub mod sort;
 pub mod sql;
 pub mod url_query;
+pub mod value;
 
 pub use url_query::UrlQuery;
 
-#[derive(Debug, PartialEq)]
+/// Derives a `schema::QuerySchema` from the fields of a struct.
+///
+/// # Examples
+///
+/// ```ignore
+/// use query::{sql::QueryBuilder, Queryable, UrlQuery};
+///
+/// #[derive(Queryable)]
+/// struct Order {
+///     id: Uuid,
+///     #[query(rename = "userId", ops(eq))]
+///     user_id: i64,
+///     #[query(ops(eq, ge, le))]
+///     price: Decimal,
+///     #[query(skip)]
+///     secret: String,
+/// }
+///
+/// let parsed = UrlQuery::new(query, Order::allowed_fields())?;
+///
+/// let (sql, args) = QueryBuilder::new("orders", vec!["*"], parsed)
+///     .with_schema(Order::schema())
+///     .build_typed()?;
+/// ```
+#[cfg(feature = "derive")]
+pub use query_derive::Queryable;
+
+// Lets the code generated by `Queryable` refer to `::query` within this crate.
+#[cfg(feature = "derive")]
+extern crate self as query;
+
+#[derive(Debug, Clone, PartialEq)]
 pub enum ParseError {
     InvalidSort,
     InvalidSortBy,
     InvalidFilter,
-    InvalidCondition,
+    InvalidOperator,
     InvalidField,
+    InvalidHaving,
+    InvalidPage,
+    InvalidLimit,
+    LimitExceeded,
+    InvalidSparseField {
+        resource: String,
+        field: String,
+    },
+    FieldCollision(String),
+    InvalidOffset,
+    InvalidAfter,
+    DeniedField(String),
+    InvalidCursor,
+    InvalidIdentifier(String),
+    /// A sort field which isn't allowed.
+    InvalidSortField(String),
+    /// A group field which isn't allowed.
+    InvalidGroupField(String),
+    ComputedSortPlaceholder(String),
+    ComputedSortOnly(String),
+    ComputedSortKeyset(String),
+    UnknownRelation(String),
+    InvalidValue {
+        field: String,
+        value: String,
+        /// The parameter of the url query the value comes from, eg `filter[]=price-ge-abc`,
+        /// unless it was parsed with `Options::discard_raw`.
+        raw: Option<String>,
+    },
+    OperatorNotAllowed {
+        field: String,
+        operator: filter::Operator,
+        /// The parameter of the url query the value comes from, eg `filter[]=price-ge-abc`,
+        /// unless it was parsed with `Options::discard_raw`.
+        raw: Option<String>,
+    },
+    InvalidFormat {
+        field: String,
+        value: String,
+        format: &'static str,
+        /// The parameter of the url query the value comes from, eg `filter[]=price-ge-abc`,
+        /// unless it was parsed with `Options::discard_raw`.
+        raw: Option<String>,
+    },
+    /// A param with other filters on it, eg `userId=1&filter[]=userId-eq-2`, see
+    /// `UrlQuery::get`.
+    AmbiguousParam(String),
+    /// The params which `UrlQuery::require` found missing, in the order they were required.
+    MissingParams(Vec<String>),
+    /// The params and fields which aren't allowed, in the order they were passed, when parsing
+    /// with `Options::strict`.
+    UnknownParameter(Vec<String>),
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::InvalidSort => write!(f, "invalid sort"),
+            ParseError::InvalidSort => write!(
+                f,
+                "invalid sort, expected `field-asc`/`field-desc` or `field`/`-field`"
+            ),
             ParseError::InvalidSortBy => write!(f, "invalid sort by"),
             ParseError::InvalidFilter => write!(f, "invalid filter"),
-            ParseError::InvalidCondition => write!(f, "invalid filter condition"),
+            ParseError::InvalidOperator => write!(f, "invalid filter operator"),
             ParseError::InvalidField => write!(f, "invalid field"),
+            ParseError::InvalidHaving => write!(f, "having is only allowed on the grouped field"),
+            ParseError::InvalidPage => write!(f, "invalid page"),
+            ParseError::InvalidLimit => write!(f, "invalid limit"),
+            ParseError::LimitExceeded => write!(f, "limit exceeds the maximum"),
+            ParseError::InvalidSparseField { resource, field } => {
+                write!(f, "invalid field {} for resource {}", field, resource)
+            }
+            ParseError::FieldCollision(column) => {
+                write!(f, "multiple fields resolve to column {}", column)
+            }
+            ParseError::InvalidOffset => write!(f, "invalid offset"),
+            ParseError::InvalidAfter => write!(f, "after requires a sort"),
+            ParseError::DeniedField(field) => write!(f, "field {} is not accessible", field),
+            ParseError::InvalidCursor => write!(f, "invalid cursor"),
+            ParseError::InvalidIdentifier(field) => write!(f, "invalid identifier {}", field),
+            ParseError::InvalidSortField(field) => write!(f, "sorting by {} is not allowed", field),
+            ParseError::InvalidGroupField(field) => {
+                write!(f, "grouping by {} is not allowed", field)
+            }
+            ParseError::ComputedSortPlaceholder(field) => {
+                write!(f, "computed sort {} must not contain placeholders", field)
+            }
+            ParseError::ComputedSortOnly(field) => {
+                write!(f, "field {} can only be used to sort", field)
+            }
+            ParseError::ComputedSortKeyset(field) => {
+                write!(
+                    f,
+                    "keyset pagination is not supported on computed sort {}",
+                    field
+                )
+            }
+            ParseError::UnknownRelation(field) => {
+                write!(f, "field {} references an unknown relation", field)
+            }
+            ParseError::InvalidValue { field, value, raw } => {
+                write!(f, "invalid value {} for field {}", value, field)?;
+                write_raw(f, raw)
+            }
+            ParseError::InvalidFormat {
+                field,
+                value,
+                format,
+                raw,
+            } => {
+                write!(
+                    f,
+                    "invalid value {} for field {}, expected {}",
+                    value, field, format
+                )?;
+                write_raw(f, raw)
+            }
+            ParseError::OperatorNotAllowed {
+                field,
+                operator,
+                raw,
+            } => {
+                write!(
+                    f,
+                    "operator {} is not allowed on field {}",
+                    operator.as_query_str(),
+                    field
+                )?;
+                write_raw(f, raw)
+            }
+            ParseError::AmbiguousParam(key) => {
+                write!(f, "parameter {} has more than one value", key)
+            }
+            ParseError::MissingParams(keys) => {
+                write!(f, "missing required parameters: {}", keys.join(", "))
+            }
+            ParseError::UnknownParameter(keys) => {
+                write!(f, "unknown parameters: {}", keys.join(", "))
+            }
         }
     }
 }
 
 impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// Sets the raw parameter of the errors on a value or an operator, see
+    /// `ParseError::InvalidValue`.
+    pub(crate) fn with_raw(mut self, raw: Option<String>) -> Self {
+        match self {
+            ParseError::InvalidValue { raw: ref mut r, .. }
+            | ParseError::OperatorNotAllowed { raw: ref mut r, .. }
+            | ParseError::InvalidFormat { raw: ref mut r, .. } => *r = raw,
+            _ => {}
+        }
+        self
+    }
+}
+
+fn write_raw(f: &mut std::fmt::Formatter<'_>, raw: &Option<String>) -> std::fmt::Result {
+    match raw {
+        Some(raw) => write!(f, " in {}", raw),
+        None => Ok(()),
+    }
+}
+
+/// A subscriber recording the events emitted while running a test, to check their fields.
+#[cfg(all(test, feature = "tracing"))]
+pub(crate) mod capture {
+    use std::{
+        fmt::{Debug, Write},
+        sync::{Arc, Mutex},
+    };
+
+    use tracing::{
+        field::{Field, Visit},
+        span,
+        subscriber::Interest,
+        Event, Metadata, Subscriber,
+    };
+
+    struct Capture(Arc<Mutex<Vec<String>>>);
+
+    /// The fields of an event, eg `message=built query sql="SELECT 1"`.
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            let _ = write!(self.0, " {}={:?}", field.name(), value);
+        }
+    }
+
+    impl Subscriber for Capture {
+        fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+            Interest::always()
+        }
+
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &span::Attributes<'_>) -> span::Id {
+            span::Id::from_u64(1)
+        }
+
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut fields = Fields(event.metadata().level().to_string());
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+
+        fn enter(&self, _: &span::Id) {}
+
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    /// Runs `f` and returns the events it emitted, eg
+    /// `DEBUG message=built query sql="SELECT * FROM orders" args=0`.
+    pub(crate) fn events(f: impl FnOnce()) -> Vec<String> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        tracing::subscriber::with_default(Capture(events.clone()), f);
+
+        let events = events.lock().unwrap().clone();
+        events
+    }
+}
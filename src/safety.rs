@@ -0,0 +1,488 @@
+use crate::sql::{Arg, Database};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlSafetyError {
+    MultipleStatements,
+    UnfilteredDelete,
+    UnfilteredUpdate,
+}
+
+impl std::fmt::Display for SqlSafetyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlSafetyError::MultipleStatements => write!(f, "multiple statements"),
+            SqlSafetyError::UnfilteredDelete => write!(f, "delete without a filter"),
+            SqlSafetyError::UnfilteredUpdate => write!(f, "update without a filter"),
+        }
+    }
+}
+
+impl std::error::Error for SqlSafetyError {}
+
+/// The problems found by `verify_bind_coverage`.
+#[derive(Debug, PartialEq)]
+pub enum CoverageError {
+    /// The fields of the args which aren't bound, eg because they're missing from `sqlx_bind!`.
+    UnboundFields(Vec<String>),
+    /// The SQL takes a different number of bind parameters than the args fill.
+    PlaceholderCount { placeholders: usize, binds: usize },
+}
+
+impl std::fmt::Display for CoverageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoverageError::UnboundFields(fields) => {
+                write!(f, "args of unbound fields: {}", fields.join(", "))
+            }
+            CoverageError::PlaceholderCount {
+                placeholders,
+                binds,
+            } => write!(f, "{} placeholders but {} binds", placeholders, binds),
+        }
+    }
+}
+
+impl std::error::Error for CoverageError {}
+
+/// Returns an error if the SQL contains a semicolon outside of string literals, quoted
+/// identifiers, comments and dollar-quoted blocks, for any database.
+///
+/// A backslash escapes a quote in MySQL string literals but not in the others, where `'C:\'` is a
+/// complete literal, so the semicolon must be quoted either way. This can reject a statement like
+/// `'it\'s; fine'`, see `check_single_statement_on` to check for one database.
+///
+/// # Examples
+///
+/// ```
+/// use query::safety::{check_single_statement, SqlSafetyError};
+///
+/// assert!(check_single_statement("SELECT * FROM users WHERE name = 'a;b'").is_ok());
+///
+/// assert_eq!(
+///     check_single_statement("SELECT * FROM users; DROP TABLE users"),
+///     Err(SqlSafetyError::MultipleStatements)
+/// );
+/// ```
+pub fn check_single_statement(sql: &str) -> Result<(), SqlSafetyError> {
+    check_single_statement_on(sql, &Database::Postgres)?;
+    check_single_statement_on(sql, &Database::MySQL)
+}
+
+/// Like `check_single_statement`, but with the string literals of the database: on MySQL a
+/// backslash escapes the next character.
+///
+/// # Examples
+///
+/// ```
+/// use query::{safety::{check_single_statement_on, SqlSafetyError}, sql::Database};
+///
+/// let sql = r"SELECT * FROM t WHERE name = 'a\'' ; DROP TABLE users; -- '";
+///
+/// assert!(check_single_statement_on(sql, &Database::Postgres).is_ok());
+/// assert_eq!(
+///     check_single_statement_on(sql, &Database::MySQL),
+///     Err(SqlSafetyError::MultipleStatements)
+/// );
+/// ```
+pub fn check_single_statement_on(sql: &str, database: &Database) -> Result<(), SqlSafetyError> {
+    let backslash_escapes = matches!(database, Database::MySQL);
+    match find_unquoted(sql, 0, backslash_escapes, |bytes, i| bytes[i] == b';') {
+        Some(_) => Err(SqlSafetyError::MultipleStatements),
+        None => Ok(()),
+    }
+}
+
+/// Returns true if the SQL contains a `?` or `$1` style placeholder outside of string literals,
+/// quoted identifiers, comments and dollar-quoted blocks.
+///
+/// # Examples
+///
+/// ```
+/// use query::safety::contains_placeholder;
+///
+/// assert!(contains_placeholder("SELECT * FROM users WHERE id = $1"));
+/// assert!(!contains_placeholder("SELECT * FROM users WHERE name = '$1?'"));
+/// ```
+pub fn contains_placeholder(sql: &str) -> bool {
+    find_unquoted(sql, 0, false, is_placeholder).is_some()
+}
+
+/// Returns the number of bind parameters the SQL takes: the highest `$n`, or the number of `?`.
+/// Placeholders in string literals, quoted identifiers, comments and dollar-quoted blocks are
+/// ignored.
+///
+/// # Examples
+///
+/// ```
+/// use query::safety::count_placeholders;
+///
+/// assert_eq!(count_placeholders("SELECT * FROM users WHERE id = $2 OR id = $1"), 2);
+/// assert_eq!(count_placeholders("SELECT * FROM users WHERE id = ? OR name = '?'"), 1);
+/// ```
+pub fn count_placeholders(sql: &str) -> usize {
+    let bytes = sql.as_bytes();
+    let (mut highest, mut question_marks) = (0, 0);
+
+    let mut start = 0;
+    while let Some(i) = find_unquoted(sql, start, false, is_placeholder) {
+        if bytes[i] == b'?' {
+            question_marks += 1;
+            start = i + 1;
+            continue;
+        }
+
+        let digits = bytes[i + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let n = sql[i + 1..i + 1 + digits].parse().unwrap_or(0);
+        highest = usize::max(highest, n);
+        start = i + 1 + digits;
+    }
+
+    usize::max(highest, question_marks)
+}
+
+/// Returns an error if an arg of the builder wouldn't be bound, because its field isn't one of
+/// `bound_fields`, or if the placeholders of the SQL don't match the args. The placeholders
+/// numbered before the first arg, eg those of `shift_bind` or `with_cte`, are left to the caller.
+/// Only positional placeholders are counted, and placeholders in string literals, quoted
+/// identifiers, comments and dollar-quoted blocks are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use query::{safety::{verify_bind_coverage, CoverageError}, sql::{Database, QueryBuilder}, UrlQuery};
+///
+/// let parsed = UrlQuery::new("userId=1&status=paid", ["userId", "status"]).unwrap();
+/// let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+///
+/// assert!(verify_bind_coverage(&sql, &["userId", "status"], &args, &Database::Postgres).is_ok());
+///
+/// assert_eq!(
+///     verify_bind_coverage(&sql, &["userId"], &args, &Database::Postgres),
+///     Err(CoverageError::UnboundFields(vec!["status".to_owned()]))
+/// );
+/// ```
+pub fn verify_bind_coverage(
+    sql: &str,
+    bound_fields: &[&str],
+    args: &[Arg],
+    database: &Database,
+) -> Result<(), CoverageError> {
+    let mut unbound: Vec<String> = Vec::new();
+    for arg in args {
+        if !bound_fields.contains(&arg.field.as_str()) && !unbound.contains(&arg.field) {
+            unbound.push(arg.field.to_owned());
+        }
+    }
+
+    if !unbound.is_empty() {
+        return Err(CoverageError::UnboundFields(unbound));
+    }
+
+    let Some(first) = args.iter().map(|arg| arg.index).min() else {
+        return Ok(());
+    };
+
+    let placeholders = count_database_placeholders(sql, database);
+    let binds = match database {
+        Database::MySQL | Database::Sqlite => first - 1 + args.len(),
+        _ => args.iter().map(|arg| arg.index).max().unwrap_or(0),
+    };
+
+    if placeholders != binds {
+        return Err(CoverageError::PlaceholderCount {
+            placeholders,
+            binds,
+        });
+    }
+
+    Ok(())
+}
+
+/// Returns the number of bind parameters the SQL takes with the placeholders of the database: the
+/// highest `$n`, `@pn` or `:n`, or the number of `?`.
+fn count_database_placeholders(sql: &str, database: &Database) -> usize {
+    let prefix: &[u8] = match database {
+        Database::MySQL | Database::Sqlite => return count_placeholders(sql),
+        Database::Postgres => b"$",
+        Database::Mssql => b"@p",
+        Database::Oracle => b":",
+    };
+
+    let is_numbered = |bytes: &[u8], i: usize| {
+        bytes[i..].starts_with(prefix)
+            && bytes
+                .get(i + prefix.len())
+                .is_some_and(|b| b.is_ascii_digit())
+    };
+
+    let mut highest = 0;
+    let mut start = 0;
+    while let Some(i) = find_unquoted(sql, start, false, is_numbered) {
+        let digits = sql.as_bytes()[i + prefix.len()..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        let end = i + prefix.len() + digits;
+        highest = usize::max(highest, sql[i + prefix.len()..end].parse().unwrap_or(0));
+        start = end;
+    }
+
+    highest
+}
+
+pub(crate) fn is_placeholder(bytes: &[u8], i: usize) -> bool {
+    match bytes[i] {
+        b'?' => true,
+        b'$' => bytes.get(i + 1).is_some_and(|b| b.is_ascii_digit()),
+        _ => false,
+    }
+}
+
+/// Returns the index of the first byte from `start` matching `f` outside of string literals,
+/// quoted identifiers, comments and dollar-quoted blocks. With `backslash_escapes`, as on MySQL, a
+/// backslash in a string literal escapes the next character.
+pub(crate) fn find_unquoted(
+    sql: &str,
+    start: usize,
+    backslash_escapes: bool,
+    f: impl Fn(&[u8], usize) -> bool,
+) -> Option<usize> {
+    let bytes = sql.as_bytes();
+
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"') => i = skip_quoted(bytes, i, quote, backslash_escapes),
+            b'`' => i = skip_quoted(bytes, i, b'`', false),
+            b'-' if bytes.get(i + 1) == Some(&b'-') => i = skip_until(bytes, i + 2, b"\n"),
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_until(bytes, i + 2, b"*/"),
+            b'$' if dollar_tag(bytes, i).is_some() => {
+                let tag = dollar_tag(bytes, i).unwrap();
+                i = skip_until(bytes, i + tag.len(), tag);
+            }
+            _ if f(bytes, i) => return Some(i),
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+/// Returns the index after the closing quote. A doubled quote is an escaped quote, and so is a
+/// quote after a backslash with `backslash_escapes`.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if backslash_escapes && bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+                continue;
+            }
+
+            return i + 1;
+        }
+
+        i += 1;
+    }
+
+    i
+}
+
+/// Returns the index after the next occurrence of `end`, or the end of the SQL.
+fn skip_until(bytes: &[u8], start: usize, end: &[u8]) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        if bytes[i..].starts_with(end) {
+            return i + end.len();
+        }
+
+        i += 1;
+    }
+
+    i
+}
+
+/// Returns the opening tag of a dollar-quoted block, eg `$$` or `$body$`, starting at `start`.
+/// Positional parameters like `$1` are not dollar quotes.
+fn dollar_tag(bytes: &[u8], start: usize) -> Option<&[u8]> {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'$' => return Some(&bytes[start..=i]),
+            b'0'..=b'9' if i == start + 1 => return None,
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'_' => i += 1,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        sql::{Database, QueryBuilder},
+        UrlQuery,
+    };
+
+    use super::{
+        check_single_statement, check_single_statement_on, contains_placeholder,
+        count_placeholders, verify_bind_coverage, CoverageError, SqlSafetyError,
+    };
+
+    #[test]
+    fn test_check_single_statement() {
+        let allowed = [
+            "SELECT * FROM users WHERE id = $1",
+            "SELECT * FROM users WHERE name = 'a;b'",
+            "SELECT * FROM users WHERE name = 'it''s; fine'",
+            "SELECT \"a;b\" FROM users",
+            "SELECT `a;b` FROM users",
+            "SELECT * FROM users -- trailing; comment",
+            "SELECT * FROM users /* block; comment */ WHERE id = $1",
+            "SELECT $$a;b$$, $body$c;d$body$ FROM users WHERE id = $1",
+        ];
+
+        for sql in allowed {
+            assert_eq!(check_single_statement(sql), Ok(()), "{}", sql);
+        }
+
+        let rejected = [
+            "SELECT * FROM users; DROP TABLE users",
+            "SELECT * FROM users;",
+            "SELECT * FROM users WHERE name = 'a' ; DROP TABLE users",
+            "SELECT * FROM users WHERE name = 'C:\\'; DROP TABLE users; --'",
+            "SELECT * FROM users -- comment\n; DROP TABLE users",
+            "SELECT * FROM users WHERE id = $1; DROP TABLE users",
+        ];
+
+        for sql in rejected {
+            assert_eq!(
+                check_single_statement(sql),
+                Err(SqlSafetyError::MultipleStatements),
+                "{}",
+                sql
+            );
+        }
+
+        // A backslash escapes the quote on MySQL, so the literal ends before the semicolon.
+        let sql = r"SELECT * FROM t WHERE name = 'a\'' ; DROP TABLE users; -- '";
+        assert_eq!(
+            check_single_statement(sql),
+            Err(SqlSafetyError::MultipleStatements)
+        );
+        assert_eq!(
+            check_single_statement_on(sql, &Database::MySQL),
+            Err(SqlSafetyError::MultipleStatements)
+        );
+        assert_eq!(check_single_statement_on(sql, &Database::Postgres), Ok(()));
+        assert_eq!(
+            check_single_statement_on(r"SELECT 'it\'s; fine'", &Database::MySQL),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_contains_placeholder() {
+        let with = [
+            "SELECT * FROM users WHERE id = $1",
+            "SELECT * FROM users WHERE id = ?",
+            "(SELECT MAX(created_at) FROM orders WHERE customer_id = $12)",
+        ];
+
+        for sql in with {
+            assert!(contains_placeholder(sql), "{}", sql);
+        }
+
+        let without = [
+            "(SELECT MAX(created_at) FROM orders o WHERE o.customer_id = customers.id)",
+            "SELECT * FROM users WHERE name = '$1?'",
+            "SELECT \"a?\" FROM users -- where id = ?",
+            "SELECT $$?$$, $body$$1$body$ FROM users /* ? */",
+        ];
+
+        for sql in without {
+            assert!(!contains_placeholder(sql), "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_count_placeholders() {
+        let cases = [
+            ("SELECT 1", 0),
+            ("SELECT * FROM users WHERE id = $1", 1),
+            (
+                "SELECT * FROM users WHERE id = $1 AND (a = $12 OR b = $3)",
+                12,
+            ),
+            ("SELECT * FROM users WHERE a = ? AND b = ? -- c = ?", 2),
+            ("SELECT '$4', $$?$$ FROM users WHERE id = $2", 2),
+        ];
+
+        for (sql, count) in cases {
+            assert_eq!(count_placeholders(sql), count, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_verify_bind_coverage() {
+        let query = "userId=1&filter[]=status-eq-paid&limit=10";
+        let fields = ["userId", "status", "limit"];
+
+        let parsed = UrlQuery::new(query, ["userId", "status"]).unwrap();
+        let (sql, args) =
+            QueryBuilder::from_str("SELECT * FROM orders WHERE tenant_id = $1", parsed)
+                .shift_bind(1)
+                .bind_pagination(true)
+                .build();
+        assert_eq!(
+            verify_bind_coverage(&sql, &fields, &args, &Database::Postgres),
+            Ok(())
+        );
+        assert_eq!(
+            verify_bind_coverage(&sql, &["status"], &args, &Database::Postgres),
+            Err(CoverageError::UnboundFields(vec![
+                "userId".to_owned(),
+                "limit".to_owned()
+            ]))
+        );
+
+        let sql = format!("{} AND note = '$9' AND region = $5", sql);
+        assert_eq!(
+            verify_bind_coverage(&sql, &fields, &args, &Database::Postgres),
+            Err(CoverageError::PlaceholderCount {
+                placeholders: 5,
+                binds: 4
+            })
+        );
+
+        let parsed = UrlQuery::new(query, ["userId", "status"]).unwrap();
+        let (sql, args) =
+            QueryBuilder::from_str("SELECT * FROM orders WHERE tenant_id = ?", parsed)
+                .set_database(Database::MySQL)
+                .shift_bind(1)
+                .bind_pagination(true)
+                .build();
+        assert_eq!(
+            verify_bind_coverage(&sql, &fields, &args, &Database::MySQL),
+            Ok(())
+        );
+        assert_eq!(
+            verify_bind_coverage(
+                &format!("{} AND x = '?'", sql),
+                &fields,
+                &args,
+                &Database::MySQL
+            ),
+            Ok(())
+        );
+    }
+}
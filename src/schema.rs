@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+
+use crate::{filter::Operator, value::FieldType, ParseError, UrlQuery};
+
+/// A field of a `QuerySchema`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    /// The name of the field in the url query, eg `userId`.
+    pub name: String,
+    pub field_type: FieldType,
+    /// The column, used as is, eg `user_id`. The name goes through `convert_case` otherwise.
+    pub column: Option<String>,
+    /// The table prefixing the column, eg `orders`.
+    pub table: Option<String>,
+    /// The SQL expression replacing the column, eg `(price * quantity)`.
+    pub expression: Option<String>,
+    /// The operators allowed on the field, or every operator if `None`.
+    pub operators: Option<Vec<Operator>>,
+}
+
+impl Field {
+    pub fn new(name: &str, field_type: FieldType) -> Self {
+        Self {
+            name: name.to_owned(),
+            field_type,
+            column: None,
+            table: None,
+            expression: None,
+            operators: None,
+        }
+    }
+
+    /// Set the column of the field.
+    pub fn column(mut self, column: &str) -> Self {
+        self.column = Some(column.to_owned());
+
+        self
+    }
+
+    /// Set the table prefixing the column of the field.
+    pub fn table(mut self, table: &str) -> Self {
+        self.table = Some(table.to_owned());
+
+        self
+    }
+
+    /// Set the SQL expression replacing the column of the field.
+    pub fn expression(mut self, expression: &str) -> Self {
+        self.expression = Some(expression.to_owned());
+
+        self
+    }
+
+    /// Only allow filtering on the field with the given operators.
+    pub fn operators(mut self, operators: impl IntoIterator<Item = Operator>) -> Self {
+        self.operators = Some(operators.into_iter().collect());
+
+        self
+    }
+}
+
+/// The fields of an endpoint, along with their column, type and allowed operators, declared in
+/// one place. `UrlQuery::with_schema` validates a url query against it, and
+/// `QueryBuilder::with_schema` renders the fields with their column and types the args.
+///
+/// # Examples
+///
+/// ```
+/// use query::{
+///     filter::Operator,
+///     schema::{Field, QuerySchema},
+///     sql::QueryBuilder,
+///     value::FieldType,
+///     ParseError, UrlQuery,
+/// };
+///
+/// let schema = QuerySchema::new()
+///     .field(Field::new("userId", FieldType::Int).column("user_id").operators([Operator::EQ]))
+///     .field(Field::new("price", FieldType::Float).table("orders"));
+///
+/// let parsed = UrlQuery::with_schema("userId=1&filter[]=price-ge-10", &schema).unwrap();
+///
+/// let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+///     .with_schema(&schema)
+///     .build_typed()
+///     .unwrap();
+///
+/// assert_eq!(sql, "SELECT * FROM orders WHERE user_id = $1 AND orders.price >= $2");
+///
+/// assert_eq!(
+///     UrlQuery::with_schema("filter[]=userId-gt-1", &schema),
+///     Err(ParseError::OperatorNotAllowed {
+///         field: "userId".to_owned(),
+///         operator: Operator::GT,
+///         raw: Some("filter[]=userId-gt-1".to_owned()),
+///     })
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QuerySchema {
+    fields: Vec<Field>,
+}
+
+impl QuerySchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a field. A field added twice replaces the previous one.
+    pub fn field(mut self, field: Field) -> Self {
+        self.fields.retain(|other| other.name != field.name);
+        self.fields.push(field);
+
+        self
+    }
+
+    /// Returns the fields, in the order they were added.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Returns the field with the given name.
+    pub fn get(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Returns the names of the fields, to pass to `UrlQuery::new`.
+    pub fn allowed_fields(&self) -> HashSet<&str> {
+        self.fields
+            .iter()
+            .map(|field| field.name.as_str())
+            .collect()
+    }
+
+    /// Returns `ParseError::OperatorNotAllowed` if a filter or having of the url query uses an
+    /// operator which isn't allowed on its field, or `ParseError::InvalidValue` if its value isn't
+    /// valid for the type of the field, `ParseError::InvalidFormat` for dates and timestamps.
+    /// The values of `after` and of the cursor are checked against the type of their sort field.
+    pub fn check(&self, url_query: &UrlQuery) -> Result<(), ParseError> {
+        for filter in url_query.filters.iter().chain(url_query.havings.iter()) {
+            // Havings on aggregates aren't fields of the schema.
+            let field = match self.get(&filter.field) {
+                Some(field) => field,
+                None => continue,
+            };
+
+            if let Some(ref operators) = field.operators {
+                if !operators.contains(&filter.operator) {
+                    Err(ParseError::OperatorNotAllowed {
+                        field: field.name.to_owned(),
+                        operator: filter.operator,
+                        raw: filter.raw.clone(),
+                    })?
+                }
+            }
+
+            for filter in filter.expand_range() {
+                for value in filter.values() {
+                    if field.field_type.parse(value).is_none() {
+                        let error = field.field_type.invalid(&field.name, value);
+                        Err(error.with_raw(filter.raw.clone()))?
+                    }
+                }
+            }
+        }
+
+        // The cursor also holds the tiebreaker, after the values of the sort fields.
+        let after = url_query.sorts.first().zip(url_query.after.as_ref());
+        let cursor = url_query
+            .cursor
+            .iter()
+            .flat_map(|cursor| url_query.sorts.iter().zip(cursor.values.iter()));
+        for (sort, value) in after.into_iter().chain(cursor) {
+            if let Some(field) = self.get(&sort.field) {
+                if field.field_type.parse(value).is_none() {
+                    Err(field.field_type.invalid(&field.name, value))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Field, QuerySchema};
+    use crate::{
+        case::NameCase, filter::Operator, sql::QueryBuilder, value::ArgValue, value::FieldType,
+        ParseError, UrlQuery,
+    };
+
+    fn schema() -> QuerySchema {
+        QuerySchema::new()
+            .field(Field::new("id", FieldType::Uuid).operators([Operator::EQ, Operator::NE]))
+            .field(Field::new("userId", FieldType::Int).column("owner"))
+            .field(Field::new("createdAt", FieldType::Date).table("orders"))
+            .field(Field::new("total", FieldType::Float).expression("(price * quantity)"))
+    }
+
+    #[test]
+    fn test_with_schema() {
+        let schema = schema();
+
+        let cases = [
+            ("userId=1&sort=createdAt-desc", Ok(())),
+            ("status=paid", Err(ParseError::InvalidField)),
+            (
+                "filter[]=id-ge-8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e",
+                Err(ParseError::OperatorNotAllowed {
+                    field: "id".to_owned(),
+                    operator: Operator::GE,
+                    raw: Some("filter[]=id-ge-8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e".to_owned()),
+                }),
+            ),
+            (
+                "filter[]=createdAt-gt-yesterday",
+                Err(ParseError::InvalidFormat {
+                    field: "createdAt".to_owned(),
+                    value: "yesterday".to_owned(),
+                    format: "YYYY-MM-DD",
+                    raw: Some("filter[]=createdAt-gt-yesterday".to_owned()),
+                }),
+            ),
+            (
+                "sort=id-asc&after=abc",
+                Err(ParseError::InvalidValue {
+                    field: "id".to_owned(),
+                    value: "abc".to_owned(),
+                    raw: None,
+                }),
+            ),
+        ];
+
+        for (query, expected) in cases {
+            let result = UrlQuery::with_schema(query, &schema).map(|_| ());
+            assert_eq!(result, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_query_builder_with_schema() {
+        let schema = schema();
+
+        let query =
+            "userId=1&filter[]=createdAt-ge-2024-05-01&filter[]=total-gt-10&sort=total-desc";
+
+        let parsed = UrlQuery::with_schema(query, &schema).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .with_schema(&schema)
+            .build_checked()
+            .unwrap();
+
+        let expected = "SELECT * FROM orders WHERE owner = $1 \
+        AND orders.created_at >= $2 AND (price * quantity) > $3 \
+        ORDER BY (price * quantity) DESC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 3);
+
+        let parsed = UrlQuery::with_schema(query, &schema).unwrap();
+
+        let (_, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .with_schema(&schema)
+            .build_typed()
+            .unwrap();
+
+        assert_eq!(args[0], ("userId".to_owned(), ArgValue::Int(1)));
+        assert_eq!(args[2], ("total".to_owned(), ArgValue::Float(10.0)));
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod derive_test {
+    use crate::{
+        filter::Operator,
+        schema::{Field, QuerySchema},
+        value::FieldType,
+        Queryable,
+    };
+
+    #[allow(dead_code)]
+    #[derive(Queryable)]
+    struct Order {
+        id: String,
+        #[query(rename = "userId", ops(eq, ne))]
+        user_id: i64,
+        price: Option<f64>,
+        #[query(skip)]
+        secret: String,
+    }
+
+    #[test]
+    fn test_derive_queryable() {
+        assert_eq!(Order::allowed_fields(), ["id", "userId", "price"]);
+
+        let schema = QuerySchema::new()
+            .field(Field::new("id", FieldType::Text))
+            .field(
+                Field::new("userId", FieldType::Int)
+                    .column("user_id")
+                    .operators([Operator::EQ, Operator::NE]),
+            )
+            .field(Field::new("price", FieldType::Float));
+
+        assert_eq!(Order::schema(), &schema);
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use sea_query::{
+    Alias, Condition, Expr, LikeExpr, NullOrdering, Order, SelectStatement, SimpleExpr, Value,
+};
+
+use crate::{
+    case::NameCase,
+    filter::{Filter, Operator},
+    sort::{Nulls, SortBy},
+    sql::BuildError,
+    value::{ArgValue, FieldType},
+    UrlQuery,
+};
+
+/// Converts a url query into the conditions, orderings and pagination of a sea-query
+/// `SelectStatement`, with the column mapping of `QueryBuilder`.
+///
+/// # Examples
+///
+/// ```ignore
+/// use query::{case::NameCase, sea::SeaQueryBuilder, UrlQuery};
+/// use sea_query::{Alias, Asterisk, PostgresQueryBuilder, Query};
+///
+/// let parsed = UrlQuery::new("userId=1&sort=price-desc&limit=10", ["userId", "price"])?;
+///
+/// let mut select = Query::select();
+/// select.column(Asterisk).from(Alias::new("orders"));
+///
+/// SeaQueryBuilder::new(parsed)
+///     .convert_case(NameCase::Snake)
+///     .apply(&mut select)?;
+///
+/// let (sql, values) = select.build(PostgresQueryBuilder);
+/// ```
+#[derive(Debug, Clone)]
+pub struct SeaQueryBuilder<'a> {
+    url_query: UrlQuery,
+    convert_case: Option<NameCase>,
+    map_columns: HashMap<&'a str, &'a str>,
+    rename_columns: HashMap<&'a str, &'a str>,
+    field_types: HashMap<&'a str, FieldType>,
+}
+
+impl<'a> SeaQueryBuilder<'a> {
+    pub fn new(url_query: UrlQuery) -> Self {
+        Self {
+            url_query,
+            convert_case: None,
+            map_columns: HashMap::new(),
+            rename_columns: HashMap::new(),
+            field_types: HashMap::new(),
+        }
+    }
+
+    /// Convert the fields of the url query to the case of their columns, see
+    /// `QueryBuilder::convert_case`.
+    pub fn convert_case(mut self, case: impl Into<NameCase>) -> Self {
+        self.convert_case = Some(case.into());
+
+        self
+    }
+
+    /// Provide the table of each ambiguous field, see `QueryBuilder::map_columns`.
+    pub fn map_columns(mut self, map_columns: HashMap<&'a str, &'a str>) -> Self {
+        self.map_columns = map_columns;
+
+        self
+    }
+
+    /// Rename fields to the columns they're stored in, see `QueryBuilder::rename_columns`.
+    pub fn rename_columns(mut self, rename_columns: HashMap<&'a str, &'a str>) -> Self {
+        self.rename_columns = rename_columns;
+
+        self
+    }
+
+    /// Provide the type of the values of each field, see `QueryBuilder::field_types`. The values
+    /// of fields without a type are strings.
+    pub fn field_types(mut self, field_types: HashMap<&'a str, FieldType>) -> Self {
+        self.field_types = field_types;
+
+        self
+    }
+
+    /// Returns the filters of the url query, ANDed. Fails with `BuildError::Parse` if a value
+    /// isn't valid for the type of its field, and with `BuildError::UnsupportedOperator` for the
+    /// operators of JSON and array columns: `cont`, `has` and `overlaps`.
+    pub fn condition(&self) -> Result<Condition, BuildError> {
+        let mut condition = Condition::all();
+        for filter in &self.url_query.filters {
+            let bounds = filter.expand_range();
+            if bounds.len() == 1 {
+                condition = condition.add(self.filter(&bounds[0])?);
+                continue;
+            }
+
+            let mut range = Condition::all();
+            for bound in &bounds {
+                range = range.add(self.filter(bound)?);
+            }
+            condition = condition.add(range);
+        }
+
+        Ok(condition)
+    }
+
+    /// Returns the sorts of the url query as expressions for
+    /// `SelectStatement::order_by_expr_with_nulls`.
+    pub fn orderings(&self) -> Vec<(SimpleExpr, Order, Option<NullOrdering>)> {
+        self.url_query
+            .sorts
+            .iter()
+            .map(|sort| {
+                let order = match sort.sort_by {
+                    SortBy::ASC => Order::Asc,
+                    SortBy::DESC => Order::Desc,
+                };
+                let nulls = sort.nulls.map(|nulls| match nulls {
+                    Nulls::First => NullOrdering::First,
+                    Nulls::Last => NullOrdering::Last,
+                });
+
+                (self.column(&sort.field), order, nulls)
+            })
+            .collect()
+    }
+
+    /// Adds the filters, sorts, limit and offset of the url query to the statement.
+    pub fn apply(&self, select: &mut SelectStatement) -> Result<(), BuildError> {
+        select.cond_where(self.condition()?);
+
+        for (expr, order, nulls) in self.orderings() {
+            match nulls {
+                Some(nulls) => select.order_by_expr_with_nulls(expr, order, nulls),
+                None => select.order_by_expr(expr, order),
+            };
+        }
+
+        let (limit, offset) = self.url_query.limit_offset;
+        if let Some(limit) = limit {
+            select.limit(limit);
+        }
+        if let Some(offset) = offset {
+            select.offset(offset);
+        }
+
+        Ok(())
+    }
+
+    fn filter(&self, filter: &Filter) -> Result<SimpleExpr, BuildError> {
+        let column = Expr::expr(self.column(&filter.field));
+
+        let expr = match filter.operator {
+            Operator::EQ => column.eq(self.value(filter)?),
+            Operator::NE => column.ne(self.value(filter)?),
+            Operator::GT => column.gt(self.value(filter)?),
+            Operator::GE => column.gte(self.value(filter)?),
+            Operator::LT => column.lt(self.value(filter)?),
+            Operator::LE => column.lte(self.value(filter)?),
+            Operator::LIKE | Operator::CONTAINS | Operator::STARTSWITH | Operator::ENDSWITH => {
+                let pattern = filter.operator.like_pattern(&filter.value);
+                let pattern = pattern.unwrap_or_else(|| filter.value.to_owned());
+                column.like(LikeExpr::new(pattern).escape('\\'))
+            }
+            Operator::RANGE | Operator::CONT | Operator::HAS | Operator::OVERLAPS => {
+                return Err(BuildError::UnsupportedOperator {
+                    field: filter.field.to_owned(),
+                    operator: filter.operator,
+                })
+            }
+        };
+
+        Ok(expr)
+    }
+
+    /// Returns the value of the filter with the type of its field.
+    fn value(&self, filter: &Filter) -> Result<Value, BuildError> {
+        let Some(field_type) = self.field_types.get(filter.field.as_str()) else {
+            return Ok(filter.value.to_owned().into());
+        };
+
+        let value = match field_type.parse(&filter.value) {
+            Some(ArgValue::Int(value)) => value.into(),
+            Some(ArgValue::Float(value)) => value.into(),
+            Some(ArgValue::Bool(value)) => value.into(),
+            Some(value) => value.to_string().into(),
+            None => {
+                let error = field_type.invalid(&filter.field, &filter.value);
+                return Err(BuildError::Parse(error.with_raw(filter.raw.clone())));
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn column(&self, field: &str) -> SimpleExpr {
+        let name = self.rename_columns.get(field).unwrap_or(&field);
+        let name = match self.convert_case {
+            Some(case) => case.convert(name),
+            None => name.to_string(),
+        };
+
+        match self.map_columns.get(field) {
+            Some(table) => Expr::col((Alias::new(*table), Alias::new(name))).into(),
+            None => Expr::col(Alias::new(name)).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use sea_query::{Alias, Asterisk, MysqlQueryBuilder, PostgresQueryBuilder, Query, Value};
+
+    use crate::{
+        case::NameCase,
+        filter::Operator,
+        sql::{BuildError, Database, QueryBuilder},
+        value::FieldType,
+        ParseError, UrlQuery,
+    };
+
+    use super::SeaQueryBuilder;
+
+    #[test]
+    fn test_sea_query() {
+        let query = "userId=1&filter[]=price-range-100..500&filter[]=name-contains-50%\
+        &sort=price-desc-nullslast,userId&limit=10&offset=20";
+        let allowed = ["userId", "price", "name"];
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+        let field_types = HashMap::from([("userId", FieldType::Int), ("price", FieldType::Int)]);
+
+        let mut select = Query::select();
+        select.column(Asterisk).from(Alias::new("orders"));
+        SeaQueryBuilder::new(parsed.clone())
+            .convert_case(NameCase::Snake)
+            .field_types(field_types.clone())
+            .apply(&mut select)
+            .unwrap();
+
+        let (sea_sql, values) = select.build(PostgresQueryBuilder);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM \"orders\"", parsed.clone())
+            .convert_case(NameCase::Snake)
+            .quote_identifiers(true)
+            .bind_pagination(true)
+            .build();
+
+        assert_eq!(
+            sea_sql.replace(" ESCAPE E'\\\\'", ""),
+            sql.replace(" ESCAPE '\\'", "")
+        );
+        assert_eq!(
+            values.0,
+            vec![
+                Value::BigInt(Some(1)),
+                Value::BigInt(Some(100)),
+                Value::BigInt(Some(500)),
+                Value::String(Some(Box::new("%50\\%%".to_owned()))),
+                Value::BigUnsigned(Some(10)),
+                Value::BigUnsigned(Some(20)),
+            ]
+        );
+        assert_eq!(args.len(), values.0.len());
+
+        let mut select = Query::select();
+        select.column(Asterisk).from(Alias::new("orders"));
+        SeaQueryBuilder::new(parsed.clone())
+            .convert_case(NameCase::Snake)
+            .map_columns(HashMap::from([("userId", "orders")]))
+            .rename_columns(HashMap::from([("name", "title")]))
+            .apply(&mut select)
+            .unwrap();
+
+        let (sea_sql, _) = select.build(MysqlQueryBuilder);
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM `orders`", parsed)
+            .convert_case(NameCase::Snake)
+            .map_columns(HashMap::from([("userId", "orders")]))
+            .rename_columns(HashMap::from([("name", "title")]))
+            .set_database(Database::MySQL)
+            .quote_identifiers(true)
+            .bind_pagination(true)
+            .build();
+
+        // `IS NULL ASC` and `IS NULL` order the same.
+        assert_eq!(
+            sea_sql
+                .replace(" ESCAPE '\\\\'", "")
+                .replace(" IS NULL ASC", " IS NULL"),
+            sql.replace(" ESCAPE '\\\\'", "")
+        );
+    }
+
+    #[test]
+    fn test_sea_query_errors() {
+        let parsed = UrlQuery::new("userId=abc", ["userId"]).unwrap();
+        let result = SeaQueryBuilder::new(parsed)
+            .field_types(HashMap::from([("userId", FieldType::Int)]))
+            .condition();
+
+        assert_eq!(
+            result.map(|_| ()),
+            Err(BuildError::Parse(ParseError::InvalidValue {
+                field: "userId".to_owned(),
+                value: "abc".to_owned(),
+                raw: Some("userId=abc".to_owned()),
+            }))
+        );
+
+        let parsed = UrlQuery::new("filter[]=tags-has-rust", ["tags"]).unwrap();
+        let result = SeaQueryBuilder::new(parsed).condition();
+
+        assert_eq!(
+            result.map(|_| ()),
+            Err(BuildError::UnsupportedOperator {
+                field: "tags".to_owned(),
+                operator: Operator::HAS,
+            })
+        );
+    }
+}
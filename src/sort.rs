@@ -1,82 +1,359 @@
 use std::str::FromStr;
 
-use convert_case::{Case, Casing};
+use crate::case::NameCase;
 
-use crate::ParseError;
+use crate::{dialect::Dialect, sql::Database, ParseError};
 
 // sort=field-desc
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Sort {
     pub field: String,
     pub sort_by: SortBy,
+    pub nulls: Option<Nulls>,
+    /// The decoded segment the sort was parsed from, eg `sort=price-desc`. Not compared by
+    /// `PartialEq`.
+    pub raw: Option<String>,
+}
+
+impl PartialEq for Sort {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field && self.sort_by == other.sort_by && self.nulls == other.nulls
+    }
 }
 
 impl Sort {
+    /// Parses either `field-asc`/`field-desc`, or the shorthand `field`/`-field` where a leading
+    /// dash means descending. Input using both forms at once, like `-price-desc`, is rejected.
+    /// Either form may end with `-nullsfirst` or `-nullslast`, eg `shippedAt-desc-nullslast`.
     pub fn new(str: &str) -> Result<Self, ParseError> {
-        let (field, sort_by) = str
-            .split_once("-")
-            .map(|(f, s)| (f.to_owned(), s))
-            .ok_or_else(|| ParseError::InvalidSort)?;
+        let (str, nulls) = match str.rsplit_once('-') {
+            Some((rest, nulls)) if Nulls::from_str(nulls).is_ok() => (rest, nulls.parse().ok()),
+            _ => (str, None),
+        };
 
-        let sort_by = SortBy::from_str(sort_by)?;
+        let (field, sort_by) = parse_field_and_direction(str)?;
 
-        Ok(Sort { field, sort_by })
+        Ok(Sort {
+            field: field.to_owned(),
+            sort_by,
+            nulls,
+            raw: None,
+        })
     }
 
-    pub fn to_string(&self) -> String {
+    /// Returns the text the client sent for this sort, if it was kept when parsing.
+    pub fn raw(&self) -> Option<&str> {
+        self.raw.as_deref()
+    }
+
+    /// Returns the sort in the `field-direction` form used by the `sort` query parameter.
+    pub fn to_query_string(&self) -> String {
         let mut sort = String::new();
         sort.push_str(&self.field);
-        sort.push_str(" ");
-        sort.push_str(self.sort_by.as_str());
+        sort.push('-');
+        sort.push_str(self.sort_by.as_query_str());
+        if let Some(nulls) = self.nulls {
+            sort.push('-');
+            sort.push_str(nulls.as_query_str());
+        }
 
         sort
     }
 
-    pub fn to_sql(&self, mut sort: String, case: Option<Case>) -> String {
-        match case {
-            Some(case) => sort.push_str(&self.field.to_case(case)),
-            None => sort.push_str(&self.field.to_case(Case::Snake)),
-        }
-        sort.push_str(" ");
-        sort.push_str(self.sort_by.as_str());
+    pub fn to_sql(&self, mut sort: String, case: Option<NameCase>) -> String {
+        sort.push_str(&self.column(case));
+        self.to_sql_expression(&sort, &Database::Postgres)
+    }
 
-        sort
+    /// Returns the sort ordering by `expression` instead of the field, eg `(price * quantity)
+    /// DESC`, see `Dialect::render_sort`.
+    pub fn to_sql_expression(&self, expression: &str, database: &dyn Dialect) -> String {
+        database.render_sort(expression, self.sort_by, self.nulls)
     }
 
-    pub fn to_sql_map_table(&self, table: Option<&&str>, case: Option<Case>) -> String {
+    /// Returns the sort on the column, prefixed with `table`. See `to_sql_expression`.
+    pub fn to_sql_map_table(
+        &self,
+        table: Option<&&str>,
+        case: Option<NameCase>,
+        database: &dyn Dialect,
+    ) -> String {
         let mut sort = String::new();
         if let Some(table) = table {
             sort.push_str(table);
-            sort.push_str(".")
+            sort.push('.')
         }
 
-        self.to_sql(sort, case)
+        sort.push_str(&self.column(case));
+        self.to_sql_expression(&sort, database)
+    }
+
+    fn column(&self, case: Option<NameCase>) -> String {
+        match case {
+            Some(case) => case.convert(&self.field),
+            None => self.field.to_owned(),
+        }
+    }
+}
+
+/// Splits `field-asc`/`field-desc` or `field`/`-field` into the field and the direction.
+fn parse_field_and_direction(str: &str) -> Result<(&str, SortBy), ParseError> {
+    if let Some((field, sort_by)) = str.rsplit_once('-') {
+        if let Ok(sort_by) = SortBy::from_str(sort_by) {
+            if field.is_empty() || field.starts_with('-') {
+                Err(ParseError::InvalidSort)?
+            }
+
+            return Ok((field, sort_by));
+        }
+    }
+
+    let (field, sort_by) = match str.strip_prefix('-') {
+        Some(field) => (field, SortBy::DESC),
+        None => (str, SortBy::ASC),
+    };
+
+    if field.is_empty() {
+        Err(ParseError::InvalidSort)?
+    }
+    if field.contains('-') {
+        Err(ParseError::InvalidSortBy)?
+    }
+
+    Ok((field, sort_by))
+}
+
+impl FromStr for Sort {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+/// Formats the sort like `to_query_string`, eg `price-desc`.
+impl std::fmt::Display for Sort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_query_string())
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortBy {
     ASC,
     DESC,
 }
 
+/// Formats the direction like `as_query_str`, eg `desc`.
+impl std::fmt::Display for SortBy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_query_str())
+    }
+}
+
 impl FromStr for SortBy {
     type Err = ParseError;
 
+    /// Parses `asc` or `desc`, ignoring case.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "asc" => Ok(Self::ASC),
-            "desc" => Ok(Self::DESC),
+            _ if s.eq_ignore_ascii_case("asc") => Ok(Self::ASC),
+            _ if s.eq_ignore_ascii_case("desc") => Ok(Self::DESC),
             _ => Err(ParseError::InvalidSortBy),
         }
     }
 }
 
 impl SortBy {
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             Self::ASC => "ASC",
             Self::DESC => "DESC",
         }
     }
+
+    /// Returns the direction as it appears in the `sort` query parameter.
+    pub fn as_query_str(&self) -> &'static str {
+        match self {
+            Self::ASC => "asc",
+            Self::DESC => "desc",
+        }
+    }
+}
+
+// sort=field-desc-nullslast
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nulls {
+    First,
+    Last,
+}
+
+impl FromStr for Nulls {
+    type Err = ParseError;
+
+    /// Parses `nullsfirst` or `nullslast`, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            _ if s.eq_ignore_ascii_case("nullsfirst") => Ok(Self::First),
+            _ if s.eq_ignore_ascii_case("nullslast") => Ok(Self::Last),
+            _ => Err(ParseError::InvalidSort),
+        }
+    }
+}
+
+impl Nulls {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::First => "NULLS FIRST",
+            Self::Last => "NULLS LAST",
+        }
+    }
+
+    /// Returns the nulls ordering as it appears in the `sort` query parameter.
+    pub fn as_query_str(&self) -> &'static str {
+        match self {
+            Self::First => "nullsfirst",
+            Self::Last => "nullslast",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{case::NameCase, sql::Database, ParseError};
+
+    use super::{Nulls, Sort, SortBy};
+
+    #[test]
+    fn test_new() {
+        let cases = [
+            ("price-desc", "price", SortBy::DESC),
+            ("price-asc", "price", SortBy::ASC),
+            ("-price", "price", SortBy::DESC),
+            ("createdAt", "createdAt", SortBy::ASC),
+            ("desc", "desc", SortBy::ASC),
+        ];
+
+        for (str, field, sort_by) in cases {
+            let sort = Sort::new(str).unwrap();
+            assert_eq!(
+                (sort.field.as_str(), sort.sort_by),
+                (field, sort_by),
+                "{}",
+                str
+            );
+        }
+    }
+
+    #[test]
+    fn test_new_invalid() {
+        let cases = [
+            ("", ParseError::InvalidSort),
+            ("-", ParseError::InvalidSort),
+            ("-desc", ParseError::InvalidSort),
+            ("-price-desc", ParseError::InvalidSort),
+            ("price-up", ParseError::InvalidSortBy),
+        ];
+
+        for (str, err) in cases {
+            assert_eq!(Sort::new(str).unwrap_err(), err, "{}", str);
+        }
+    }
+
+    #[test]
+    fn test_new_nulls() {
+        let sort = Sort::new("shippedAt-desc-nullslast").unwrap();
+        assert_eq!(sort.sort_by, SortBy::DESC);
+        assert_eq!(sort.nulls, Some(Nulls::Last));
+        assert_eq!(sort.to_query_string(), "shippedAt-desc-nullslast");
+
+        let sort = Sort::new("-shippedAt-nullsfirst").unwrap();
+        assert_eq!(sort.sort_by, SortBy::DESC);
+        assert_eq!(sort.nulls, Some(Nulls::First));
+
+        assert_eq!(Sort::new("shippedAt-desc").unwrap().nulls, None);
+        assert_eq!(
+            Sort::new("-nullslast").unwrap_err(),
+            ParseError::InvalidSort
+        );
+    }
+
+    #[test]
+    fn test_to_sql_map_table_nulls() {
+        let sort = Sort::new("shippedAt-desc-nullslast").unwrap();
+        let table = Some(&"orders");
+        let case = Some(NameCase::Snake);
+
+        assert_eq!(
+            sort.to_sql_map_table(table, case, &Database::Postgres),
+            "orders.shipped_at DESC NULLS LAST"
+        );
+        assert_eq!(
+            sort.to_sql_map_table(table, case, &Database::MySQL),
+            "orders.shipped_at IS NULL, orders.shipped_at DESC"
+        );
+
+        let sort = Sort::new("shippedAt-asc-nullsfirst").unwrap();
+        assert_eq!(
+            sort.to_sql_map_table(None, case, &Database::MySQL),
+            "shipped_at IS NOT NULL, shipped_at ASC"
+        );
+
+        assert_eq!(
+            sort.to_sql_map_table(None, case, &Database::Mssql),
+            "CASE WHEN shipped_at IS NULL THEN 0 ELSE 1 END, shipped_at ASC"
+        );
+
+        let sort = Sort::new("shippedAt-desc").unwrap();
+        assert_eq!(
+            sort.to_sql_map_table(None, case, &Database::MySQL),
+            "shipped_at DESC"
+        );
+    }
+
+    #[test]
+    fn test_to_sql_without_case() {
+        let sort = Sort::new("createdAt-desc").unwrap();
+
+        assert_eq!(sort.to_sql(String::new(), None), "createdAt DESC");
+        assert_eq!(
+            sort.to_sql(String::new(), Some(NameCase::Snake)),
+            "created_at DESC"
+        );
+    }
+
+    #[test]
+    fn test_from_str_mixed_case() {
+        for (str, sort_by) in [
+            ("price-ASC", SortBy::ASC),
+            ("price-Desc", SortBy::DESC),
+            ("price-desc-NullsLast", SortBy::DESC),
+        ] {
+            let sort: Sort = str.parse().unwrap();
+            assert_eq!(
+                (sort.field.as_str(), sort.sort_by),
+                ("price", sort_by),
+                "{}",
+                str
+            );
+        }
+
+        assert_eq!("DESC".parse::<SortBy>(), Ok(SortBy::DESC));
+        assert_eq!("Asc".parse::<SortBy>(), Ok(SortBy::ASC));
+        assert_eq!("up".parse::<SortBy>(), Err(ParseError::InvalidSortBy));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for str in ["price-desc", "createdAt-asc", "shippedAt-desc-nullsfirst"] {
+            let sort: Sort = str.parse().unwrap();
+            assert_eq!(sort.to_string(), str);
+            assert_eq!(sort.to_string().parse::<Sort>().unwrap(), sort);
+        }
+
+        let sort: Sort = "-price".parse().unwrap();
+        assert_eq!(sort.to_string(), "price-desc");
+        assert_eq!(SortBy::DESC.to_string(), "desc");
+        assert_eq!(SortBy::DESC.as_str(), "DESC");
+    }
 }
@@ -1,12 +1,328 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    rc::Rc,
+    time::Duration,
+};
 
-use convert_case::{Case, Casing};
-
-use crate::UrlQuery;
+use crate::{
+    case::NameCase,
+    dialect::Dialect,
+    filter::{escape_like, Filter, Operator},
+    safety::{
+        check_single_statement, contains_placeholder, count_placeholders, find_unquoted,
+        SqlSafetyError,
+    },
+    schema::QuerySchema,
+    sort::{Sort, SortBy},
+    value::{ArgValue, FieldType},
+    ParseError, UrlQuery,
+};
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Database {
     Postgres,
     MySQL,
+    Sqlite,
+    Mssql,
+    Oracle,
+}
+
+/// How the filters of the url query are joined, see `QueryBuilder::filter_combinator`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Combinator {
+    #[default]
+    And,
+    Or,
+}
+
+/// How the placeholders of the url query are rendered, see `QueryBuilder::placeholder_style`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    /// The placeholders of the database, eg `$1` or `?`.
+    #[default]
+    Positional,
+    /// `:user_id`
+    Named,
+    /// `@user_id`
+    NamedAt,
+}
+
+/// The row-locking clause rendered at the end of the statement, see `QueryBuilder::lock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// `FOR UPDATE`, with `SKIP LOCKED` to skip rows locked by other transactions.
+    ForUpdate { skip_locked: bool },
+    /// `FOR SHARE`, with `SKIP LOCKED` to skip rows locked by other transactions.
+    ForShare { skip_locked: bool },
+}
+
+impl LockMode {
+    /// Returns the clause, eg `FOR UPDATE SKIP LOCKED`. MySQL before 8.0 has neither `FOR SHARE`
+    /// nor `SKIP LOCKED`, so `legacy_mysql` gets `LOCK IN SHARE MODE` or `FOR UPDATE`.
+    pub(crate) fn to_sql(self, legacy_mysql: bool) -> &'static str {
+        match (self, legacy_mysql) {
+            (Self::ForUpdate { .. }, true) => "FOR UPDATE",
+            (Self::ForShare { .. }, true) => "LOCK IN SHARE MODE",
+            (Self::ForUpdate { skip_locked: true }, false) => "FOR UPDATE SKIP LOCKED",
+            (Self::ForUpdate { skip_locked: false }, false) => "FOR UPDATE",
+            (Self::ForShare { skip_locked: true }, false) => "FOR SHARE SKIP LOCKED",
+            (Self::ForShare { skip_locked: false }, false) => "FOR SHARE",
+        }
+    }
+}
+
+/// A description of the shape of a query, without any of the values to bind.
+#[derive(Debug, PartialEq)]
+pub struct QueryInfo<'q> {
+    /// The field and operator of each filter.
+    pub filters: Vec<(&'q str, Operator)>,
+    pub groups: &'q [String],
+    pub sorts: &'q [Sort],
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+/// A value to bind, returned by `QueryBuilder::build` in the order of the placeholders.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    /// The field of the url query, or the name the value was bound under, eg the column of a
+    /// `base_filter` or `"limit"`.
+    pub field: String,
+    pub value: String,
+    /// The operator the value is compared with, if it comes from a filter.
+    pub comparator: Option<Operator>,
+    /// The number of the placeholder the value is bound to, eg `2` for `$2`.
+    pub index: usize,
+}
+
+impl Arg {
+    fn new(field: &str, value: &str, comparator: Option<Operator>, index: usize) -> Self {
+        Self {
+            field: field.to_owned(),
+            value: value.to_owned(),
+            comparator,
+            index,
+        }
+    }
+
+    /// Returns the `(field, value)` pair the builder used to return.
+    #[deprecated(note = "use the fields of `Arg` instead")]
+    pub fn into_tuple(self) -> (String, String) {
+        (self.field, self.value)
+    }
+}
+
+/// Returns the args as the `(field, value)` pairs the builder used to return, to migrate code
+/// binding them gradually.
+pub fn into_tuples(args: Vec<Arg>) -> Vec<(String, String)> {
+    args.into_iter().map(|arg| (arg.field, arg.value)).collect()
+}
+
+type TimeoutPolicy<'a> = dyn Fn(&QueryInfo) -> Option<Duration> + 'a;
+
+type ValueTransform<'a> = dyn Fn(&str) -> Result<String, String> + 'a;
+
+/// The statements returned by `QueryBuilder::build_paginated`.
+#[derive(Debug, PartialEq)]
+pub struct Paginated {
+    /// The page of rows, with ORDER BY, LIMIT and OFFSET.
+    pub data_sql: String,
+    /// The total number of rows.
+    pub count_sql: String,
+    pub args: Vec<Arg>,
+    count_args_len: usize,
+}
+
+impl Paginated {
+    /// Returns the args to bind to the count statement, which exclude pagination binds.
+    pub fn count_args(&self) -> &[Arg] {
+        &self.args[..self.count_args_len]
+    }
+}
+
+/// The statement returned by `QueryBuilder::build_parts`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuiltQuery {
+    pub sql: String,
+    pub args: Vec<Arg>,
+    /// The number of the next bind parameter, to append more conditions to the SQL.
+    pub next_bind_index: usize,
+    /// The limit rendered in the SQL, see `QueryBuilder::applied_limit`.
+    pub limit: Option<u64>,
+    /// The offset rendered in the SQL.
+    pub offset: Option<u64>,
+    /// Whether the builder rendered a WHERE clause.
+    pub has_where: bool,
+    /// The filters and havings replaced by `(1 = 0)` because their value was refused, eg by
+    /// `validate_value`, the `after` and cursors left out for the same reason, and the `and_where`
+    /// fragments replaced because they don't have a value for each `{}`. `build_checked` returns them as errors instead. Their values are never bound.
+    pub rejected: Vec<BuildError>,
+    /// The field of each arg, before `placeholder_style` renames them.
+    fields: Vec<String>,
+}
+
+/// The problems found by `QueryBuilder::build_checked`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildError {
+    /// A check of the builder, eg `check_denied_fields`, failed.
+    Parse(ParseError),
+    /// A field used in the clause isn't mapped to a column, nor passed to `pass_through`.
+    UnmappedField { field: String, clause: &'static str },
+    /// The url query has a having, but no group.
+    HavingWithoutGroup(String),
+    /// A fragment passed to `and_where` doesn't have a value for each `{}`.
+    PlaceholderMismatch {
+        fragment: String,
+        placeholders: usize,
+        values: usize,
+    },
+    /// The url query has an offset, but no limit is applied, so the offset is ignored.
+    OffsetWithoutLimit,
+    /// The query is paginated on SQL Server without a sort, so the order of the rows, and the
+    /// rows of each page, are undefined.
+    PaginationWithoutSort,
+    /// The complete statement isn't safe to run, see `QueryBuilder::try_build`.
+    Safety(SqlSafetyError),
+    /// A value of the url query was refused by `transform_value` or `validate_value`.
+    InvalidValue {
+        field: String,
+        value: String,
+        reason: String,
+    },
+    /// The url query has a search, but the database has no full-text search, see
+    /// `QueryBuilder::full_text_search`.
+    UnsupportedSearch,
+    /// A filter uses an operator which the database doesn't have, eg `overlaps` on MySQL, or which
+    /// can't be rendered for its field, eg `has` on a `map_exists` field.
+    UnsupportedOperator { field: String, operator: Operator },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BuildError::Parse(e) => write!(f, "{}", e),
+            BuildError::UnmappedField { field, clause } => {
+                write!(f, "field {} in {} is not mapped to a column", field, clause)
+            }
+            BuildError::HavingWithoutGroup(field) => {
+                write!(f, "having on {} without a group", field)
+            }
+            BuildError::PlaceholderMismatch {
+                fragment,
+                placeholders,
+                values,
+            } => write!(
+                f,
+                "condition {} has {} placeholders but {} values",
+                fragment, placeholders, values
+            ),
+            BuildError::OffsetWithoutLimit => write!(f, "offset is ignored without a limit"),
+            BuildError::PaginationWithoutSort => write!(f, "pagination requires a sort"),
+            BuildError::Safety(e) => write!(f, "{}", e),
+            BuildError::InvalidValue {
+                field,
+                value,
+                reason,
+            } => write!(f, "invalid value {} for field {}: {}", value, field, reason),
+            BuildError::UnsupportedSearch => {
+                write!(f, "full-text search is not supported by the database")
+            }
+            BuildError::UnsupportedOperator { field, operator } => write!(
+                f,
+                "operator {} on field {} is not supported by the database",
+                operator.as_query_str(),
+                field
+            ),
+        }
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<ParseError> for BuildError {
+    fn from(e: ParseError) -> Self {
+        BuildError::Parse(e)
+    }
+}
+
+impl From<SqlSafetyError> for BuildError {
+    fn from(e: SqlSafetyError) -> Self {
+        BuildError::Safety(e)
+    }
+}
+
+/// The kind of statement a `QueryBuilder` generates.
+#[derive(Clone)]
+enum Statement {
+    Select,
+    Delete,
+    /// The columns to SET.
+    Update(Vec<String>),
+}
+
+/// A JOIN clause or a fragment added to a `QueryBuilder` with `append`, rendered in the order
+/// they were added.
+#[derive(Clone)]
+struct Join {
+    /// The joined table, if the join was added with `join` or `left_join`.
+    table: Option<String>,
+    sql: String,
+    /// Only render the join when a field mapped to the table is used.
+    when_used: bool,
+}
+
+/// A column of the select list, see `QueryBuilder::columns`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnInfo {
+    /// The selected expression, eg `users.first_name` or `COUNT(*) OVER()`.
+    pub name: String,
+    /// The alias given with `AS`, if any.
+    pub alias: Option<String>,
+}
+
+impl ColumnInfo {
+    /// Splits the alias off the column, on the last ` AS ` outside of parentheses and quotes, so
+    /// `CAST(price AS numeric)` has no alias.
+    fn new(column: &str) -> Self {
+        let bytes = column.as_bytes();
+        let (mut depth, mut quote, mut alias_at) = (0, None, None);
+        for (i, &b) in bytes.iter().enumerate() {
+            match (quote, b) {
+                (Some(q), _) if b == q => quote = None,
+                (Some(_), _) => {}
+                (None, b'\'' | b'"' | b'`') => quote = Some(b),
+                (None, b'(') => depth += 1,
+                (None, b')') => depth -= 1,
+                (None, b' ') if depth == 0 => {
+                    let word = column.get(i..i + " AS ".len());
+                    if word.is_some_and(|word| word.eq_ignore_ascii_case(" AS ")) {
+                        alias_at = Some(i);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match alias_at {
+            Some(i) => Self {
+                name: column[..i].trim().to_owned(),
+                alias: Some(column[i + " AS ".len()..].trim().to_owned()),
+            },
+            None => Self {
+                name: column.trim().to_owned(),
+                alias: None,
+            },
+        }
+    }
+
+    /// Returns the name of the column in the result rows: the alias, or the column without its
+    /// table.
+    pub fn output_name(&self) -> &str {
+        match self.alias {
+            Some(ref alias) => alias,
+            None => self.name.rsplit('.').next().unwrap_or(&self.name),
+        }
+    }
 }
 
 /// Generates an SQL query
@@ -26,17 +342,78 @@ pub enum Database {
 /// assert_eq!(sql, "SELECT id, status FROM orders WHERE userId = $1 AND userName = $2");
 /// assert_eq!(args.len(), 2);
 /// ```
+///
+/// The builder is consumed by `build`. Clone it to build several statements from one parsed
+/// url query, eg for another database.
+#[derive(Clone)]
 pub struct QueryBuilder<'a> {
     url_query: UrlQuery,
-    database: Database,
-    map_columns: HashMap<&'a str, &'a str>,
+    statement: Statement,
+    allow_unfiltered: bool,
+    dialect: Rc<dyn Dialect + 'a>,
+    map_columns: HashMap<String, String>,
+    rename_columns: HashMap<&'a str, &'a str>,
+    deny_fields: HashSet<&'a str>,
+    ctes: Vec<(String, String)>,
+    cte_binds: usize,
+    joins: Vec<Join>,
+    relations: HashMap<&'a str, &'a str>,
+    json_columns: HashSet<&'a str>,
+    array_columns: HashSet<&'a str>,
+    full_text_search: Option<(Vec<&'a str>, &'a str)>,
+    /// The bind number of the search, reused by the relevance sort.
+    search_idx: Option<usize>,
+    base_filters: Vec<Filter>,
+    conditions: Vec<(String, Vec<(String, String)>)>,
+    soft_delete: Option<&'a str>,
+    /// Whether a filter on the soft delete column leaves out the `IS NULL` guard.
+    soft_delete_override: bool,
+    filter_combinator: Combinator,
+    map_expressions: HashMap<&'a str, &'a str>,
+    map_aggregates: HashMap<&'a str, &'a str>,
+    map_exists: HashMap<&'a str, &'a str>,
+    computed_sorts: HashMap<&'a str, &'a str>,
+    default_sorts: Vec<Sort>,
+    tie_breaker: Option<Sort>,
+    tie_breaker_always: bool,
     shift_bind: usize,
-    convert_case: Option<Case>,
+    convert_case: Option<NameCase>,
+    skip_case: HashSet<&'a str>,
+    pass_through: HashSet<&'a str>,
+    trace_values: bool,
+    placeholder_style: PlaceholderStyle,
+    dedupe_binds: bool,
+    escape_like: bool,
+    #[cfg(feature = "chrono")]
+    expand_dates: bool,
+    #[cfg(feature = "chrono")]
+    relative_dates: Option<chrono::FixedOffset>,
+    #[cfg(feature = "chrono")]
+    now: Option<chrono::DateTime<chrono::Utc>>,
+    field_types: HashMap<&'a str, FieldType>,
+    bind_pagination: bool,
+    skip_pagination: bool,
+    max_limit: Option<u64>,
+    default_limit: Option<u64>,
+    seek_on_sort: bool,
+    cursor_tiebreaker: Option<&'a str>,
+    timeout_policy: Option<Rc<TimeoutPolicy<'a>>>,
+    value_transforms: Vec<(&'a str, Rc<ValueTransform<'a>>)>,
+    with_total: bool,
+    select_end: Option<usize>,
+    select: Option<(String, Vec<String>)>,
+    quote_identifiers: bool,
+    lock: Option<LockMode>,
+    legacy_mysql: bool,
+    columns: Vec<ColumnInfo>,
+    /// The values `append_where` and `append_having` refused, see `BuiltQuery::rejected`.
+    rejected: Vec<BuildError>,
     sql: String,
 }
 
 impl<'a> QueryBuilder<'a> {
-    /// Returns a QueryBuilder.
+    /// Returns a QueryBuilder. If the url query has sparse fields for `table`, eg
+    /// `fields[users]=id,email`, those are selected instead of `columns`.
     ///
     /// # Examples
     ///
@@ -46,14 +423,77 @@ impl<'a> QueryBuilder<'a> {
     /// let (sql, args) = QueryBuilder::new("users", vec!["id", "first_name"], url_query).build();
     /// ```
     pub fn new(table: &str, columns: Vec<&str>, url_query: UrlQuery) -> Self {
-        let sql = gen_sql_select(table, columns);
+        let columns = match url_query.sparse_fields(table) {
+            Some(fields) => fields.iter().map(|f| f.as_str()).collect(),
+            None => columns,
+        };
+        let sql = gen_sql_select(table, columns.clone());
+        let select = Some((
+            table.to_owned(),
+            columns.iter().map(|c| c.to_string()).collect(),
+        ));
+        let columns = columns.into_iter().map(ColumnInfo::new).collect();
+        let select_end = sql.len() - " FROM ".len() - table.len();
 
         Self {
             url_query,
-            database: Database::Postgres,
+            statement: Statement::Select,
+            allow_unfiltered: false,
+            dialect: Rc::new(Database::Postgres),
             map_columns: HashMap::default(),
+            rename_columns: HashMap::default(),
+            deny_fields: HashSet::default(),
+            ctes: Vec::new(),
+            cte_binds: 0,
+            joins: Vec::new(),
+            relations: HashMap::default(),
+            json_columns: HashSet::default(),
+            array_columns: HashSet::default(),
+            full_text_search: None,
+            search_idx: None,
+            base_filters: Vec::new(),
+            conditions: Vec::new(),
+            soft_delete: None,
+            soft_delete_override: false,
+            filter_combinator: Combinator::And,
+            map_expressions: HashMap::default(),
+            map_aggregates: HashMap::default(),
+            map_exists: HashMap::default(),
+            computed_sorts: HashMap::default(),
+            default_sorts: Vec::new(),
+            tie_breaker: None,
+            tie_breaker_always: false,
             shift_bind: 0,
             convert_case: None,
+            skip_case: HashSet::default(),
+            pass_through: HashSet::default(),
+            trace_values: false,
+            placeholder_style: PlaceholderStyle::Positional,
+            dedupe_binds: false,
+            escape_like: false,
+            #[cfg(feature = "chrono")]
+            expand_dates: false,
+            #[cfg(feature = "chrono")]
+            relative_dates: None,
+            #[cfg(feature = "chrono")]
+            now: None,
+            field_types: HashMap::default(),
+            bind_pagination: false,
+            skip_pagination: false,
+            max_limit: None,
+            default_limit: None,
+            seek_on_sort: false,
+            cursor_tiebreaker: None,
+            timeout_policy: None,
+            value_transforms: Vec::new(),
+            with_total: false,
+            select_end: Some(select_end),
+            select,
+            quote_identifiers: false,
+            lock: None,
+            legacy_mysql: false,
+            columns,
+            rejected: Vec::new(),
             sql,
         }
     }
@@ -70,264 +510,2843 @@ impl<'a> QueryBuilder<'a> {
     pub fn from_str(sql: &str, url_query: UrlQuery) -> Self {
         Self {
             url_query,
-            database: Database::Postgres,
+            statement: Statement::Select,
+            allow_unfiltered: false,
+            dialect: Rc::new(Database::Postgres),
             map_columns: HashMap::default(),
+            rename_columns: HashMap::default(),
+            deny_fields: HashSet::default(),
+            ctes: Vec::new(),
+            cte_binds: 0,
+            joins: Vec::new(),
+            relations: HashMap::default(),
+            json_columns: HashSet::default(),
+            array_columns: HashSet::default(),
+            full_text_search: None,
+            search_idx: None,
+            base_filters: Vec::new(),
+            conditions: Vec::new(),
+            soft_delete: None,
+            soft_delete_override: false,
+            filter_combinator: Combinator::And,
+            map_expressions: HashMap::default(),
+            map_aggregates: HashMap::default(),
+            map_exists: HashMap::default(),
+            computed_sorts: HashMap::default(),
+            default_sorts: Vec::new(),
+            tie_breaker: None,
+            tie_breaker_always: false,
             shift_bind: 0,
             convert_case: None,
+            skip_case: HashSet::default(),
+            pass_through: HashSet::default(),
+            trace_values: false,
+            placeholder_style: PlaceholderStyle::Positional,
+            dedupe_binds: false,
+            escape_like: false,
+            #[cfg(feature = "chrono")]
+            expand_dates: false,
+            #[cfg(feature = "chrono")]
+            relative_dates: None,
+            #[cfg(feature = "chrono")]
+            now: None,
+            field_types: HashMap::default(),
+            bind_pagination: false,
+            skip_pagination: false,
+            max_limit: None,
+            default_limit: None,
+            seek_on_sort: false,
+            cursor_tiebreaker: None,
+            timeout_policy: None,
+            value_transforms: Vec::new(),
+            with_total: false,
+            select_end: None,
+            select: None,
+            quote_identifiers: false,
+            lock: None,
+            legacy_mysql: false,
+            columns: Vec::new(),
+            rejected: Vec::new(),
             sql: sql.into(),
         }
     }
 
-    /// Set the database
-    pub fn set_database(mut self, database: Database) -> Self {
-        self.database = database;
+    /// Returns a QueryBuilder generating `DELETE FROM table` with the WHERE clause of the url
+    /// query. A delete without a filter from the url query, eg from an empty query string, is
+    /// refused unless `allow_unfiltered` is set, even if `base_filter`, `exclude_soft_deleted` or
+    /// `and_where` add a WHERE clause: `try_build` returns `SqlSafetyError::UnfilteredDelete` and
+    /// `build` appends `WHERE 1 = 0`, which every database accepts, so nothing is deleted.
+    ///
+    /// MySQL also gets the ORDER BY and the LIMIT of the url query. Postgres doesn't support
+    /// them in a DELETE, so they are left out, along with the OFFSET on both databases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1&status=paid", ["userId", "status"]).unwrap();
+    ///
+    /// let (sql, args) = QueryBuilder::delete("orders", parsed).try_build().unwrap();
+    ///
+    /// assert_eq!(sql, "DELETE FROM orders WHERE userId = $1 AND status = $2");
+    /// assert_eq!(args.len(), 2);
+    /// ```
+    pub fn delete(table: &str, url_query: UrlQuery) -> Self {
+        let mut builder = Self::from_str(&format!("DELETE FROM {}", table), url_query);
+        builder.statement = Statement::Delete;
 
-        self
+        builder
     }
 
-    /// Append anything to the SQL.
-    pub fn append(mut self, sql: &str) -> Self {
-        self.sql.push_str(" ");
-        self.sql.push_str(sql);
+    /// Returns a QueryBuilder generating `UPDATE table SET column = $1, ...` with the WHERE clause
+    /// of the url query. The SET values are bound by the caller, before the returned args, which
+    /// only hold the binds of the WHERE clause and are numbered after the SET placeholders.
+    ///
+    /// Like `delete`, an update without a filter from the url query is refused unless
+    /// `allow_unfiltered` is set, whatever the base filters, and only MySQL gets the ORDER BY and
+    /// the LIMIT of the url query.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1", ["userId"]).unwrap();
+    ///
+    /// let (sql, args) = QueryBuilder::update("orders", vec!["status", "note"], parsed)
+    ///     .try_build()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(sql, "UPDATE orders SET status = $1, note = $2 WHERE userId = $3");
+    /// assert_eq!(args.len(), 1);
+    /// ```
+    pub fn update(table: &str, set_columns: Vec<&str>, url_query: UrlQuery) -> Self {
+        let mut builder = Self::from_str(&format!("UPDATE {}", table), url_query);
+        let set_columns = set_columns.into_iter().map(|c| c.to_owned()).collect();
+        builder.statement = Statement::Update(set_columns);
+
+        builder
+    }
+
+    /// Allow a delete or an update without a filter from the url query, which affects every row
+    /// the other conditions match. See `delete`.
+    pub fn allow_unfiltered(mut self) -> Self {
+        self.allow_unfiltered = true;
 
         self
     }
 
-    /// Provide a HashMap containing table and column to map ambiguous columns.
-    pub fn map_columns(mut self, map_columns: HashMap<&'a str, &'a str>) -> Self {
-        self.map_columns = map_columns;
+    /// Render the SQL with a custom dialect, eg for a database which isn't a `Database`. The whole
+    /// statement is rendered by the dialect, see `Dialect`.
+    pub fn set_dialect(mut self, dialect: Box<dyn Dialect + 'a>) -> Self {
+        self.dialect = Rc::from(dialect);
 
         self
     }
 
-    /// Shifts the number of the bind parameter for postgres. For example, if you call this
-    /// method with a value of 1, the first arg you'll need to bind to the SQL will be $2.
-    pub fn shift_bind(mut self, x: usize) -> Self {
-        self.shift_bind = x;
+    /// Lock the selected rows, eg with `FOR UPDATE SKIP LOCKED` for a worker queue. The clause is
+    /// rendered at the very end of the statement, after LIMIT and OFFSET. SQLite locks the whole
+    /// database instead and SQL Server locks with table hints, so the clause is left out there, as
+    /// is `ForShare` on Oracle.
+    pub fn lock(mut self, lock: LockMode) -> Self {
+        self.lock = Some(lock);
 
         self
     }
 
-    pub fn convert_case(mut self, case: Case) -> Self {
-        self.convert_case = Some(case);
+    /// Target MySQL before 8.0, which doesn't support `SKIP LOCKED` or `FOR SHARE`. See `lock`.
+    pub fn legacy_mysql(mut self) -> Self {
+        self.legacy_mysql = true;
 
         self
     }
 
-    /// Append the WHERE clause to the SQL. Does nothing if there are no queries/filters in the url query.
-    pub fn append_where(&mut self) -> Vec<(String, String)> {
-        let mut args: Vec<(String, String)> = Vec::new();
+    /// Set the database
+    pub fn set_database(mut self, database: Database) -> Self {
+        self.dialect = Rc::new(database);
 
-        // Filters:
-        let mut filterv = Vec::new();
-        for filter in self.url_query.filters.iter() {
-            let table = self.map_columns.get(filter.field.as_str());
-            filterv.push(filter.to_sql_map_table(
-                args.len() + self.shift_bind + 1,
-                table,
-                self.convert_case,
-                &self.database,
-            ));
-            args.push((filter.field.to_owned(), filter.value.to_owned()));
-        }
-        let filter = filterv.join(" AND ");
+        self
+    }
 
-        // WHERE clause
-        if filterv.len() > 0 {
-            self.sql.push_str(" WHERE ");
-            self.sql.push_str(&filter);
-        }
+    /// Append anything to the SQL before the WHERE clause. Fragments and joins are rendered by
+    /// `build` in the order they were added. Prefer `join_raw`, `and_where` and the other builder
+    /// methods, which are rendered in the right clause whatever order they are called in.
+    pub fn append(mut self, sql: &str) -> Self {
+        self.joins.push(Join {
+            table: None,
+            sql: sql.to_owned(),
+            when_used: false,
+        });
 
-        args
+        self
     }
 
-    /// Append a GROUP BY to the SQL. Does nothing if there is no group in the url query.
-    pub fn append_group(&mut self) {
-        if self.url_query.group.is_none() {
-            return;
-        };
+    /// Add a JOIN clause, eg `JOIN users ON users.id = orders.user_id`. Joins are rendered by
+    /// `build` after the SQL given so far, before the WHERE clause, in the order they were added
+    /// along with the fragments of `append`.
+    pub fn join_raw(mut self, sql: &str) -> Self {
+        self.joins.push(Join {
+            table: None,
+            sql: sql.to_owned(),
+            when_used: false,
+        });
 
-        let group = self.url_query.group.as_ref().unwrap();
-        self.sql.push_str(" GROUP BY ");
-        if let Some(table) = self.map_columns.get(group.as_str()) {
-            self.sql.push_str(table);
-            self.sql.push_str(".");
-        }
+        self
+    }
 
-        match self.convert_case {
-            Some(c) => self.sql.push_str(&group.to_case(c)),
-            None => self.sql.push_str(&group),
-        }
+    /// Add `JOIN table ON on`. Joining the same table again does nothing, so each mapped field
+    /// can make sure its table is joined.
+    pub fn join(self, table: &str, on: &str) -> Self {
+        self.push_join("JOIN", table, on, false)
     }
 
-    /// Append an ORDER BY to the SQL. Does nothing if there is no sort in the url query.
-    pub fn append_sort(&mut self) {
-        if self.url_query.sort.is_none() {
-            return;
-        }
+    /// Add `LEFT JOIN table ON on`. See `join`.
+    pub fn left_join(self, table: &str, on: &str) -> Self {
+        self.push_join("LEFT JOIN", table, on, false)
+    }
 
-        let sort = self.url_query.sort.as_ref().unwrap();
-        let table = self.map_columns.get(sort.field.as_str());
-        self.sql.push_str(" ORDER BY ");
-        self.sql
-            .push_str(&sort.to_sql_map_table(table, self.convert_case));
+    /// Like `join`, but the join is only rendered when the url query uses a field which
+    /// `map_columns` maps to the table.
+    pub fn join_when_used(self, table: &str, on: &str) -> Self {
+        self.push_join("JOIN", table, on, true)
     }
 
-    /// Returns SQL statement along with a list of columns and args to bind.
-    pub fn build(mut self) -> (String, Vec<(String, String)>) {
-        // returns bind args
-        let args = self.append_where();
+    /// Register a relation which fields can reference with a dot, eg `user.name` with
+    /// `relation("user", "users", "users.id = orders.user_id")`. The table is joined once when
+    /// a field of the relation is used, and the case is only converted after the dot, eg
+    /// `user.firstName` becomes `users.first_name`. See `check_relations`.
+    pub fn relation(mut self, name: &'a str, table: &'a str, on: &str) -> Self {
+        self.relations.insert(name, table);
 
-        self.append_group();
+        self.push_join("JOIN", table, on, true)
+    }
 
-        self.append_sort();
+    /// Declare a JSON column whose paths fields can reference with dots, eg `metadata.color`
+    /// renders `metadata->>'color'` on Postgres, and `metadata.size.width`
+    /// `metadata->'size'->>'width'`. The case of the path isn't converted. Filters with `gt`,
+    /// `ge`, `lt` and `le` on a path which `field_types` types as `Int` or `Float` compare
+    /// numerically, eg `(metadata->>'price')::numeric >= $1`, other paths compare as text. Allow
+    /// the paths with `url_query::Options::json_fields`.
+    pub fn json_column(mut self, column: &'a str) -> Self {
+        self.json_columns.insert(column);
 
-        // Limit & offset:
-        if let Ok(limit) = self.url_query.check_limit() {
-            append_limit(&mut self.sql, limit);
+        self
+    }
 
-            if let Ok(offset) = self.url_query.check_offset() {
-                append_offset(&mut self.sql, offset);
-            }
-        }
+    /// Declare an array column, eg `text[]`, which filters can compare with `has` and `overlaps`,
+    /// eg `filter[]=tags-has-rust` renders `$1 = ANY(tags)` and `filter[]=tags-overlaps-rust,sql`
+    /// `tags && $1`. The list of `overlaps` is bound as one arg, `rust,sql`, which `build_typed`
+    /// parses into an `ArgValue::Array` of the elements, typed with `field_types`, to bind as a
+    /// `Vec`. `build_checked` returns `ParseError::OperatorNotAllowed` for these operators on
+    /// other fields, and `BuildError::UnsupportedOperator` on databases without arrays, where
+    /// `build` renders `(1 = 0)` instead.
+    pub fn array_column(mut self, column: &'a str) -> Self {
+        self.array_columns.insert(column);
 
-        (self.sql, args)
+        self
     }
-}
 
-fn gen_sql_select(table: &str, columns: Vec<&str>) -> String {
-    let mut sql = String::from("SELECT ");
-    let columns = columns.join(", ");
-    sql.push_str(&columns);
-    sql.push_str(" FROM ");
-    sql.push_str(table);
-    sql
-}
+    /// Search the text of the columns for the `q` parameter of the url query, eg `q=rust async`
+    /// renders `to_tsvector('english', coalesce(title, '') || ' ' || coalesce(description, ''))
+    /// @@ plainto_tsquery('english', $1)` on Postgres, along with the filters, binding the text
+    /// as `q`. `sort=relevance-desc` then sorts by the `ts_rank` of the rows. MySQL renders
+    /// `MATCH(title, description) AGAINST (? IN NATURAL LANGUAGE MODE)` and ignores the relevance
+    /// sort. Nothing is rendered without `q`, which is only parsed with `Options::search`. The
+    /// other databases have no full-text search: the search renders `(1 = 0)`, and
+    /// `build_checked` returns `BuildError::UnsupportedSearch`.
+    pub fn full_text_search(mut self, columns: &[&'a str], config: &'a str) -> Self {
+        self.full_text_search = Some((columns.to_vec(), config));
 
-fn append_limit(sql: &mut String, limit: &str) {
-    sql.push_str(" LIMIT ");
-    sql.push_str(limit);
-}
+        self
+    }
 
-fn append_offset(sql: &mut String, offset: &str) {
-    sql.push_str(" OFFSET ");
-    sql.push_str(offset);
-}
+    /// Returns the JSON column and the path of a field referencing a `json_column`.
+    fn json_path<'f>(&self, field: &'f str) -> Option<(&'f str, Vec<&'f str>)> {
+        let (column, path) = field.split_once('.')?;
 
-/// Bind args to an sqlx query with the required types.
-///
-/// ```ignore
-/// pub async fn get_orders(
-///     pool: &PgPool,
-///     query: UrlQuery,
-/// ) -> Result<Vec<Order>, Either<sqlx::Error, ParseError>> {
-///     let (sql, args) = QueryBuilder::from_str(
-///         "SELECT * FROM orders",
-///         query,
-///     )
-///     .build();
-///
-///     let mut query = sqlx::query_as(&sql);
-///
-///     bind!(
-///         args => query,
-///         error: Either::Right(ParseError),
-///         "id" => Uuid,
-///         "userId" => i64
-///     );
-///
-///     Ok(query.fetch_all(pool).await.map_err(|e| Either::Left(e))?)
-/// }
-/// ```
-#[macro_export]
-macro_rules! sqlx_bind {
-    ( $args:ident => $query:ident, error: $error:expr, $( $x:expr => $t:ty ),* ) => {
-        {
-            for (column, arg) in $args {
-                match column.as_str() {
-                    $(
-                        $x => {
-                            let parsed: $t = arg.parse().map_err(|_| {
-                                $error
-                            })?;
-                            $query = $query.bind(parsed);
-                        }
-                    )*
-                    _ => {}
-                }
-            }
+        match self.json_columns.contains(column) {
+            true => Some((column, path.split('.').collect())),
+            false => None,
         }
-    };
-}
+    }
 
-#[cfg(test)]
-mod test {
-    use std::collections::HashMap;
+    fn push_join(mut self, kind: &str, table: &str, on: &str, when_used: bool) -> Self {
+        if self
+            .joins
+            .iter()
+            .any(|join| join.table.as_deref() == Some(table))
+        {
+            return self;
+        }
 
-    use convert_case::Case;
+        self.joins.push(Join {
+            table: Some(table.to_owned()),
+            sql: format!("{} {} ON {}", kind, table, on),
+            when_used,
+        });
 
-    use crate::{sql::Database, UrlQuery};
+        self
+    }
 
-    use super::QueryBuilder;
+    /// Provide a HashMap containing table and column to map ambiguous columns.
+    pub fn map_columns(self, map_columns: HashMap<&str, &str>) -> Self {
+        let map_columns = map_columns
+            .into_iter()
+            .map(|(field, table)| (field.to_owned(), table.to_owned()))
+            .collect();
 
-    #[test]
-    fn test_query_builder_from_str() {
-        let query =
-            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+        self.map_columns_owned(map_columns)
+    }
 
-        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+    /// Like `map_columns`, for a mapping built at runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// // eg loaded from a per-tenant schema
+    /// let tables = vec![("id".to_owned(), "orders".to_owned())];
+    /// let map_columns: HashMap<String, String> = tables.into_iter().collect();
+    ///
+    /// let parsed = UrlQuery::new("id=1", ["id"]).unwrap();
+    ///
+    /// let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+    ///     .map_columns_owned(map_columns)
+    ///     .build();
+    ///
+    /// assert_eq!(sql, "SELECT * FROM orders WHERE orders.id = $1");
+    /// ```
+    pub fn map_columns_owned(mut self, map_columns: HashMap<String, String>) -> Self {
+        self.map_columns = map_columns;
 
-        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
-            .convert_case(Case::Snake)
-            .build();
+        self
+    }
 
-        let expected = "SELECT * FROM orders \
-        WHERE user_id = $1 AND user_name = $2 \
-        AND order_id = $3 AND price >= $4 \
-        ORDER BY price DESC \
-        LIMIT 10 \
-        OFFSET 0";
+    /// Rename fields to the columns they're stored in, eg `customer` to `clientId`. The renamed
+    /// column goes through `convert_case` and keeps the table from `map_columns` of the field,
+    /// while the args still use the field name.
+    pub fn rename_columns(mut self, rename_columns: HashMap<&'a str, &'a str>) -> Self {
+        self.rename_columns = rename_columns;
 
-        assert_eq!(sql, expected);
-        assert_eq!(args.len(), 4);
+        self
     }
 
-    #[test]
-    fn test_query_builder_new() {
-        let query =
-            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+    /// Map fields to SQL expressions, eg `total` to `(price * quantity)`, so they can be filtered,
+    /// sorted and grouped on. The expression replaces the column entirely: it's used as is,
+    /// without `map_columns` or `convert_case`, and takes precedence over both.
+    pub fn map_expressions(mut self, map_expressions: HashMap<&'a str, &'a str>) -> Self {
+        self.map_expressions = map_expressions;
 
-        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+        self
+    }
 
-        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
-            .convert_case(Case::Snake)
-            .build();
+    /// Filter on a field of a related table with an EXISTS subquery instead of a join, which
+    /// would duplicate rows. In the subquery, `{op}` is replaced with the operator of the filter
+    /// and `{placeholder}` with its placeholder, eg
+    /// `SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku {op} {placeholder}`.
+    ///
+    /// Both bounds of a `range` go in the same subquery as `BETWEEN $1 AND $2`, and LIKE filters
+    /// render `LIKE $1 ESCAPE '\'`. The `has`, `overlaps` and `cont` operators don't fit the
+    /// subquery: their filters render `(1 = 0)`, see `build_checked`.
+    pub fn map_exists(mut self, field: &'a str, subquery: &'a str) -> Self {
+        self.map_exists.insert(field, subquery);
 
-        let expected = "SELECT id, status FROM orders \
-        WHERE user_id = $1 AND user_name = $2 \
-        AND order_id = $3 AND price >= $4 \
-        ORDER BY price DESC \
-        LIMIT 10 \
-        OFFSET 0";
+        self
+    }
 
-        assert_eq!(sql, expected);
-        assert_eq!(args.len(), 4);
+    /// Map the aggregate aliases of `url_query::Options::aggregates` to their SQL, eg `count` to
+    /// `COUNT(*)`, for use in the HAVING clause.
+    pub fn map_aggregates(mut self, map_aggregates: HashMap<&'a str, &'a str>) -> Self {
+        self.map_aggregates = map_aggregates;
+
+        self
     }
 
-    #[test]
-    fn test_query_builder_new_append_joins() {
-        let query =
-            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+    /// Quote the identifiers the builder emits, eg `"orders"."order"` on Postgres or
+    /// `` `orders`.`order` `` on MySQL, after `map_columns` and `convert_case` are applied. This
+    /// covers filters, groups, sorts and, with `new`, the table and select list. Expressions and
+    /// select list entries that aren't plain identifiers, like `COUNT(*) AS total`, are left as is.
+    pub fn quote_identifiers(mut self, quote_identifiers: bool) -> Self {
+        self.quote_identifiers = quote_identifiers;
 
-        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+        self
+    }
 
-        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
-            .append("JOIN users ON users.id = order.user_id")
-            .append("JOIN inventory ON inventory.id = order.inventory_id")
-            .convert_case(Case::Snake)
-            .build();
+    /// Provide fields which must never be filtered, sorted, grouped or projected on, even if they
+    /// are in the allowed fields of the url query. See `check_denied_fields`.
+    pub fn deny_fields(mut self, fields: impl IntoIterator<Item = &'a str>) -> Self {
+        self.deny_fields = fields.into_iter().collect();
 
-        let expected = "SELECT id, status FROM orders \
+        self
+    }
+
+    /// Register a field which sorts by a SQL expression, typically a correlated subquery, eg
+    /// `(SELECT MAX(o.created_at) FROM orders o WHERE o.customer_id = customers.id)`. The
+    /// expression is emitted verbatim followed by the direction, and the field may only be used
+    /// to sort. The field must still be in the allowed fields of the url query. See
+    /// `check_computed_sorts`.
+    pub fn computed_sortable(mut self, field: &'a str, sql: &'a str) -> Self {
+        self.computed_sorts.insert(field, sql);
+
+        self
+    }
+
+    /// Sort by `field` when the url query has no sort. Call it again to add more columns. The
+    /// default sort is rendered like a sort from the url query, so `map_columns` and
+    /// `convert_case` apply, and it is ignored entirely when the url query has a sort.
+    pub fn default_sort(mut self, field: &'a str, sort_by: SortBy) -> Self {
+        self.default_sorts.push(Sort {
+            field: field.to_owned(),
+            sort_by,
+            nulls: None,
+            raw: None,
+        });
+
+        self
+    }
+
+    /// Append `field` to the ORDER BY so rows with equal sort values keep a stable order, eg
+    /// `ORDER BY price DESC, id ASC`. It's skipped if the field is already sorted on, and does
+    /// nothing without an ORDER BY unless `tie_breaker_always` is used.
+    pub fn tie_breaker(mut self, field: &'a str, sort_by: SortBy) -> Self {
+        self.tie_breaker = Some(Sort {
+            field: field.to_owned(),
+            sort_by,
+            nulls: None,
+            raw: None,
+        });
+
+        self
+    }
+
+    /// Add an ORDER BY on the `tie_breaker` alone when there is no sort.
+    pub fn tie_breaker_always(mut self) -> Self {
+        self.tie_breaker_always = true;
+
+        self
+    }
+
+    /// Add a condition on `column` which applies whatever the url query is, eg tenant scoping with
+    /// `base_filter("tenant_id", Operator::EQ, tenant_id)`. Base filters come first in the WHERE
+    /// clause and their args come first in the args, under the column name. The column is used
+    /// as is, without `map_columns` or `convert_case`.
+    pub fn base_filter(mut self, column: &str, operator: Operator, value: &str) -> Self {
+        self.base_filters
+            .push(Filter::from_key_value(column, value, operator));
+
+        self
+    }
+
+    /// Transform the values of the url query for `field` before they are bound, eg to lowercase
+    /// emails with `transform_value("email", |v| Ok(v.to_lowercase()))`. The transforms and
+    /// validations of a field are applied in the order they are added. An `Err` refuses the value
+    /// with a reason: `build_checked` returns `BuildError::InvalidValue`, while `build` renders
+    /// `(1 = 0)` instead of the filter, see `BuiltQuery::rejected`. Each element of an `overlaps` list is transformed
+    /// on its own.
+    pub fn transform_value(
+        mut self,
+        field: &'a str,
+        transform: impl Fn(&str) -> Result<String, String> + 'a,
+    ) -> Self {
+        self.value_transforms.push((field, Rc::new(transform)));
+
+        self
+    }
+
+    /// Refuse the values of the url query for `field` which `validate` returns false for, eg
+    /// `validate_value("status", |v| STATUSES.contains(&v))`. See `transform_value`.
+    pub fn validate_value(self, field: &'a str, validate: impl Fn(&str) -> bool + 'a) -> Self {
+        self.transform_value(field, move |value| match validate(value) {
+            true => Ok(value.to_owned()),
+            false => Err("not allowed".to_owned()),
+        })
+    }
+
+    /// Replace a value of the url query for `field` with another, eg `userId=me` with the id of
+    /// the authenticated user, in plain params and `filter[]` alike. Only a value equal to `from`
+    /// is replaced, never a part of it. See `transform_value` for anything more general.
+    pub fn substitute_value(self, field: &'a str, from: &'a str, to: impl Into<String>) -> Self {
+        let to = to.into();
+        self.transform_value(field, move |value| match value == from {
+            true => Ok(to.to_owned()),
+            false => Ok(value.to_owned()),
+        })
+    }
+
+    /// Map the values of the url query for `field` to the values stored in the database, eg
+    /// `map_values("status", [("open", "0"), ("closed", "1")])` binds `0` for `status=open`, in
+    /// plain params, `filter[]` and each element of an `overlaps` list alike. Values which
+    /// aren't mapped are bound as they are, see `map_values_strict` to refuse them.
+    pub fn map_values(
+        self,
+        field: &'a str,
+        values: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        let values: HashMap<&str, &str> = values.into_iter().collect();
+        self.transform_value(field, move |value| match values.get(value) {
+            Some(mapped) => Ok(mapped.to_string()),
+            None => Ok(value.to_owned()),
+        })
+    }
+
+    /// Like `map_values`, but refuses the values which aren't mapped, see `transform_value`.
+    pub fn map_values_strict(
+        self,
+        field: &'a str,
+        values: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        let values: HashMap<&str, &str> = values.into_iter().collect();
+        self.transform_value(field, move |value| match values.get(value) {
+            Some(mapped) => Ok(mapped.to_string()),
+            None => Err("not mapped".to_owned()),
+        })
+    }
+
+    /// Returns the value of a filter or having of the url query to bind, after the transforms of
+    /// its field, in the canonical form of booleans, dates and timestamps.
+    fn filter_value(&self, filter: &Filter) -> Result<String, BuildError> {
+        let field_type = self.field_types.get(filter.field.as_str());
+
+        // The elements of an `overlaps` list are transformed and typed one by one.
+        let mut typed = Vec::new();
+        for original in filter.values() {
+            let mut value = self.transformed_value(&filter.field, original)?;
+
+            #[cfg(feature = "chrono")]
+            if let Some(date) = self.relative_date(&filter.field, &value) {
+                value = date;
+            }
+
+            if let Some(FieldType::Bool | FieldType::Date | FieldType::DateTime) = field_type {
+                let field_type = field_type.copied().unwrap_or(FieldType::Text);
+                let invalid = || {
+                    field_type
+                        .invalid(&filter.field, original)
+                        .with_raw(filter.raw.clone())
+                };
+                value = field_type.parse(&value).ok_or_else(invalid)?.to_string();
+            }
+            let value = self.key_value(&filter.field, &value);
+            typed.push(value.map_err(|e| e.with_raw(filter.raw.clone()))?);
+        }
+
+        let value = typed.join(",");
+        match filter.operator {
+            Operator::LIKE if self.escape_like => Ok(escape_like(&value)),
+            operator => Ok(operator.like_pattern(&value).unwrap_or(value)),
+        }
+    }
+
+    /// Returns the value of `field` with the transforms of `transform_value` applied.
+    fn transformed_value(&self, field: &str, original: &str) -> Result<String, BuildError> {
+        let mut value = original.to_owned();
+        for (_, transform) in self.value_transforms.iter().filter(|(f, _)| *f == field) {
+            value = transform(&value).map_err(|reason| BuildError::InvalidValue {
+                field: field.to_owned(),
+                value: original.to_owned(),
+                reason,
+            })?;
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the value of a UUID field in canonical form, with the `uuid` feature, or the value
+    /// as is. Also used for the values of `after` and cursors, which don't go through transforms.
+    fn key_value(&self, field: &str, value: &str) -> Result<String, ParseError> {
+        match self.field_types.get(field) {
+            #[cfg(feature = "uuid")]
+            Some(FieldType::Uuid) => Ok(FieldType::Uuid
+                .parse(value)
+                .ok_or_else(|| FieldType::Uuid.invalid(field, value))?
+                .to_string()),
+            _ => Ok(value.to_owned()),
+        }
+    }
+
+    /// Add a raw condition to the WHERE clause, in parentheses so an OR in the fragment can't
+    /// escape the other conditions, and joined to them with AND. Each `{}` in the fragment is
+    /// replaced with the placeholder of the matching value, which is bound under the given name, eg
+    /// `and_where("id = ANY({})", vec![("ids", "{1,2}")])`. A `{}` in a string literal or a quoted
+    /// identifier, eg `tags = '{}'`, is left as it is.
+    ///
+    /// If the number of `{}` doesn't match the number of values, `build_checked` returns
+    /// `BuildError::PlaceholderMismatch`, and `build` renders a condition matching no row instead,
+    /// see `BuiltQuery::rejected`.
+    pub fn and_where(mut self, fragment: &str, values: Vec<(&str, &str)>) -> Self {
+        let values = values
+            .into_iter()
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        self.conditions.push((fragment.to_owned(), values));
+
+        self
+    }
+
+    /// Exclude soft deleted rows with `deleted_at IS NULL`, prefixed with the table of the column
+    /// in `map_columns`. The condition comes right after the base filters and doesn't bind
+    /// anything. Filters on the column keep the condition, see `allow_soft_deleted_filter`.
+    pub fn exclude_soft_deleted(mut self, column: &'a str) -> Self {
+        self.soft_delete = Some(column);
+
+        self
+    }
+
+    /// Leave out the condition of `exclude_soft_deleted` when the url query filters on a field
+    /// resolving to the same column, eg `filter[]=deletedAt-gt-2024-01-01` with
+    /// `convert_case(NameCase::Snake)`, so clients can ask for deleted rows explicitly.
+    pub fn allow_soft_deleted_filter(mut self) -> Self {
+        self.soft_delete_override = true;
+
+        self
+    }
+
+    /// Join the filters of the url query with OR instead of AND. They are wrapped in parentheses
+    /// when there are several, so base filters, raw conditions and keyset pagination still
+    /// apply to all of them, eg `WHERE tenant_id = $1 AND (a = $2 OR b = $3)`.
+    pub fn filter_combinator(mut self, combinator: Combinator) -> Self {
+        self.filter_combinator = combinator;
+
+        self
+    }
+
+    /// Prefix the statement with `WITH name AS (sql)`. The placeholders of the CTEs are counted,
+    /// and the binds of the builder are numbered after them, on top of `shift_bind`. Several CTEs
+    /// are joined with commas in the order they are added, so on Postgres the placeholders of a
+    /// later CTE continue the numbering of the earlier ones.
+    pub fn with_cte(mut self, name: &str, sql: &str) -> Self {
+        self.ctes.push((name.to_owned(), sql.to_owned()));
+
+        let ctes: Vec<&str> = self.ctes.iter().map(|(_, cte)| cte.as_str()).collect();
+        self.cte_binds = count_placeholders(&ctes.join(" "));
+
+        self
+    }
+
+    /// Returns the number of binds which precede those of the builder.
+    fn bind_offset(&self) -> usize {
+        let set_binds = match self.statement {
+            Statement::Update(ref set_columns) => set_columns.len(),
+            _ => 0,
+        };
+
+        self.shift_bind + self.cte_binds + set_binds
+    }
+
+    /// Prefixes the SQL with the CTEs, if any.
+    fn prepend_ctes(&self, sql: String) -> String {
+        if self.ctes.is_empty() {
+            return sql;
+        }
+
+        let ctes: Vec<String> = self
+            .ctes
+            .iter()
+            .map(|(name, cte)| format!("{} AS ({})", name, cte))
+            .collect();
+        format!("WITH {} {}", ctes.join(", "), sql)
+    }
+
+    /// Shifts the number of the bind parameter for postgres. For example, if you call this
+    /// method with a value of 1, the first arg you'll need to bind to the SQL will be $2.
+    pub fn shift_bind(mut self, x: usize) -> Self {
+        self.shift_bind = x;
+
+        self
+    }
+
+    /// Convert field names to the given case when rendering columns, eg `userId` to `user_id`.
+    pub fn convert_case(mut self, case: impl Into<NameCase>) -> Self {
+        self.convert_case = Some(case.into());
+
+        self
+    }
+
+    /// Provide fields whose columns don't follow the case of `convert_case`, eg `iOSVersion`.
+    /// They are used as is, or as renamed by `rename_columns`.
+    pub fn skip_case_for(mut self, fields: impl IntoIterator<Item = &'a str>) -> Self {
+        self.skip_case = fields.into_iter().collect();
+
+        self
+    }
+
+    /// Provide fields which are used as column names on purpose, without any mapping. See
+    /// `build_checked`.
+    pub fn pass_through(mut self, fields: impl IntoIterator<Item = &'a str>) -> Self {
+        self.pass_through = fields.into_iter().collect();
+
+        self
+    }
+
+    /// Render named placeholders instead of those of the database, eg `user_id = :user_id`.
+    /// The names are the fields in snake case, numbered when a field is bound more than once,
+    /// eg `price >= :price_1 AND price <= :price_2`, and replace the field names in the args.
+    /// Placeholders which precede those of the builder, eg the binds of `with_cte`, are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::{PlaceholderStyle, QueryBuilder}, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1", ["userId"]).unwrap();
+    ///
+    /// let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+    ///     .placeholder_style(PlaceholderStyle::Named)
+    ///     .build();
+    ///
+    /// assert_eq!(sql, "SELECT * FROM orders WHERE userId = :user_id");
+    /// assert_eq!(args[0].field, "user_id");
+    /// ```
+    pub fn placeholder_style(mut self, placeholder_style: PlaceholderStyle) -> Self {
+        self.placeholder_style = placeholder_style;
+
+        self
+    }
+
+    /// Bind each distinct value once on Postgres, so filters with the same value share a
+    /// placeholder, eg `status = $1 AND visibility = $1`, and the args shrink accordingly. Since
+    /// Postgres infers a single type for each placeholder, values are only shared between fields
+    /// of the same `field_types` type, or, for untyped fields, uses of the same field. The shared
+    /// arg keeps the name of its first use. Pagination binds are never shared. Does nothing with
+    /// `?` placeholders, which are positional only, or with `placeholder_style`.
+    pub fn dedupe_binds(mut self) -> Self {
+        self.dedupe_binds = true;
+
+        self
+    }
+
+    /// Escape the wildcards of the values of `like` filters too, so they match literally like
+    /// `contains`, `startswith` and `endswith`, see `filter::escape_like`.
+    pub fn escape_like(mut self) -> Self {
+        self.escape_like = true;
+
+        self
+    }
+
+    /// Compare a `FieldType::DateTime` field equal to a date, eg `createdAt=2024-05-01`, with the
+    /// whole day in UTC: `(created_at >= $1 AND created_at < $2)`, binding the midnight of the
+    /// date and of the next day.
+    #[cfg(feature = "chrono")]
+    pub fn expand_dates(mut self) -> Self {
+        self.expand_dates = true;
+
+        self
+    }
+
+    /// Replace the relative date keywords in the values of `FieldType::Date` and
+    /// `FieldType::DateTime` fields with the date they stand for, eg `createdAt-ge-last7days`, see
+    /// `value::relative_date`. Days start at midnight in the timezone with the given offset, and
+    /// timestamps are bound in UTC. Other values are kept, and an unknown keyword fails
+    /// `build_checked` like any invalid date. The values of untyped fields are used as is.
+    ///
+    /// `UrlQuery::with_schema` rejects the keywords, since the schema doesn't know they are
+    /// resolved, so use `UrlQuery::new` with `QuerySchema::allowed_fields` instead.
+    #[cfg(feature = "chrono")]
+    pub fn relative_dates(mut self, offset: chrono::FixedOffset) -> Self {
+        self.relative_dates = Some(offset);
+
+        self
+    }
+
+    /// Resolve the relative date keywords of `relative_dates` at the given time instead of the
+    /// current time.
+    #[cfg(feature = "chrono")]
+    pub fn now(mut self, now: chrono::DateTime<chrono::Utc>) -> Self {
+        self.now = Some(now);
+
+        self
+    }
+
+    /// Returns the date a relative date keyword of a date field stands for, see `relative_dates`.
+    #[cfg(feature = "chrono")]
+    fn relative_date(&self, field: &str, value: &str) -> Option<String> {
+        let offset = self.relative_dates?;
+        let now = self
+            .now
+            .unwrap_or_else(|| std::time::SystemTime::now().into())
+            .with_timezone(&offset);
+
+        match self.field_types.get(field)? {
+            FieldType::Date => {
+                let date = crate::value::relative_date(value, now)?;
+                Some(date.format("%Y-%m-%d").to_string())
+            }
+            FieldType::DateTime => Some(crate::value::relative_date(value, now)?.to_rfc3339()),
+            _ => None,
+        }
+    }
+
+    /// Returns the day to compare a filter with, see `expand_dates`. The value goes through
+    /// `transform_value` first, and is refused like any other value.
+    #[cfg(feature = "chrono")]
+    fn day_range(&self, filter: &Filter) -> Result<Option<(String, String)>, BuildError> {
+        let field = filter.field.as_str();
+        let datetime = matches!(self.field_types.get(field), Some(FieldType::DateTime));
+        if !self.expand_dates || !datetime || filter.operator != Operator::EQ {
+            return Ok(None);
+        }
+        if self.map_exists.contains_key(field) {
+            return Ok(None);
+        }
+
+        let value = self.transformed_value(field, &filter.value)?;
+        Ok(crate::value::day_range(&value))
+    }
+
+    /// Provide the type of the values of each field, to parse the args with `build_typed`. The
+    /// args of fields without a type are text, except `limit` and `offset` which are integers.
+    ///
+    /// The values of `FieldType::Bool` fields are bound as `true` or `false` whatever the
+    /// spelling the client used, eg `isActive=1` or `isActive=YES`, see `value::parse_bool`.
+    /// `build_checked` returns `ParseError::InvalidValue` for any other value. Likewise, the
+    /// values of `FieldType::Date` and `FieldType::DateTime` fields are bound in canonical form,
+    /// and `build_checked` returns `ParseError::InvalidFormat` for malformed ones.
+    pub fn field_types(mut self, field_types: HashMap<&'a str, FieldType>) -> Self {
+        self.field_types = field_types;
+
+        self
+    }
+
+    /// Render the fields of the schema with their column, table or expression, and type their
+    /// args for `build_typed`. Declared columns are used as is, without `convert_case`, and the
+    /// fields count as mapped for `build_checked`. The mappings are added to those given so far,
+    /// so call this after `map_columns`, `rename_columns` and the other methods it extends.
+    pub fn with_schema(mut self, schema: &'a QuerySchema) -> Self {
+        for field in schema.fields() {
+            let name = field.name.as_str();
+            if let Some(ref column) = field.column {
+                self.rename_columns.insert(name, column);
+                self.skip_case.insert(name);
+            }
+            if let Some(ref table) = field.table {
+                self.map_columns.insert(name.to_owned(), table.to_owned());
+            }
+            if let Some(ref expression) = field.expression {
+                self.map_expressions.insert(name, expression);
+            }
+            self.field_types.insert(name, field.field_type);
+            self.pass_through.insert(name);
+        }
+
+        self
+    }
+
+    /// Include the args in the tracing events of `build`, `build_count` and `build_paginated`,
+    /// which only have their number by default since they may hold personal data. Does nothing
+    /// without the `tracing` feature.
+    pub fn trace_values(mut self) -> Self {
+        self.trace_values = true;
+
+        self
+    }
+
+    /// Render LIMIT and OFFSET as bind parameters instead of literals, so the statement text is
+    /// the same for every page. The values are appended to the args as `"limit"` and `"offset"`.
+    pub fn bind_pagination(mut self, bind_pagination: bool) -> Self {
+        self.bind_pagination = bind_pagination;
+
+        self
+    }
+
+    /// Ignore the limit and offset of the url query, eg to export every row matching the
+    /// filters.
+    pub fn skip_pagination(mut self) -> Self {
+        self.skip_pagination = true;
+
+        self
+    }
+
+    /// Clamp the limit of the url query to `max_limit`, and use it as the limit when the url
+    /// query has none, so the query is never unbounded. See `applied_limit`.
+    pub fn max_limit(mut self, max_limit: u64) -> Self {
+        self.max_limit = Some(max_limit);
+
+        self
+    }
+
+    /// Use `default_limit` when the url query has no limit. The offset of the url query is
+    /// applied along with it.
+    pub fn default_limit(mut self, default_limit: u64) -> Self {
+        self.default_limit = Some(default_limit);
+
+        self
+    }
+
+    /// Returns the limit rendered by `build`, after `default_limit` and `max_limit` are applied,
+    /// so it can be reported back to the client.
+    pub fn applied_limit(&self) -> Option<u64> {
+        if self.skip_pagination {
+            return None;
+        }
+
+        let limit = self.url_query.check_limit().ok().or(self.default_limit);
+        match self.max_limit {
+            Some(max_limit) => Some(limit.map_or(max_limit, |limit| limit.min(max_limit))),
+            None => limit,
+        }
+    }
+
+    /// Returns the offset rendered by `build`. The offset is only applied along with a limit,
+    /// unless the dialect supports an offset without a limit.
+    fn applied_offset(&self) -> Option<u64> {
+        let offset = self.url_query.limit_offset.1;
+        match self.applied_limit() {
+            Some(_) => offset,
+            None if self.dialect.supports_offset_without_limit() && !self.skip_pagination => offset,
+            None => None,
+        }
+    }
+
+    /// Use the `after` param of the url query for keyset pagination: rows are filtered to those
+    /// after the given value of the first sort column, eg `created_at < $1` for a descending
+    /// sort.
+    pub fn seek_on_sort(mut self) -> Self {
+        self.seek_on_sort = true;
+
+        self
+    }
+
+    /// Use the `cursor` param of the url query for keyset pagination on the sort columns plus a
+    /// unique tiebreaker column, eg `(created_at, id) < ($1, $2)` for a descending sort. The
+    /// tiebreaker is also appended to the ORDER BY, in the direction of the last sort. When the
+    /// sorts have different directions, or on MySQL, the comparison is expanded into
+    /// `(a > $1 OR (a = $2 AND b < $3))`. See `check_cursor`.
+    pub fn seek_on_cursor(mut self, tiebreaker: &'a str) -> Self {
+        self.cursor_tiebreaker = Some(tiebreaker);
+
+        self
+    }
+
+    /// Returns `ParseError::InvalidCursor` if the cursor of the url query doesn't hold a value
+    /// for each sort column and the tiebreaker. Such a cursor is ignored when building. With the
+    /// `uuid` feature, returns `ParseError::InvalidValue` if a value of a UUID key isn't a UUID.
+    pub fn check_cursor(&self) -> Result<(), ParseError> {
+        match self.cursor_keys() {
+            Some(Err(e)) => Err(e),
+            Some(Ok((keys, values))) => {
+                for ((field, _), value) in keys.iter().zip(values) {
+                    self.key_value(field, value)?;
+                }
+
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the sort keys, including the tiebreaker, along with the cursor values if cursor
+    /// pagination applies.
+    #[allow(clippy::type_complexity)]
+    fn cursor_keys(&self) -> Option<Result<(Vec<(&str, SortBy)>, &[String]), ParseError>> {
+        let tiebreaker = self.cursor_tiebreaker?;
+        let last = self.url_query.sorts.last()?;
+        let cursor = self.url_query.cursor.as_ref()?;
+
+        let mut fields = self.url_query.sorts.iter().map(|sort| sort.field.as_str());
+        if let Some(field) = fields.find(|field| self.computed_sorts.contains_key(field)) {
+            return Some(Err(ParseError::ComputedSortKeyset(field.to_owned())));
+        }
+
+        let mut keys: Vec<_> = self
+            .url_query
+            .sorts
+            .iter()
+            .map(|sort| (sort.field.as_str(), sort.sort_by))
+            .collect();
+        keys.push((tiebreaker, last.sort_by));
+
+        if cursor.values.len() != keys.len() {
+            return Some(Err(ParseError::InvalidCursor));
+        }
+
+        Some(Ok((keys, &cursor.values)))
+    }
+
+    /// Returns the column for a field, prefixed with its table and converted to the case, or its
+    /// mapped expression.
+    fn column(&self, field: &str) -> String {
+        if let Some(expression) = self.map_expressions.get(field) {
+            return expression.to_string();
+        }
+
+        if let Some((column, path)) = self.json_path(field) {
+            return self.dialect.json_path(&self.column(column), &path);
+        }
+
+        if let Some((table, name)) = field
+            .split_once('.')
+            .and_then(|(relation, name)| Some((self.relations.get(relation)?, name)))
+        {
+            let name = match self.convert_case {
+                Some(case) => case.convert(name),
+                None => name.to_owned(),
+            };
+            return format!("{}.{}", self.quote(table), self.quote(&name));
+        }
+
+        let mut column = String::new();
+        if let Some(table) = self.map_columns.get(field) {
+            column.push_str(&self.quote(table));
+            column.push('.');
+        }
+
+        let name = self.rename_columns.get(field).unwrap_or(&field);
+        match self.convert_case {
+            Some(case) if !self.skip_case.contains(field) => {
+                column.push_str(&self.quote(&case.convert(name)))
+            }
+            _ => column.push_str(&self.quote(name)),
+        }
+
+        column
+    }
+
+    /// Quotes each part of a dotted identifier if `quote_identifiers` is set. Anything that
+    /// isn't a plain identifier is returned as is.
+    fn quote(&self, identifier: &str) -> String {
+        let plain = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+        if !self.quote_identifiers || identifier.is_empty() || !identifier.chars().all(plain) {
+            return identifier.to_owned();
+        }
+
+        identifier
+            .split('.')
+            .map(|part| self.dialect.quote_ident(part))
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Renders the clauses collected by the builder methods which precede the WHERE clause.
+    fn append_from(&mut self) {
+        self.quote_select();
+
+        let query = &self.url_query;
+        let used_tables: HashSet<&str> = query
+            .filters
+            .iter()
+            .chain(query.havings.iter())
+            .map(|filter| filter.field.as_str())
+            .chain(query.groups.iter().map(|group| group.as_str()))
+            .chain(query.sorts.iter().map(|sort| sort.field.as_str()))
+            .filter_map(|field| match self.map_columns.get(field) {
+                Some(table) => Some(table.as_str()),
+                None => {
+                    let (relation, _) = field.split_once('.')?;
+                    self.relations.get(relation).copied()
+                }
+            })
+            .collect();
+
+        for join in self.joins.iter() {
+            let table = join.table.as_deref().unwrap_or_default();
+            if join.when_used && !used_tables.contains(table) {
+                continue;
+            }
+
+            self.sql.push(' ');
+            self.sql.push_str(&join.sql);
+        }
+    }
+
+    /// Regenerates the select list of a builder created with `new` with quoted identifiers.
+    fn quote_select(&mut self) {
+        let (table, columns) = match (self.quote_identifiers, &self.select) {
+            (true, Some(select)) => select,
+            _ => return,
+        };
+
+        let len = gen_sql_select(table, columns.iter().map(|c| c.as_str()).collect()).len();
+        let table = self.quote(table);
+        let columns: Vec<String> = columns.iter().map(|c| self.quote(c)).collect();
+        let select = gen_sql_select(&table, columns.iter().map(|c| c.as_str()).collect());
+
+        self.select_end = Some(select.len() - " FROM ".len() - table.len());
+        self.sql.replace_range(..len, &select);
+    }
+
+    /// Provide a policy deciding the timeout of the query from its shape. On MySQL the timeout is
+    /// added to the SQL as a `MAX_EXECUTION_TIME` optimizer hint, on Postgres it is returned by
+    /// `timeout_statement` to be executed in the same transaction before the query. Other
+    /// dialects choose with `Dialect::timeout_hint` and `Dialect::timeout_statement`.
+    pub fn timeout_policy(mut self, policy: impl Fn(&QueryInfo) -> Option<Duration> + 'a) -> Self {
+        self.timeout_policy = Some(Rc::new(policy));
+
+        self
+    }
+
+    /// Returns a description of the shape of the query.
+    pub fn query_info(&self) -> QueryInfo<'_> {
+        let query = &self.url_query;
+
+        QueryInfo {
+            filters: query
+                .filters
+                .iter()
+                .map(|filter| (filter.field.as_str(), filter.operator))
+                .collect(),
+            groups: &query.groups,
+            sorts: &query.sorts,
+            limit: self.applied_limit(),
+            offset: query.limit_offset.1,
+        }
+    }
+
+    /// Returns the timeout chosen by the timeout policy.
+    fn timeout(&self) -> Option<Duration> {
+        let policy = self.timeout_policy.as_ref()?;
+
+        policy(&self.query_info())
+    }
+
+    /// Returns `SET LOCAL statement_timeout = <ms>` if the timeout policy returns a timeout on
+    /// Postgres, to be executed in the same transaction before the built query.
+    pub fn timeout_statement(&self) -> Option<String> {
+        self.dialect.timeout_statement(self.timeout()?)
+    }
+
+    /// Add `COUNT(*) OVER() AS total_count` to the select list, so every row holds the total
+    /// number of rows matching the filters. Window functions are supported by Postgres and MySQL
+    /// 8+. Only applies to builders created with `new`; with `from_str` the select list can't be
+    /// located reliably and this does nothing.
+    pub fn with_total(mut self) -> Self {
+        if !self.with_total && self.select_end.is_some() {
+            self.columns
+                .push(ColumnInfo::new("COUNT(*) OVER() AS total_count"));
+        }
+        self.with_total = true;
+
+        self
+    }
+
+    /// Returns the columns selected by the built SQL, in order, including the ones added by the
+    /// builder like `total_count`. Only builders created with `new` know their select list, so
+    /// this is empty with `from_str`.
+    pub fn columns(&self) -> &[ColumnInfo] {
+        &self.columns
+    }
+
+    /// Returns an error if two differently spelled fields in the url query, eg `userId` and
+    /// `user_id`, resolve to the same column after case conversion.
+    pub fn check_case_collisions(&self) -> Result<(), ParseError> {
+        let case = match self.convert_case {
+            Some(case) => case,
+            None => return Ok(()),
+        };
+
+        let query = &self.url_query;
+        let fields = query
+            .filters
+            .iter()
+            .chain(query.havings.iter())
+            .map(|filter| filter.field.as_str())
+            .chain(query.groups.iter().map(|group| group.as_str()))
+            .chain(query.sorts.iter().map(|sort| sort.field.as_str()));
+
+        let mut columns: HashMap<String, &str> = HashMap::new();
+        // The paths of JSON columns are used as is.
+        for field in fields.filter(|field| self.json_path(field).is_none()) {
+            let column = match self.skip_case.contains(field) {
+                true => field.to_owned(),
+                false => case.convert(field),
+            };
+            match columns.get(column.as_str()) {
+                Some(other) if *other != field => Err(ParseError::FieldCollision(column))?,
+                Some(_) => {}
+                None => {
+                    columns.insert(column, field);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns an error if an expression passed to `computed_sortable` contains a placeholder, if
+    /// the url query filters or groups on a computed sort field, or if keyset pagination would
+    /// apply to a computed sort. Keyset pagination on a computed sort is ignored when building.
+    pub fn check_computed_sorts(&self) -> Result<(), ParseError> {
+        let mut computed: Vec<_> = self.computed_sorts.iter().collect();
+        computed.sort();
+        if let Some((field, _)) = computed.iter().find(|(_, sql)| contains_placeholder(sql)) {
+            Err(ParseError::ComputedSortPlaceholder(field.to_string()))?
+        }
+
+        let query = &self.url_query;
+        let mut fields = query
+            .filters
+            .iter()
+            .chain(query.havings.iter())
+            .map(|filter| filter.field.as_str())
+            .chain(query.groups.iter().map(|group| group.as_str()));
+
+        if let Some(field) = fields.find(|field| self.computed_sorts.contains_key(field)) {
+            Err(ParseError::ComputedSortOnly(field.to_owned()))?
+        }
+
+        if let (true, Some(sort), Some(_)) = (self.seek_on_sort, query.sort(), &query.after) {
+            if self.computed_sorts.contains_key(sort.field.as_str()) {
+                Err(ParseError::ComputedSortKeyset(sort.field.to_owned()))?
+            }
+        }
+
+        match self.cursor_keys() {
+            Some(Err(e @ ParseError::ComputedSortKeyset(_))) => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `ParseError::UnknownRelation` if the url query uses a dotted field, eg `user.name`,
+    /// whose relation wasn't registered with `relation`. Fields passed to `map_expressions`,
+    /// `map_exists` or `computed_sortable` and paths of a `json_column` aren't checked.
+    pub fn check_relations(&self) -> Result<(), ParseError> {
+        let query = &self.url_query;
+        let mut fields = query
+            .filters
+            .iter()
+            .chain(query.havings.iter())
+            .map(|filter| filter.field.as_str())
+            .chain(query.groups.iter().map(|group| group.as_str()))
+            .chain(query.sorts.iter().map(|sort| sort.field.as_str()));
+
+        let unknown = |field: &&str| match field.split_once('.') {
+            Some((relation, _)) => {
+                !self.relations.contains_key(relation)
+                    && !self.json_columns.contains(relation)
+                    && !self.map_expressions.contains_key(field)
+                    && !self.map_exists.contains_key(field)
+                    && !self.computed_sorts.contains_key(field)
+            }
+            None => false,
+        };
+
+        match fields.find(unknown) {
+            Some(field) => Err(ParseError::UnknownRelation(field.to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns `ParseError::DeniedField` if the url query filters, sorts, groups or projects on a
+    /// field passed to `deny_fields`. This should be reported as forbidden (403) rather than as a
+    /// bad request.
+    pub fn check_denied_fields(&self) -> Result<(), ParseError> {
+        let query = &self.url_query;
+        let mut fields = query
+            .filters
+            .iter()
+            .chain(query.havings.iter())
+            .map(|filter| filter.field.as_str())
+            .chain(query.groups.iter().map(|group| group.as_str()))
+            .chain(query.sorts.iter().map(|sort| sort.field.as_str()))
+            .chain(query.sparse_fields.values().flatten().map(|f| f.as_str()));
+
+        match fields.find(|field| self.deny_fields.contains(field)) {
+            Some(field) => Err(ParseError::DeniedField(field.to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    /// Append the WHERE clause to the SQL. Does nothing if there are no queries/filters in the url query.
+    pub fn append_where(&mut self) -> Vec<Arg> {
+        let mut args: Vec<Arg> = Vec::new();
+        let mut rejected = Vec::new();
+
+        // Base filters:
+        let mut filterv = Vec::new();
+        for filter in self.base_filters.iter() {
+            let idx = args.len() + self.bind_offset() + 1;
+            filterv.push(filter.to_sql_expression(
+                idx,
+                &self.quote(&filter.field),
+                self.dialect.as_ref(),
+            ));
+            args.push(Arg::new(
+                &filter.field,
+                &filter.value,
+                Some(filter.operator),
+                idx,
+            ));
+        }
+
+        // Soft delete:
+        if let Some(column) = self.soft_delete {
+            let mut soft_delete = String::new();
+            if let Some(table) = self.map_columns.get(column) {
+                soft_delete.push_str(&self.quote(table));
+                soft_delete.push('.');
+            }
+            soft_delete.push_str(&self.quote(column));
+
+            let mut filters = self.url_query.filters.iter();
+            let filtered = filters.any(|filter| self.column(&filter.field) == soft_delete);
+            if !(self.soft_delete_override && filtered) {
+                filterv.push(format!("{} IS NULL", soft_delete));
+            }
+        }
+
+        // Filters, skipping repeats of the same predicate:
+        let mut url_filterv = Vec::new();
+        for (i, filter) in self.url_query.filters.iter().enumerate() {
+            if self.url_query.filters[..i].contains(filter) {
+                continue;
+            }
+
+            #[cfg(feature = "chrono")]
+            let day = match self.day_range(filter) {
+                Ok(day) => day,
+                Err(e) => {
+                    rejected.push(e);
+                    url_filterv.push("(1 = 0)".to_owned());
+                    continue;
+                }
+            };
+            #[cfg(feature = "chrono")]
+            if let Some((start, end)) = day {
+                let column = self.column(&filter.field);
+                let start_idx = args.len() + self.bind_offset() + 1;
+                let end_idx = start_idx + 1;
+                url_filterv.push(format!(
+                    "({} >= {} AND {} < {})",
+                    column,
+                    self.dialect.placeholder(start_idx),
+                    column,
+                    self.dialect.placeholder(end_idx)
+                ));
+                args.push(Arg::new(
+                    &filter.field,
+                    &start,
+                    Some(Operator::GE),
+                    start_idx,
+                ));
+                args.push(Arg::new(&filter.field, &end, Some(Operator::LT), end_idx));
+                continue;
+            }
+
+            let bounds = filter.expand_range();
+            let values: Result<Vec<String>, BuildError> =
+                self.check_operator(filter).and_then(|_| {
+                    bounds
+                        .iter()
+                        .map(|bound| self.filter_value(bound))
+                        .collect()
+                });
+            // A refused filter matches nothing, rather than widening the query.
+            let values = match values {
+                Ok(values) => values,
+                Err(e) => {
+                    rejected.push(e);
+                    url_filterv.push("(1 = 0)".to_owned());
+                    continue;
+                }
+            };
+
+            // Both bounds of a range go in the same subquery, so they apply to the same row.
+            if let (Some(subquery), [start, end]) =
+                (self.map_exists.get(filter.field.as_str()), &values[..])
+            {
+                let start_idx = args.len() + self.bind_offset() + 1;
+                let end_idx = start_idx + 1;
+                let placeholders = format!(
+                    "{} AND {}",
+                    self.dialect.placeholder(start_idx),
+                    self.dialect.placeholder(end_idx)
+                );
+                url_filterv.push(exists_sql(subquery, "BETWEEN", &placeholders));
+                args.push(Arg::new(
+                    &filter.field,
+                    start,
+                    Some(Operator::GE),
+                    start_idx,
+                ));
+                args.push(Arg::new(&filter.field, end, Some(Operator::LE), end_idx));
+                continue;
+            }
+
+            let mut conditions = Vec::new();
+            for (filter, value) in bounds.iter().zip(values) {
+                let idx = args.len() + self.bind_offset() + 1;
+                conditions.push(self.filter_sql(filter, idx));
+                args.push(Arg::new(&filter.field, &value, Some(filter.operator), idx));
+            }
+            match conditions.len() {
+                1 => url_filterv.append(&mut conditions),
+                _ => url_filterv.push(format!("({})", conditions.join(" AND "))),
+            }
+        }
+
+        match self.filter_combinator {
+            Combinator::Or if url_filterv.len() > 1 => {
+                filterv.push(format!("({})", url_filterv.join(" OR ")))
+            }
+            _ => filterv.append(&mut url_filterv),
+        }
+
+        // Full-text search:
+        if let (Some((columns, config)), Some(search)) =
+            (&self.full_text_search, &self.url_query.search)
+        {
+            if !self.dialect.supports_full_text_search() {
+                rejected.push(BuildError::UnsupportedSearch);
+                filterv.push("(1 = 0)".to_owned());
+            } else {
+                let idx = args.len() + self.bind_offset() + 1;
+                let columns: Vec<String> =
+                    columns.iter().map(|column| self.quote(column)).collect();
+                let placeholder = self.dialect.placeholder(idx);
+                filterv.push(
+                    self.dialect
+                        .full_text_search(&columns, config, &placeholder),
+                );
+                args.push(Arg::new("q", search, None, idx));
+                self.search_idx = Some(idx);
+            }
+        }
+
+        // Raw conditions:
+        for (fragment, values) in self.conditions.iter() {
+            let parts = split_markers(fragment);
+            if parts.len() - 1 != values.len() {
+                rejected.push(BuildError::PlaceholderMismatch {
+                    fragment: fragment.to_owned(),
+                    placeholders: parts.len() - 1,
+                    values: values.len(),
+                });
+                filterv.push("(1 = 0)".to_owned());
+                continue;
+            }
+
+            let mut condition = String::from(parts[0]);
+            for (part, (name, value)) in parts[1..].iter().zip(values) {
+                let idx = args.len() + self.bind_offset() + 1;
+                condition.push_str(&self.dialect.placeholder(idx));
+                condition.push_str(part);
+                args.push(Arg::new(name, value, None, idx));
+            }
+            filterv.push(format!("({})", condition));
+        }
+
+        // Keyset pagination:
+        if let (true, Some(sort), Some(after)) = (
+            self.seek_on_sort,
+            self.url_query
+                .sort()
+                .filter(|sort| !self.computed_sorts.contains_key(sort.field.as_str())),
+            self.url_query.after.as_ref(),
+        ) {
+            let operator = match sort.sort_by {
+                SortBy::ASC => Operator::GT,
+                SortBy::DESC => Operator::LT,
+            };
+            match self.key_value(&sort.field, after) {
+                Ok(after) => {
+                    let seek = Filter::from_key_value(&sort.field, &after, operator);
+                    let idx = args.len() + self.bind_offset() + 1;
+                    filterv.push(self.filter_sql(&seek, idx));
+                    args.push(Arg::new(&seek.field, &seek.value, Some(operator), idx));
+                }
+                Err(e) => rejected.push(e.into()),
+            }
+        }
+
+        // Cursor pagination:
+        let cursor = self
+            .cursor_keys()
+            .and_then(Result::ok)
+            .and_then(|(keys, values)| {
+                let values: Result<Vec<String>, ParseError> = keys
+                    .iter()
+                    .zip(values)
+                    .map(|((field, _), value)| self.key_value(field, value))
+                    .collect();
+                match values {
+                    Ok(values) => Some((keys, values)),
+                    Err(e) => {
+                        rejected.push(e.into());
+                        None
+                    }
+                }
+            });
+        if let Some((keys, values)) = cursor {
+            let operator = |sort_by| match sort_by {
+                SortBy::ASC => ">",
+                SortBy::DESC => "<",
+            };
+            let columns: Vec<String> = keys.iter().map(|(field, _)| self.column(field)).collect();
+            let same_direction = keys.iter().all(|(_, sort_by)| *sort_by == keys[0].1);
+
+            let mut placeholder = |field: &str, value: &str| {
+                let idx = args.len() + self.bind_offset() + 1;
+                args.push(Arg::new(field, value, None, idx));
+                self.dialect.placeholder(idx)
+            };
+
+            if same_direction && self.dialect.supports_row_values() {
+                let placeholders: Vec<String> = keys
+                    .iter()
+                    .zip(values.iter())
+                    .map(|((field, _), value)| placeholder(field, value))
+                    .collect();
+                filterv.push(format!(
+                    "({}) {} ({})",
+                    columns.join(", "),
+                    operator(keys[0].1),
+                    placeholders.join(", ")
+                ));
+            } else {
+                // (a > $1 OR (a = $2 AND b > $3) OR (a = $4 AND b = $5 AND c > $6))
+                let mut terms = Vec::new();
+                for (i, (field, sort_by)) in keys.iter().enumerate() {
+                    let mut term = Vec::new();
+                    for (j, column) in columns[..i].iter().enumerate() {
+                        let p = placeholder(keys[j].0, &values[j]);
+                        term.push(format!("{} = {}", column, p));
+                    }
+                    let p = placeholder(field, &values[i]);
+                    term.push(format!("{} {} {}", columns[i], operator(*sort_by), p));
+
+                    match term.len() {
+                        1 => terms.push(term.remove(0)),
+                        _ => terms.push(format!("({})", term.join(" AND "))),
+                    }
+                }
+                filterv.push(format!("({})", terms.join(" OR ")));
+            }
+        }
+
+        let filter = filterv.join(" AND ");
+
+        // WHERE clause
+        if !filterv.is_empty() {
+            self.sql.push_str(" WHERE ");
+            self.sql.push_str(&filter);
+        }
+        self.rejected.append(&mut rejected);
+
+        args
+    }
+
+    /// Append a GROUP BY to the SQL. Does nothing if there is no group in the url query.
+    pub fn append_group(&mut self) {
+        if self.url_query.groups.is_empty() {
+            return;
+        };
+
+        let groups: Vec<String> = self
+            .url_query
+            .groups
+            .iter()
+            .map(|group| self.column(group))
+            .collect();
+        self.sql.push_str(" GROUP BY ");
+        self.sql.push_str(&groups.join(", "));
+    }
+
+    /// Append a HAVING clause to the SQL, numbering its binds after `args` and pushing its values
+    /// onto it. Does nothing if there are no havings in the url query.
+    pub fn append_having(&mut self, args: &mut Vec<Arg>) {
+        let mut havingv = Vec::new();
+        let mut rejected = Vec::new();
+        for (i, having) in self.url_query.havings.iter().enumerate() {
+            if self.url_query.havings[..i].contains(having) {
+                continue;
+            }
+
+            let bounds = having.expand_range();
+            let values: Result<Vec<String>, BuildError> =
+                self.check_operator(having).and_then(|_| {
+                    bounds
+                        .iter()
+                        .map(|bound| self.filter_value(bound))
+                        .collect()
+                });
+            let values = match values {
+                Ok(values) => values,
+                Err(e) => {
+                    rejected.push(e);
+                    havingv.push("(1 = 0)".to_owned());
+                    continue;
+                }
+            };
+
+            for (having, value) in bounds.iter().zip(values) {
+                let idx = args.len() + self.bind_offset() + 1;
+                match self.map_aggregates.get(having.field.as_str()) {
+                    Some(aggregate) => havingv.push(having.to_sql_expression(
+                        idx,
+                        aggregate,
+                        self.dialect.as_ref(),
+                    )),
+                    None => havingv.push(self.filter_sql(having, idx)),
+                }
+                args.push(Arg::new(&having.field, &value, Some(having.operator), idx));
+            }
+        }
+        self.rejected.append(&mut rejected);
+
+        if !havingv.is_empty() {
+            self.sql.push_str(" HAVING ");
+            self.sql.push_str(&havingv.join(" AND "));
+        }
+    }
+
+    /// Append an ORDER BY to the SQL. Does nothing if there is no sort in the url query and no
+    /// `default_sort`, unless `tie_breaker_always` is used.
+    pub fn append_sort(&mut self) {
+        if !self.has_order_by() {
+            return;
+        }
+
+        let sorts = match self.url_query.sorts.is_empty() {
+            true => &self.default_sorts,
+            false => &self.url_query.sorts,
+        };
+
+        let cursor_tiebreaker = match (self.cursor_tiebreaker, sorts.last()) {
+            (Some(tiebreaker), Some(last)) => Some(Sort {
+                field: tiebreaker.to_owned(),
+                sort_by: last.sort_by,
+                nulls: None,
+                raw: None,
+            }),
+            _ => None,
+        };
+
+        // A field sorted on twice only orders by its first occurrence.
+        let mut sorted = HashSet::new();
+        let mut sortv = Vec::new();
+        let tiebreakers = cursor_tiebreaker.iter().chain(self.tie_breaker.as_ref());
+        for sort in sorts.iter().chain(tiebreakers) {
+            if !sorted.insert(sort.field.as_str()) {
+                continue;
+            }
+
+            let column = match self.computed_sorts.get(sort.field.as_str()) {
+                Some(expression) => expression.to_string(),
+                None if self.is_relevance(&sort.field) => match self.relevance() {
+                    Some(rank) => rank,
+                    None => continue,
+                },
+                None => self.column(&sort.field),
+            };
+            sortv.push(sort.to_sql_expression(&column, self.dialect.as_ref()));
+        }
+
+        if sortv.is_empty() {
+            return;
+        }
+
+        self.sql.push_str(" ORDER BY ");
+        self.sql.push_str(&sortv.join(", "));
+    }
+
+    /// Returns whether the field is the relevance of a `full_text_search`.
+    fn is_relevance(&self, field: &str) -> bool {
+        field == "relevance" && self.full_text_search.is_some()
+    }
+
+    /// Returns the rank of the rows matching the search, rendered after the WHERE clause.
+    fn relevance(&self) -> Option<String> {
+        let (columns, config) = self.full_text_search.as_ref()?;
+        let columns: Vec<String> = columns.iter().map(|column| self.quote(column)).collect();
+        let placeholder = self.dialect.placeholder(self.search_idx?);
+
+        self.dialect.full_text_rank(&columns, config, &placeholder)
+    }
+
+    /// Returns whether `append_sort` renders an ORDER BY.
+    fn has_order_by(&self) -> bool {
+        !self.url_query.sorts.is_empty()
+            || !self.default_sorts.is_empty()
+            || (self.tie_breaker_always && self.tie_breaker.is_some())
+    }
+
+    /// Renders a filter or having with the bind number `idx`.
+    fn filter_sql(&self, filter: &Filter, idx: usize) -> String {
+        if let Some(subquery) = self.map_exists.get(filter.field.as_str()) {
+            let placeholder = self.dialect.placeholder(idx);
+            if let Operator::LIKE | Operator::CONTAINS | Operator::STARTSWITH | Operator::ENDSWITH =
+                filter.operator
+            {
+                // Eg `LIKE $1 ESCAPE '\'`, split at the operator.
+                let like = self.dialect.render_like("", &placeholder);
+                let (op, placeholder) = like.trim_start().split_once(' ').unwrap_or(("LIKE", ""));
+                return exists_sql(subquery, op, placeholder);
+            }
+            return exists_sql(subquery, filter.operator.as_str(), &placeholder);
+        }
+
+        let numeric = matches!(
+            self.field_types.get(filter.field.as_str()),
+            Some(FieldType::Int | FieldType::Float)
+        );
+        let ordering = matches!(
+            filter.operator,
+            Operator::GT | Operator::GE | Operator::LT | Operator::LE
+        );
+        if self.json_path(&filter.field).is_some() && numeric && ordering {
+            let column = self.dialect.cast_numeric(&self.column(&filter.field));
+            return filter.to_sql_expression(idx, &column, self.dialect.as_ref());
+        }
+
+        filter.to_sql_expression(idx, &self.column(&filter.field), self.dialect.as_ref())
+    }
+
+    /// Returns an error if the operator of `filter` can't be rendered: `has`, `overlaps` and
+    /// `cont` on a `map_exists` field, which don't fit the `{op} {placeholder}` of the subquery,
+    /// `has` and `overlaps` on a database without arrays, or `cont` on a database without JSON
+    /// containment.
+    fn check_operator(&self, filter: &Filter) -> Result<(), BuildError> {
+        let exists = self.map_exists.contains_key(filter.field.as_str());
+        let unsupported = match filter.operator {
+            Operator::HAS | Operator::OVERLAPS => exists || !self.dialect.supports_arrays(),
+            Operator::CONT => exists || !self.dialect.supports_json_contains(),
+            _ => false,
+        };
+        if unsupported {
+            Err(BuildError::UnsupportedOperator {
+                field: filter.field.to_owned(),
+                operator: filter.operator,
+            })?
+        }
+
+        Ok(())
+    }
+
+    /// Returns SQL statement along with a list of columns and args to bind. Filters whose value
+    /// is refused, eg by `validate_value` or `field_types`, render `(1 = 0)` instead, so the
+    /// query matches nothing rather than more rows, see `build_parts` to get them and
+    /// `build_checked` to fail on them.
+    pub fn build(self) -> (String, Vec<Arg>) {
+        let built = self.build_parts();
+
+        (built.sql, built.args)
+    }
+
+    /// Like `build`, but also returns the applied limit and offset, whether there is a WHERE
+    /// clause and the number of the next bind parameter.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1&limit=500&offset=20", ["userId"]).unwrap();
+    ///
+    /// let built = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+    ///     .max_limit(100)
+    ///     .build_parts();
+    ///
+    /// assert_eq!(built.sql, "SELECT * FROM orders WHERE userId = $1 LIMIT 100 OFFSET 20");
+    /// assert_eq!(built.next_bind_index, 2);
+    /// assert_eq!((built.limit, built.offset), (Some(100), Some(20)));
+    /// assert!(built.has_where);
+    /// ```
+    pub fn build_parts(mut self) -> BuiltQuery {
+        if let Statement::Delete | Statement::Update(_) = self.statement {
+            return self.build_mutation().0;
+        }
+
+        self.append_from();
+
+        if let (true, Some(select_end)) = (self.with_total, self.select_end) {
+            self.sql
+                .insert_str(select_end, ", COUNT(*) OVER() AS total_count");
+        }
+
+        // returns bind args
+        let len = self.sql.len();
+        let mut args = self.append_where();
+        let has_where = self.sql.len() != len;
+
+        self.append_group();
+
+        self.append_having(&mut args);
+
+        self.append_sort();
+
+        let bound = args.len();
+        self.append_pagination(&mut args);
+
+        self.append_lock();
+
+        if let Some(hint) = self.timeout().and_then(|t| self.dialect.timeout_hint(t)) {
+            self.sql = insert_hint(&self.sql, &hint);
+        }
+
+        let numbers = self.dedupe_args(&mut args, bound);
+        self.sql = self.renumber_binds(&self.sql, &numbers);
+
+        let fields = args.iter().map(|arg| arg.field.to_owned()).collect();
+        let names = self.placeholder_names(&args);
+        self.name_args(&mut args, &names);
+
+        let limit = self.applied_limit();
+        let built = BuiltQuery {
+            sql: self.name_binds(self.prepend_ctes(self.sql.clone()), &names),
+            next_bind_index: self.bind_offset() + args.len() + 1,
+            args,
+            limit,
+            offset: self.applied_offset(),
+            has_where,
+            rejected: std::mem::take(&mut self.rejected),
+            fields,
+        };
+        self.trace(&built.sql, &built.args, built.limit, built.offset);
+
+        built
+    }
+
+    /// Like `build`, but parses each arg with the type of its field, see `field_types`. Returns
+    /// `ParseError::InvalidValue` for the first value which isn't valid for its type.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::collections::HashMap;
+    /// use query::{sql::QueryBuilder, value::{ArgValue, FieldType}, ParseError, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1&name=bob&limit=10", ["userId", "name"]).unwrap();
+    ///
+    /// let (sql, args) = QueryBuilder::from_str("SELECT * FROM users", parsed)
+    ///     .field_types(HashMap::from([("userId", FieldType::Int)]))
+    ///     .build_typed()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(sql, "SELECT * FROM users WHERE userId = $1 AND name = $2 LIMIT 10");
+    /// assert_eq!(args[0], ("userId".to_owned(), ArgValue::Int(1)));
+    /// assert_eq!(args[1], ("name".to_owned(), ArgValue::Text("bob".to_owned())));
+    /// ```
+    pub fn build_typed(self) -> Result<(String, Vec<(String, ArgValue)>), ParseError> {
+        let field_types = self.field_types.clone();
+        let built = self.build_parts();
+
+        for rejected in built.rejected.iter() {
+            match rejected {
+                BuildError::Parse(e) => Err(e.clone())?,
+                BuildError::InvalidValue { field, value, .. } => Err(ParseError::InvalidValue {
+                    field: field.to_owned(),
+                    value: value.to_owned(),
+                    raw: None,
+                })?,
+                _ => {}
+            }
+        }
+
+        let mut args = Vec::new();
+        for (arg, field) in built.args.into_iter().zip(built.fields) {
+            let field_type = match (field_types.get(field.as_str()), field.as_str()) {
+                (Some(field_type), _) => *field_type,
+                (None, "limit" | "offset") => FieldType::Int,
+                (None, _) => FieldType::Text,
+            };
+            if arg.comparator == Some(Operator::OVERLAPS) {
+                let mut values = Vec::new();
+                for value in arg.value.split(',') {
+                    values.push(
+                        field_type
+                            .parse(value)
+                            .ok_or_else(|| field_type.invalid(&field, value))?,
+                    );
+                }
+                args.push((arg.field, ArgValue::Array(values)));
+                continue;
+            }
+
+            match field_type.parse(&arg.value) {
+                Some(value) => args.push((arg.field, value)),
+                None => Err(field_type.invalid(&field, &arg.value))?,
+            }
+        }
+
+        Ok((built.sql, args))
+    }
+
+    /// Emits a debug event with the built statement.
+    #[cfg(feature = "tracing")]
+    fn trace(&self, sql: &str, args: &[Arg], limit: Option<u64>, offset: Option<u64>) {
+        let filters = self.url_query.filters.len();
+        match self.trace_values {
+            true => tracing::debug!(sql, ?args, ?limit, ?offset, filters, "built query"),
+            false => {
+                let args = args.len();
+                tracing::debug!(sql, args, ?limit, ?offset, filters, "built query")
+            }
+        }
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    fn trace(&self, _sql: &str, _args: &[Arg], _limit: Option<u64>, _offset: Option<u64>) {}
+
+    /// Returns the statement of `build` with the args inlined as string literals, eg
+    /// `WHERE name = 'O''Brien'`, to log it or run it with EXPLAIN. **Never execute it**: the
+    /// quoting is only meant to be readable. Placeholders which precede those of the builder,
+    /// eg the binds of `shift_bind` or `with_cte`, are left as they are.
+    pub fn debug_sql(&self) -> String {
+        let mut builder = self.clone();
+        builder.placeholder_style = PlaceholderStyle::Positional;
+        let (sql, args) = builder.build();
+        let first = self.bind_offset();
+
+        self.replace_binds(&sql, |n| {
+            let arg = args.get(n.checked_sub(first + 1)?)?;
+            Some(self.dialect.quote_literal(&arg.value))
+        })
+    }
+
+    /// Replaces the placeholders of the dialect in the SQL with the replacement `f` returns for
+    /// their number, if any. Unnumbered placeholders, like `?`, are numbered in order.
+    fn replace_binds(&self, sql: &str, mut f: impl FnMut(usize) -> Option<String>) -> String {
+        // The prefix of numbered placeholders, eg `$` for `$1`, or the unnumbered placeholder.
+        let prefix = self.dialect.placeholder_prefix();
+        let token = self.dialect.placeholder(1);
+        let is_bind = |bytes: &[u8], i: usize| match prefix {
+            Some(prefix) => {
+                bytes[i..].starts_with(prefix.as_bytes())
+                    && bytes
+                        .get(i + prefix.len())
+                        .is_some_and(|b| b.is_ascii_digit())
+            }
+            None => bytes[i..].starts_with(token.as_bytes()),
+        };
+
+        let bytes = sql.as_bytes();
+        let mut replaced = String::new();
+        let (mut start, mut question_marks) = (0, 0);
+        while let Some(i) = find_unquoted(sql, start, false, is_bind) {
+            replaced.push_str(&sql[start..i]);
+
+            let (n, end): (usize, usize) = match prefix {
+                None => {
+                    question_marks += 1;
+                    (question_marks, i + token.len())
+                }
+                Some(prefix) => {
+                    let number = i + prefix.len();
+                    let digits = bytes[number..].iter().take_while(|b| b.is_ascii_digit());
+                    let end = number + digits.count();
+                    (sql[number..end].parse().unwrap_or(0), end)
+                }
+            };
+
+            match f(n) {
+                Some(replacement) => replaced.push_str(&replacement),
+                None => replaced.push_str(&sql[i..end]),
+            }
+            start = end;
+        }
+        replaced.push_str(&sql[start..]);
+
+        replaced
+    }
+
+    /// Removes the args whose value was already bound, see `dedupe_binds`, and returns the new
+    /// number of each bind of the builder, in the order of the original args. Only the first
+    /// `len` args are shared, the others are renumbered. Returns nothing if binds aren't shared.
+    fn dedupe_args(&self, args: &mut Vec<Arg>, len: usize) -> Vec<usize> {
+        if !self.dedupe_binds
+            || !self.dialect.supports_shared_placeholders()
+            || !matches!(self.placeholder_style, PlaceholderStyle::Positional)
+        {
+            return Vec::new();
+        }
+
+        // The type of the field if it has one, or else the field itself.
+        let kind = |arg: &Arg| {
+            let field = arg.field.to_owned();
+            self.field_types
+                .get(arg.field.as_str())
+                .copied()
+                .ok_or(field)
+        };
+
+        let first = self.bind_offset() + 1;
+        let mut numbers = Vec::new();
+        let mut deduped: Vec<Arg> = Vec::new();
+        for (i, mut arg) in args.drain(..).enumerate() {
+            let shared = deduped
+                .iter()
+                .position(|other| i < len && other.value == arg.value && kind(other) == kind(&arg));
+            match shared {
+                Some(position) => numbers.push(first + position),
+                None => {
+                    arg.index = first + deduped.len();
+                    numbers.push(arg.index);
+                    deduped.push(arg);
+                }
+            }
+        }
+        *args = deduped;
+
+        numbers
+    }
+
+    /// Replaces the placeholders of the builder with the `numbers` returned by `dedupe_args`.
+    fn renumber_binds(&self, sql: &str, numbers: &[usize]) -> String {
+        if numbers.is_empty() {
+            return sql.to_owned();
+        }
+
+        let first = self.bind_offset();
+        self.replace_binds(sql, |n| {
+            let number = numbers.get(n.checked_sub(first + 1)?)?;
+            Some(self.dialect.placeholder(*number))
+        })
+    }
+
+    /// Returns the name of each bind of the builder for `PlaceholderStyle::Named`: the SET
+    /// columns of an update, then the args, in snake case. Names used more than once are
+    /// numbered, eg `price_1` and `price_2`.
+    fn placeholder_names(&self, args: &[Arg]) -> Vec<String> {
+        let set_columns = match self.statement {
+            Statement::Update(ref set_columns) => set_columns.as_slice(),
+            _ => &[],
+        };
+        let names: Vec<String> = set_columns
+            .iter()
+            .chain(args.iter().map(|arg| &arg.field))
+            .map(|name| {
+                let plain = |c: char| c.is_ascii_alphanumeric() || c == '_';
+                NameCase::Snake.convert(name).replace(|c| !plain(c), "_")
+            })
+            .collect();
+
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        names
+            .iter()
+            .map(|name| {
+                if names.iter().filter(|other| *other == name).count() == 1 {
+                    return name.to_owned();
+                }
+
+                let n = seen.entry(name).or_default();
+                *n += 1;
+                format!("{}_{}", name, n)
+            })
+            .collect()
+    }
+
+    /// Replaces the positional placeholders of the builder with the `names`, and the names of
+    /// the args. Does nothing for `PlaceholderStyle::Positional`.
+    fn name_binds(&self, sql: String, names: &[String]) -> String {
+        let prefix = match self.placeholder_style {
+            PlaceholderStyle::Positional => return sql,
+            PlaceholderStyle::Named => ':',
+            PlaceholderStyle::NamedAt => '@',
+        };
+
+        let first = self.shift_bind + self.cte_binds;
+        self.replace_binds(&sql, |n| {
+            let name = names.get(n.checked_sub(first + 1)?)?;
+            Some(format!("{}{}", prefix, name))
+        })
+    }
+
+    /// Renames the args after the placeholders for `PlaceholderStyle::Named`.
+    fn name_args(&self, args: &mut [Arg], names: &[String]) {
+        if let PlaceholderStyle::Positional = self.placeholder_style {
+            return;
+        }
+
+        let set_binds = names.len() - args.len();
+        for (arg, placeholder) in args.iter_mut().zip(&names[set_binds..]) {
+            arg.field = placeholder.to_owned();
+        }
+    }
+
+    /// Returns a `SELECT COUNT(*)` statement with the same WHERE clause as `build`, along with the
+    /// args to bind, which are the same as those returned by `build` without pagination binds.
+    /// ORDER BY, LIMIT and OFFSET are omitted.
+    ///
+    /// The select list is replaced with `COUNT(*)`, keeping everything from the top-level FROM
+    /// onwards. If the url query has a group, or the select list can't be located, the query is
+    /// wrapped in a subquery instead, so the groups are counted rather than the rows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1&sort=id-desc&limit=10", ["userId", "id"]).unwrap();
+    ///
+    /// let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed).build_count();
+    ///
+    /// assert_eq!(sql, "SELECT COUNT(*) FROM orders WHERE userId = $1");
+    /// assert_eq!(args.len(), 1);
+    /// ```
+    pub fn build_count(mut self) -> (String, Vec<Arg>) {
+        self.append_from();
+
+        let mut args = self.append_where();
+
+        self.append_group();
+
+        self.append_having(&mut args);
+
+        let numbers = self.dedupe_args(&mut args, usize::MAX);
+        let count_sql = self.renumber_binds(&self.count_sql(), &numbers);
+
+        let names = self.placeholder_names(&args);
+        self.name_args(&mut args, &names);
+
+        let count_sql = self.name_binds(self.prepend_ctes(count_sql), &names);
+        self.trace(&count_sql, &args, None, None);
+
+        (count_sql, args)
+    }
+
+    /// Returns the data statement, a matching count statement (see `build_count`) and the args
+    /// to bind, rendering the WHERE clause once for both. The count statement binds the first
+    /// `count_args().len()` args, the data statement binds all of them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::QueryBuilder, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+    ///
+    /// let paginated = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+    ///     .bind_pagination(true)
+    ///     .build_paginated();
+    ///
+    /// assert_eq!(paginated.data_sql, "SELECT * FROM orders WHERE userId = $1 LIMIT $2");
+    /// assert_eq!(paginated.count_sql, "SELECT COUNT(*) FROM orders WHERE userId = $1");
+    /// assert_eq!(paginated.args.len(), 2);
+    /// assert_eq!(paginated.count_args().len(), 1);
+    /// ```
+    pub fn build_paginated(mut self) -> Paginated {
+        self.append_from();
+
+        let mut args = self.append_where();
+
+        self.append_group();
+
+        self.append_having(&mut args);
+
+        let count_sql = self.count_sql();
+        let bound = args.len();
+
+        self.append_sort();
+
+        self.append_pagination(&mut args);
+
+        self.append_lock();
+
+        if let Some(hint) = self.timeout().and_then(|t| self.dialect.timeout_hint(t)) {
+            self.sql = insert_hint(&self.sql, &hint);
+        }
+
+        let pagination_binds = args.len() - bound;
+        let numbers = self.dedupe_args(&mut args, bound);
+        self.sql = self.renumber_binds(&self.sql, &numbers);
+        let count_sql = self.renumber_binds(&count_sql, &numbers);
+        let count_args_len = args.len() - pagination_binds;
+
+        let names = self.placeholder_names(&args);
+        self.name_args(&mut args, &names);
+
+        let paginated = Paginated {
+            data_sql: self.name_binds(self.prepend_ctes(self.sql.clone()), &names),
+            count_sql: self.name_binds(self.prepend_ctes(count_sql), &names),
+            args,
+            count_args_len,
+        };
+        let (limit, offset) = (self.applied_limit(), self.applied_offset());
+        self.trace(&paginated.data_sql, &paginated.args, limit, offset);
+        let count_args = paginated.count_args();
+        self.trace(&paginated.count_sql, count_args, None, None);
+
+        paginated
+    }
+
+    /// Append the row-locking clause, if any.
+    fn append_lock(&mut self) {
+        let lock = self
+            .lock
+            .and_then(|lock| self.dialect.render_lock(lock, self.legacy_mysql));
+        if let Some(lock) = lock {
+            self.sql.push(' ');
+            self.sql.push_str(&lock);
+        }
+    }
+
+    /// Returns the count statement for the SQL built so far. A grouped or DISTINCT select is
+    /// counted in a subquery, since `COUNT(*)` would count the rows before grouping or
+    /// deduplicating them.
+    fn count_sql(&self) -> String {
+        match find_top_level_from(&self.sql) {
+            Some(from) if self.url_query.groups.is_empty() && !selects_distinct(&self.sql) => {
+                format!("SELECT COUNT(*) {}", &self.sql[from..])
+            }
+            _ => format!(
+                "SELECT COUNT(*) FROM ({}){}",
+                self.sql,
+                self.dialect.render_subquery_alias("count_query")
+            ),
+        }
+    }
+
+    /// Returns the DELETE or UPDATE statement and whether it was refused for having no filter from
+    /// the url query, or for leaving out a filter whose value was refused.
+    fn build_mutation(mut self) -> (BuiltQuery, bool) {
+        if let Statement::Update(ref set_columns) = self.statement {
+            let first = self.shift_bind + self.cte_binds + 1;
+            let mut setv = Vec::new();
+            for (i, column) in set_columns.iter().enumerate() {
+                let mut set = format!("{} = ", self.quote(column));
+                set.push_str(&self.dialect.placeholder(first + i));
+                setv.push(set);
+            }
+
+            self.sql.push_str(" SET ");
+            self.sql.push_str(&setv.join(", "));
+        }
+
+        let len = self.sql.len();
+        let mut args = self.append_where();
+        let has_where = self.sql.len() != len;
+
+        // Base filters, soft deletes and raw conditions scope the statement, eg to a tenant, but
+        // don't pick its rows, so the url query must filter too. A statement with a refused filter
+        // is refused as well.
+        let unfiltered = self.url_query.filters.is_empty() && !self.allow_unfiltered;
+        let refused = unfiltered || !self.rejected.is_empty();
+        if refused {
+            match has_where {
+                true => self.sql.push_str(" AND 1 = 0"),
+                false => self.sql.push_str(" WHERE 1 = 0"),
+            }
+        }
+
+        let mut limit = None;
+        if self.dialect.supports_mutation_limit() {
+            self.append_sort();
+
+            limit = self.applied_limit();
+            if let Some(limit) = limit {
+                if self.bind_pagination {
+                    let idx = args.len() + self.bind_offset() + 1;
+                    self.sql
+                        .push_str(&format!(" LIMIT {}", self.dialect.placeholder(idx)));
+                    args.push(Arg::new("limit", &limit.to_string(), None, idx));
+                } else {
+                    append_limit(&mut self.sql, limit);
+                }
+            }
+        }
+
+        let numbers = self.dedupe_args(&mut args, usize::MAX);
+        self.sql = self.renumber_binds(&self.sql, &numbers);
+
+        let fields = args.iter().map(|arg| arg.field.to_owned()).collect();
+        let names = self.placeholder_names(&args);
+        self.name_args(&mut args, &names);
+
+        let built = BuiltQuery {
+            sql: self.name_binds(self.prepend_ctes(self.sql.clone()), &names),
+            next_bind_index: self.bind_offset() + args.len() + 1,
+            args,
+            limit,
+            offset: None,
+            has_where: true,
+            rejected: std::mem::take(&mut self.rejected),
+            fields,
+        };
+        self.trace(&built.sql, &built.args, built.limit, built.offset);
+
+        (built, refused)
+    }
+
+    /// Like `build`, but returns an error if the complete statement contains more than one SQL
+    /// statement, eg because an appended fragment contained `; DROP TABLE users`, or if it is a
+    /// delete or an update without a filter from the url query, see `delete`.
+    pub fn try_build(self) -> Result<(String, Vec<Arg>), SqlSafetyError> {
+        let (sql, args) = match self.statement {
+            Statement::Delete => match self.build_mutation() {
+                (_, true) => Err(SqlSafetyError::UnfilteredDelete)?,
+                (built, false) => (built.sql, built.args),
+            },
+            Statement::Update(_) => match self.build_mutation() {
+                (_, true) => Err(SqlSafetyError::UnfilteredUpdate)?,
+                (built, false) => (built.sql, built.args),
+            },
+            Statement::Select => self.build(),
+        };
+        check_single_statement(&sql)?;
+
+        Ok((sql, args))
+    }
+
+    /// Like `try_build`, but first runs every check of the builder, and returns an error if:
+    ///
+    /// - a field used by the url query is neither mapped, by `map_columns`, `rename_columns`,
+    ///   `map_expressions`, `map_exists`, `map_aggregates`, `computed_sortable` or `relation`,
+    ///   nor passed to `pass_through`;
+    /// - the url query has a having but no group;
+    /// - a value is refused by `transform_value` or `validate_value`;
+    /// - a fragment passed to `and_where` doesn't have a value for each `{}`;
+    /// - the url query has an offset which is ignored, because no limit is applied;
+    /// - the query is paginated on SQL Server without a sort.
+    ///
+    /// Limit and offset are always numeric, since `UrlQuery` rejects anything else. `build`
+    /// stays the lenient path: the filters whose value is refused match nothing, see
+    /// `BuiltQuery::rejected`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{sql::{BuildError, QueryBuilder}, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("userId=1", ["userId"]).unwrap();
+    ///
+    /// let result = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone()).build_checked();
+    /// assert_eq!(
+    ///     result,
+    ///     Err(BuildError::UnmappedField { field: "userId".to_owned(), clause: "WHERE" })
+    /// );
+    ///
+    /// let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+    ///     .pass_through(["userId"])
+    ///     .build_checked()
+    ///     .unwrap();
+    /// assert_eq!(sql, "SELECT * FROM orders WHERE userId = $1");
+    /// ```
+    pub fn build_checked(self) -> Result<(String, Vec<Arg>), BuildError> {
+        self.check_denied_fields()?;
+        self.check_computed_sorts()?;
+        self.check_relations()?;
+        self.check_cursor()?;
+        self.check_case_collisions()?;
+
+        let query = &self.url_query;
+        let clauses = query
+            .filters
+            .iter()
+            .map(|filter| (filter.field.as_str(), "WHERE"))
+            .chain(query.havings.iter().map(|h| (h.field.as_str(), "HAVING")))
+            .chain(
+                query
+                    .groups
+                    .iter()
+                    .map(|group| (group.as_str(), "GROUP BY")),
+            )
+            .chain(
+                query
+                    .sorts
+                    .iter()
+                    .map(|sort| (sort.field.as_str(), "ORDER BY")),
+            );
+
+        for (field, clause) in clauses {
+            let relation = field
+                .split_once('.')
+                .is_some_and(|(relation, _)| self.relations.contains_key(relation));
+            let mapped = relation
+                || self.json_path(field).is_some()
+                || self.is_relevance(field)
+                || self.map_columns.contains_key(field)
+                || self.rename_columns.contains_key(field)
+                || self.map_expressions.contains_key(field)
+                || self.map_exists.contains_key(field)
+                || self.map_aggregates.contains_key(field)
+                || self.computed_sorts.contains_key(field)
+                || self.pass_through.contains(field);
+
+            if !mapped {
+                Err(BuildError::UnmappedField {
+                    field: field.to_owned(),
+                    clause,
+                })?
+            }
+        }
+
+        if let (Some(having), true) = (query.havings.first(), query.groups.is_empty()) {
+            Err(BuildError::HavingWithoutGroup(having.field.to_owned()))?
+        }
+
+        let search = self.full_text_search.is_some() && query.search.is_some();
+        if search && !self.dialect.supports_full_text_search() {
+            Err(BuildError::UnsupportedSearch)?
+        }
+
+        for filter in query.filters.iter().chain(query.havings.iter()) {
+            self.check_operator(filter)?;
+            for filter in filter.expand_range() {
+                self.filter_value(&filter)?;
+            }
+
+            if let Operator::HAS | Operator::OVERLAPS = filter.operator {
+                if !self.array_columns.contains(filter.field.as_str()) {
+                    Err(ParseError::OperatorNotAllowed {
+                        field: filter.field.to_owned(),
+                        operator: filter.operator,
+                        raw: filter.raw.clone(),
+                    })?
+                }
+            }
+        }
+
+        if let (Some(sort), Some(after)) = (query.sort(), query.after.as_ref()) {
+            self.key_value(&sort.field, after)?;
+        }
+
+        for (fragment, values) in self.conditions.iter() {
+            let placeholders = split_markers(fragment).len() - 1;
+            if placeholders != values.len() {
+                Err(BuildError::PlaceholderMismatch {
+                    fragment: fragment.to_owned(),
+                    placeholders,
+                    values: values.len(),
+                })?
+            }
+        }
+
+        if let (Some(_), None) = (query.limit_offset.1, self.applied_offset()) {
+            if !self.skip_pagination {
+                Err(BuildError::OffsetWithoutLimit)?
+            }
+        }
+
+        let paginated = self.applied_limit().is_some() || self.applied_offset().is_some();
+        let requires_order_by = self.dialect.pagination_requires_order_by();
+        if paginated && requires_order_by && !self.has_order_by() {
+            Err(BuildError::PaginationWithoutSort)?
+        }
+
+        Ok(self.try_build()?)
+    }
+
+    /// Append LIMIT and OFFSET to the SQL, either as literals or as bind parameters pushed onto
+    /// `args`. OFFSET is only appended along with a LIMIT.
+    fn append_pagination(&mut self, args: &mut Vec<Arg>) {
+        let (limit, offset) = (self.applied_limit(), self.applied_offset());
+        if limit.is_none() && offset.is_none() {
+            return;
+        }
+
+        // Without a sort, `ORDER BY (SELECT NULL)` doesn't order the rows, see `build_checked`.
+        if self.dialect.pagination_requires_order_by() && !self.has_order_by() {
+            self.sql.push_str(" ORDER BY (SELECT NULL)");
+        }
+
+        let bind_offset = self.bind_offset();
+        let mut render = |name: &str, value: Option<u64>| {
+            let value = value?;
+            if !self.bind_pagination {
+                return Some(value.to_string());
+            }
+
+            let idx = args.len() + bind_offset + 1;
+            args.push(Arg::new(name, &value.to_string(), None, idx));
+            Some(self.dialect.placeholder(idx))
+        };
+        let (limit, offset) = match self.dialect.offset_before_limit() {
+            true => {
+                let offset = render("offset", offset);
+                (render("limit", limit), offset)
+            }
+            false => (render("limit", limit), render("offset", offset)),
+        };
+
+        let pagination = self
+            .dialect
+            .render_limit_offset(limit.as_deref(), offset.as_deref());
+        self.sql.push_str(&pagination);
+    }
+}
+
+/// Returns the `map_exists` subquery with its `{op}` and `{placeholder}` replaced, wrapped in
+/// EXISTS.
+fn exists_sql(subquery: &str, op: &str, placeholder: &str) -> String {
+    let subquery = subquery
+        .replace("{op}", op)
+        .replace("{placeholder}", placeholder);
+
+    format!("EXISTS ({})", subquery)
+}
+
+/// Splits an `and_where` fragment on its `{}` markers, leaving those in string literals, quoted
+/// identifiers and comments as they are, eg `'{}'`.
+fn split_markers(fragment: &str) -> Vec<&str> {
+    let is_marker = |bytes: &[u8], i: usize| bytes[i..].starts_with(b"{}");
+
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while let Some(i) = find_unquoted(fragment, start, false, is_marker) {
+        parts.push(&fragment[start..i]);
+        start = i + 2;
+    }
+    parts.push(&fragment[start..]);
+
+    parts
+}
+
+/// Returns the index of the FROM keyword of a SELECT statement, skipping subqueries, string
+/// literals and quoted identifiers.
+fn find_top_level_from(sql: &str) -> Option<usize> {
+    let bytes = sql.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    let start = sql.len() - sql.trim_start().len();
+    if !sql[start..].get(..6)?.eq_ignore_ascii_case("SELECT") {
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut quote = None;
+    for (i, &b) in bytes.iter().enumerate() {
+        match (quote, b) {
+            (Some(q), _) if b == q => quote = None,
+            (Some(_), _) => {}
+            (None, b'\'' | b'"' | b'`') => quote = Some(b),
+            (None, b'(') => depth += 1,
+            (None, b')') => depth -= 1,
+            (None, _) if depth == 0 => {
+                let word = sql.get(i..i + 4);
+                let boundary_before = i == 0 || !is_ident(bytes[i - 1]);
+                let boundary_after = bytes.get(i + 4).is_none_or(|&b| !is_ident(b));
+                if boundary_before
+                    && boundary_after
+                    && word.is_some_and(|word| word.eq_ignore_ascii_case("FROM"))
+                {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Returns true if the SQL starts with `SELECT DISTINCT`, skipping comments such as optimizer
+/// hints in between.
+fn selects_distinct(sql: &str) -> bool {
+    let sql = sql.trim_start();
+    if !sql
+        .get(..6)
+        .is_some_and(|select| select.eq_ignore_ascii_case("SELECT"))
+    {
+        return false;
+    }
+
+    let mut rest = sql[6..].trim_start();
+
+    while let Some(comment) = rest.strip_prefix("/*") {
+        rest = match comment.split_once("*/") {
+            Some((_, rest)) => rest.trim_start(),
+            None => return false,
+        };
+    }
+
+    let is_ident = |b: &u8| b.is_ascii_alphanumeric() || *b == b'_';
+    rest.get(..8)
+        .is_some_and(|distinct| distinct.eq_ignore_ascii_case("DISTINCT"))
+        && !rest.as_bytes().get(8).is_some_and(is_ident)
+}
+
+/// Inserts an optimizer hint after the leading SELECT keyword, or does nothing if the SQL doesn't
+/// start with one.
+fn insert_hint(sql: &str, hint: &str) -> String {
+    let trimmed = sql.trim_start();
+    match trimmed.get(..6) {
+        Some(select) if select.eq_ignore_ascii_case("SELECT") => {
+            let offset = sql.len() - trimmed.len() + 6;
+            format!("{} {}{}", &sql[..offset], hint, &sql[offset..])
+        }
+        _ => sql.to_owned(),
+    }
+}
+
+/// Returns the groups of fields that resolve to the same column after converting to `case`. Use
+/// this to validate the allowed fields at startup; an empty result means there are no collisions.
+///
+/// # Examples
+///
+/// ```
+/// use query::case::NameCase;
+/// use query::sql::case_collisions;
+///
+/// let collisions = case_collisions(["userId", "user_id", "price"], NameCase::Snake);
+///
+/// assert_eq!(collisions, vec![vec!["userId", "user_id"]]);
+/// ```
+pub fn case_collisions<'a>(
+    fields: impl IntoIterator<Item = &'a str>,
+    case: NameCase,
+) -> Vec<Vec<&'a str>> {
+    let mut columns: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for field in fields {
+        let fields = columns.entry(case.convert(field)).or_default();
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+
+    columns
+        .into_values()
+        .filter(|fields| fields.len() > 1)
+        .collect()
+}
+
+/// Returns a deterministic name for a prepared statement, derived from a 64-bit FNV-1a hash of the
+/// SQL. The same SQL always yields the same name, across processes and crate builds.
+///
+/// # Examples
+///
+/// ```
+/// use query::sql::statement_name;
+///
+/// let name = statement_name("q_orders", "SELECT * FROM orders WHERE id = $1");
+///
+/// assert!(name.starts_with("q_orders_"));
+/// assert_eq!(name, statement_name("q_orders", "SELECT * FROM orders WHERE id = $1"));
+/// ```
+pub fn statement_name(prefix: &str, sql: &str) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in sql.bytes() {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    format!("{}_{:016x}", prefix, hash)
+}
+
+fn gen_sql_select(table: &str, columns: Vec<&str>) -> String {
+    let mut sql = String::from("SELECT ");
+    let columns = columns.join(", ");
+    sql.push_str(&columns);
+    sql.push_str(" FROM ");
+    sql.push_str(table);
+    sql
+}
+
+fn append_limit(sql: &mut String, limit: u64) {
+    sql.push_str(" LIMIT ");
+    sql.push_str(&limit.to_string());
+}
+
+/// Bind args to an sqlx query with the required types.
+///
+/// ```ignore
+/// pub async fn get_orders(
+///     pool: &PgPool,
+///     query: UrlQuery,
+/// ) -> Result<Vec<Order>, Either<sqlx::Error, ParseError>> {
+///     let (sql, args) = QueryBuilder::from_str(
+///         "SELECT * FROM orders",
+///         query,
+///     )
+///     .build();
+///
+///     let mut query = sqlx::query_as(&sql);
+///
+///     sqlx_bind!(
+///         args => query,
+///         error: Either::Right(ParseError),
+///         "id" => Uuid,
+///         "userId" => i64,
+///         // Only needed with `bind_pagination(true)`:
+///         "limit" => i64,
+///         "offset" => i64
+///     );
+///
+///     Ok(query.fetch_all(pool).await.map_err(|e| Either::Left(e))?)
+/// }
+/// ```
+///
+/// The error can also be a closure receiving the field and the value which failed to parse, and a
+/// last `_` arm binds the fields which aren't listed instead of skipping them:
+///
+/// ```ignore
+/// sqlx_bind!(
+///     args => query,
+///     error: |field, value| Either::Right(format!("invalid {field}: {value}")),
+///     "id" => Uuid,
+///     _ => String
+/// );
+/// ```
+///
+/// Each arm pairs with the SQL shape of the placeholders of its field:
+///
+/// | SQL                              | Arm                                    |
+/// |----------------------------------|----------------------------------------|
+/// | `user_id = $1`, `price >= $1`    | `"userId" => i64`                      |
+/// | `$1 = ANY(tags)` (`has`)         | `"tags" => String`                     |
+/// | `tags && $1` (`overlaps`)        | `"tags" => Vec<String> (split ",")`    |
+/// | `deleted_at = $1`, may be `NULL` | `"deletedAt" => Option<DateTime<Utc>>` |
+///
+/// The value of an `overlaps` filter is a comma separated list bound to a single placeholder, so
+/// with Postgres arrays its field needs the `Vec` arm, which splits the value and binds a
+/// `Vec<T>`. Every other operator binds one scalar per placeholder.
+///
+/// A field mapped to an `Option` binds `NULL` when its value is empty or `null`, and the parsed
+/// value otherwise:
+///
+/// ```
+/// use query::{sql::QueryBuilder, sqlx_bind, UrlQuery};
+/// use sqlx::{Arguments, Execute, Postgres};
+///
+/// # fn main() -> Result<(), String> {
+/// let parsed = UrlQuery::new("userId=1&parentId=null", ["userId", "parentId"]).unwrap();
+/// let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+///
+/// let mut query = sqlx::query::<Postgres>(&sql);
+///
+/// sqlx_bind!(
+///     args => query,
+///     error: "invalid value".to_owned(),
+///     "userId" => i64,
+///     "parentId" => Option<i64>
+/// );
+///
+/// assert_eq!(query.take_arguments().unwrap().unwrap().len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! sqlx_bind {
+    (@arm $arg:ident, $query:ident, $error:expr, _ => $t:ty $(,)?) => {
+        let parsed: $t = $arg.value.parse().map_err(|_| $error(&$arg.field, &$arg.value))?;
+        $query = $query.bind(parsed);
+    };
+    (@arm $arg:ident, $query:ident, $error:expr, $x:expr => Option<$t:ty> $(, $($rest:tt)*)?) => {
+        if $arg.field == $x {
+            let parsed: Option<$t> = match $arg.value.as_str() {
+                "" | "null" => None,
+                value => Some(value.parse().map_err(|_| $error(&$arg.field, value))?),
+            };
+            $query = $query.bind(parsed);
+        } else {
+            $crate::sqlx_bind!(@arm $arg, $query, $error, $($($rest)*)?);
+        }
+    };
+    (
+        @arm $arg:ident, $query:ident, $error:expr,
+        $x:expr => Vec<$t:ty> (split $separator:literal) $(, $($rest:tt)*)?
+    ) => {
+        if $arg.field == $x {
+            let parsed = $arg
+                .value
+                .split($separator)
+                .filter(|value| !value.is_empty())
+                .map(|value| value.parse().map_err(|_| $error(&$arg.field, value)))
+                .collect::<Result<Vec<$t>, _>>()?;
+            $query = $query.bind(parsed);
+        } else {
+            $crate::sqlx_bind!(@arm $arg, $query, $error, $($($rest)*)?);
+        }
+    };
+    (@arm $arg:ident, $query:ident, $error:expr, $x:expr => $t:ty $(, $($rest:tt)*)?) => {
+        if $arg.field == $x {
+            let parsed: $t = $arg.value.parse().map_err(|_| $error(&$arg.field, &$arg.value))?;
+            $query = $query.bind(parsed);
+        } else {
+            $crate::sqlx_bind!(@arm $arg, $query, $error, $($($rest)*)?);
+        }
+    };
+    (@arm $arg:ident, $query:ident, $error:expr,) => {};
+    (@bind $args:ident => $query:ident, $error:expr, $( $arms:tt )*) => {
+        {
+            for arg in $args {
+                $crate::sqlx_bind!(@arm arg, $query, $error, $($arms)*);
+            }
+        }
+    };
+    (
+        $args:ident => $query:ident,
+        error: |$column:tt $(: $ct:ty)?, $value:tt $(: $vt:ty)?| $body:expr,
+        $( $arms:tt )*
+    ) => {
+        $crate::sqlx_bind!(@bind $args => $query, (|$column: &str, $value: &str| $body), $($arms)*)
+    };
+    ( $args:ident => $query:ident, error: $error:expr, $( $arms:tt )* ) => {
+        $crate::sqlx_bind!(@bind $args => $query, (|_: &str, _: &str| $error), $($arms)*)
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use std::{collections::HashMap, time::Duration};
+
+    use crate::{
+        case::NameCase,
+        cursor::Cursor,
+        dialect::Dialect,
+        filter::{Filter, Operator},
+        safety::SqlSafetyError,
+        sort::SortBy,
+        sql::Database,
+        url_query::Options,
+        value::{ArgValue, FieldType},
+        ParseError, UrlQuery,
+    };
+
+    use super::{
+        case_collisions, find_top_level_from, into_tuples, selects_distinct, statement_name, Arg,
+        BuildError, ColumnInfo, Combinator, LockMode, PlaceholderStyle, QueryBuilder, QueryInfo,
+    };
+
+    #[test]
+    fn test_query_builder_from_str() {
+        let query =
+            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE user_id = $1 AND user_name = $2 \
+        AND order_id = $3 AND price >= $4 \
+        ORDER BY price DESC \
+        LIMIT 10 \
+        OFFSET 0";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 4);
+    }
+
+    #[test]
+    fn test_query_builder_new() {
+        let query =
+            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT id, status FROM orders \
+        WHERE user_id = $1 AND user_name = $2 \
+        AND order_id = $3 AND price >= $4 \
+        ORDER BY price DESC \
+        LIMIT 10 \
+        OFFSET 0";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 4);
+    }
+
+    #[test]
+    fn test_query_builder_new_append_joins() {
+        let query =
+            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
+            .append("JOIN users ON users.id = order.user_id")
+            .append("JOIN inventory ON inventory.id = order.inventory_id")
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT id, status FROM orders \
         JOIN users ON users.id = order.user_id \
         JOIN inventory ON inventory.id = order.inventory_id \
         WHERE user_id = $1 AND user_name = $2 \
@@ -337,92 +3356,3148 @@ mod test {
         OFFSET 0";
 
         assert_eq!(sql, expected);
-        assert_eq!(args.len(), 4);
+        assert_eq!(args.len(), 4);
+
+        // Fragments and joins keep the order they were added in.
+        let parsed = UrlQuery::new("userId=123", ["userId"]).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .join_raw("JOIN users ON users.id = orders.user_id")
+            .append("JOIN teams ON teams.id = users.team_id")
+            .left_join("notes", "notes.order_id = orders.id")
+            .and_where("teams.active", vec![])
+            .append("JOIN regions ON regions.id = teams.region_id")
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders \
+            JOIN users ON users.id = orders.user_id \
+            JOIN teams ON teams.id = users.team_id \
+            LEFT JOIN notes ON notes.order_id = orders.id \
+            JOIN regions ON regions.id = teams.region_id \
+            WHERE userId = $1 AND (teams.active)"
+        );
+    }
+
+    #[test]
+    fn test_query_builder_new_map_columns() {
+        let query = "id=1&group=id,createdAt&sort=createdAt-desc";
+
+        let parsed = UrlQuery::new(query, ["id", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str(
+            "SELECT orders.id, user_id, status, address_id, orders.created_at FROM orders",
+            parsed,
+        )
+        .append("JOIN order_items ON orders.id = order_items.order_id")
+        .append("JOIN inventory ON order_items.inventory_id = inventory.id")
+        .map_columns(HashMap::from([("id", "orders"), ("createdAt", "orders")]))
+        .convert_case(NameCase::Snake)
+        .build();
+
+        let expected =
+            "SELECT orders.id, user_id, status, address_id, orders.created_at FROM orders \
+             JOIN order_items ON orders.id = order_items.order_id \
+             JOIN inventory ON order_items.inventory_id = inventory.id \
+             WHERE orders.id = $1 GROUP BY orders.id, orders.created_at \
+             ORDER BY orders.created_at DESC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 1);
+    }
+
+    #[test]
+    fn test_append_where() {
+        let query = "filter[]=userId-eq-1&filter[]=id-eq-2";
+
+        let parsed = UrlQuery::new(query, ["userId", "id"]).unwrap();
+
+        let mut builder = QueryBuilder::from_str("", parsed);
+
+        let mut args = builder.append_where().into_iter();
+
+        let user_id = args.next().unwrap().value;
+        assert_eq!(user_id, "1");
+
+        let id = args.next().unwrap().value;
+        assert_eq!(id, "2");
+    }
+
+    #[test]
+    fn test_shift_bind() {
+        let query = "filter[]=userId-eq-1&filter[]=id-eq-2";
+
+        let parsed = UrlQuery::new(query, ["userId", "id"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str(
+            "SELECT id, (SELECT postcode FROM address WHERE id = $1) FROM orders",
+            parsed,
+        )
+        .shift_bind(1)
+        .convert_case(NameCase::Snake)
+        .build();
+
+        let expected = "SELECT id, (SELECT postcode FROM address WHERE id = $1) FROM orders WHERE user_id = $2 AND id = $3";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn test_query_builder_set_database_mysql() {
+        let query =
+            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
+            .convert_case(NameCase::Snake)
+            .set_database(Database::MySQL)
+            .build();
+
+        let expected = "SELECT id, status FROM orders \
+        WHERE user_id = ? AND user_name = ? \
+        AND order_id = ? AND price >= ? \
+        ORDER BY price DESC \
+        LIMIT 10 \
+        OFFSET 0";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 4);
+    }
+
+    #[test]
+    fn test_query_builder_having() {
+        let query = "filter[]=status-ne-cancelled&group=status&having=status-ne-pending";
+
+        let parsed = UrlQuery::new(query, ["status"]).unwrap();
+
+        let (sql, args) =
+            QueryBuilder::from_str("SELECT status, COUNT(*) FROM orders", parsed).build();
+
+        let expected = "SELECT status, COUNT(*) FROM orders \
+        WHERE status != $1 \
+        GROUP BY status \
+        HAVING status != $2";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args),
+            vec![
+                ("status".to_owned(), "cancelled".to_owned()),
+                ("status".to_owned(), "pending".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_query_builder_new_sparse_fields() {
+        let options = Options {
+            selectable_fields: HashMap::from([
+                ("orders".into(), vec!["id".into(), "status".into()]),
+                ("users".into(), vec!["email".into()]),
+            ]),
+            ..Default::default()
+        };
+
+        let query = "fields[orders]=status&fields[users]=email";
+        let parsed = UrlQuery::with_options(query, [], options).unwrap();
+
+        let builder = QueryBuilder::new("orders", vec!["id", "status", "price"], parsed);
+        assert_eq!(
+            builder.url_query.sparse_fields("users"),
+            Some(&["email".to_owned()][..])
+        );
+
+        let (sql, _) = builder.build();
+        assert_eq!(sql, "SELECT status FROM orders");
+    }
+
+    #[test]
+    fn test_case_collisions() {
+        let collisions = case_collisions(["userId", "user_id", "price", "Price"], NameCase::Snake);
+        assert_eq!(
+            collisions,
+            vec![vec!["price", "Price"], vec!["userId", "user_id"]]
+        );
+
+        let collisions = case_collisions(["userId", "price"], NameCase::Snake);
+        assert!(collisions.is_empty());
+    }
+
+    #[test]
+    fn test_check_case_collisions() {
+        let query = "userId=1&filter[]=user_id-ne-2";
+        let parsed = UrlQuery::new(query, ["userId", "user_id"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed);
+        assert_eq!(builder.check_case_collisions(), Ok(()));
+
+        let builder = builder.convert_case(NameCase::Snake);
+        assert_eq!(
+            builder.check_case_collisions(),
+            Err(ParseError::FieldCollision("user_id".into()))
+        );
+
+        let query = "userId=1&filter[]=userId-ne-2&sort=userId-asc";
+        let parsed = UrlQuery::new(query, ["userId", "user_id"]).unwrap();
+
+        let builder =
+            QueryBuilder::from_str("SELECT * FROM orders", parsed).convert_case(NameCase::Snake);
+        assert_eq!(builder.check_case_collisions(), Ok(()));
+    }
+
+    #[test]
+    fn test_bind_pagination() {
+        let query = "userId=123&sort=price-desc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["userId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .shift_bind(1)
+            .bind_pagination(true)
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE user_id = $2 \
+        ORDER BY price DESC \
+        LIMIT $3 \
+        OFFSET $4";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args),
+            vec![
+                ("userId".to_owned(), "123".to_owned()),
+                ("limit".to_owned(), "10".to_owned()),
+                ("offset".to_owned(), "20".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bind_pagination_mysql() {
+        let parsed = UrlQuery::new("userId=123&limit=10", ["userId"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::MySQL)
+            .bind_pagination(true)
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders WHERE userId = ? LIMIT ?");
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn test_statement_name() {
+        let build = |query: &str| {
+            let parsed = UrlQuery::new(query, ["userId", "price"]).unwrap();
+            let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .bind_pagination(true)
+                .build();
+
+            statement_name("q_orders", &sql)
+        };
+
+        let name = build("userId=1&sort=price-desc&limit=10&offset=0");
+        assert_eq!(name, build("userId=2&sort=price-desc&limit=10&offset=10"));
+        assert_eq!(name.len(), "q_orders_".len() + 16);
+
+        assert_ne!(name, build("userId=1&sort=price-asc&limit=10&offset=0"));
+        assert_ne!(
+            name,
+            build("userId=1&filter[]=price-gt-1&sort=price-desc&limit=10")
+        );
+        assert_ne!(name, build("userId=1&sort=price-desc"));
+    }
+
+    #[test]
+    fn test_seek_on_sort() {
+        let query = "status=paid&sort=createdAt-desc&after=2024-05-01T10:00:00Z&limit=20";
+
+        let parsed = UrlQuery::new(query, ["status", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_columns(HashMap::from([("createdAt", "orders")]))
+            .convert_case(NameCase::Snake)
+            .seek_on_sort()
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE status = $1 AND orders.created_at < $2 \
+        ORDER BY orders.created_at DESC \
+        LIMIT 20";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            (args[1].field.as_str(), args[1].value.as_str()),
+            ("createdAt", "2024-05-01T10:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_seek_on_sort_asc() {
+        let query = "sort=id-asc&after=100&limit=20";
+
+        let parsed = UrlQuery::new(query, ["id"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .seek_on_sort()
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE id > $1 ORDER BY id ASC LIMIT 20"
+        );
+
+        let parsed = UrlQuery::new(query, ["id"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+        assert_eq!(sql, "SELECT * FROM orders ORDER BY id ASC LIMIT 20");
+    }
+
+    #[test]
+    fn test_try_build_multiple_statements() {
+        let parsed = UrlQuery::new("id=1", ["id"]).unwrap();
+        let result = QueryBuilder::from_str("SELECT 'a;b' FROM users", parsed)
+            .append("JOIN notes ON notes.body != ';'")
+            .try_build();
+        assert!(result.is_ok());
+
+        let parsed = UrlQuery::new("id=1", ["id"]).unwrap();
+        let result = QueryBuilder::from_str("SELECT * FROM users", parsed)
+            .append("; DROP TABLE users")
+            .try_build();
+        assert_eq!(result, Err(SqlSafetyError::MultipleStatements));
+    }
+
+    #[test]
+    fn test_check_denied_fields() {
+        let allowed = ["id", "costBasis", "internalNotes"];
+        let options = Options {
+            selectable_fields: HashMap::from([(
+                "orders".into(),
+                vec!["id".into(), "costBasis".into()],
+            )]),
+            ..Default::default()
+        };
+
+        for (query, field) in [
+            ("filter[]=costBasis-gt-1", "costBasis"),
+            ("internalNotes=x", "internalNotes"),
+            ("sort=costBasis-desc", "costBasis"),
+            ("group=internalNotes", "internalNotes"),
+            ("group=costBasis&having=costBasis-gt-1", "costBasis"),
+            ("fields[orders]=id,costBasis", "costBasis"),
+        ] {
+            let parsed = UrlQuery::with_options(query, allowed, options.clone()).unwrap();
+            let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .deny_fields(["costBasis", "internalNotes"]);
+
+            assert_eq!(
+                builder.check_denied_fields(),
+                Err(ParseError::DeniedField(field.into())),
+                "{}",
+                query
+            );
+        }
+
+        let parsed = UrlQuery::new("id=1&sort=id-asc", allowed).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .deny_fields(["costBasis", "internalNotes"]);
+        assert_eq!(builder.check_denied_fields(), Ok(()));
+    }
+
+    #[test]
+    fn test_seek_on_cursor() {
+        let token = Cursor::encode(["2024-05-01T10:00:00Z", "42"]);
+        let query = format!("status=paid&sort=createdAt-desc&cursor={}&limit=20", token);
+
+        let parsed = UrlQuery::new(&query, ["status", "createdAt"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .seek_on_cursor("id");
+        assert_eq!(builder.check_cursor(), Ok(()));
+
+        let (sql, args) = builder.build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE status = $1 AND (created_at, id) < ($2, $3) \
+        ORDER BY created_at DESC, id DESC \
+        LIMIT 20";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args[1..].to_vec()),
+            [
+                ("createdAt".to_owned(), "2024-05-01T10:00:00Z".to_owned()),
+                ("id".to_owned(), "42".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_seek_on_cursor_mysql() {
+        let token = Cursor::encode(["100", "42"]);
+        let query = format!("sort=price-asc&cursor={}", token);
+
+        let parsed = UrlQuery::new(&query, ["price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::MySQL)
+            .seek_on_cursor("id")
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE (price > ? OR (price = ? AND id > ?)) \
+        ORDER BY price ASC, id ASC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 3);
+    }
+
+    #[test]
+    fn test_check_cursor() {
+        let token = Cursor::encode(["100"]);
+        let query = format!("sort=price-asc&cursor={}", token);
+
+        let parsed = UrlQuery::new(&query, ["price"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed).seek_on_cursor("id");
+        assert_eq!(builder.check_cursor(), Err(ParseError::InvalidCursor));
+
+        let (sql, args) = builder.build();
+        assert_eq!(sql, "SELECT * FROM orders ORDER BY price ASC, id ASC");
+        assert!(args.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_policy() {
+        let policy = |info: &QueryInfo| match info.offset {
+            Some(offset) if offset > 1000 => Some(Duration::from_millis(500)),
+            _ => None,
+        };
+
+        let parsed = UrlQuery::new("limit=10&offset=5000", []).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed).timeout_policy(policy);
+        assert_eq!(
+            builder.timeout_statement().as_deref(),
+            Some("SET LOCAL statement_timeout = 500")
+        );
+        let (sql, _) = builder.build();
+        assert_eq!(sql, "SELECT * FROM orders LIMIT 10 OFFSET 5000");
+
+        let parsed = UrlQuery::new("limit=10&offset=5000", []).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::MySQL)
+            .timeout_policy(policy);
+        assert_eq!(builder.timeout_statement(), None);
+        let (sql, _) = builder.build();
+        assert_eq!(
+            sql,
+            "SELECT /*+ MAX_EXECUTION_TIME(500) */ * FROM orders LIMIT 10 OFFSET 5000"
+        );
+
+        let parsed = UrlQuery::new("limit=10&offset=0", []).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::MySQL)
+            .timeout_policy(policy);
+        let (sql, _) = builder.build();
+        assert_eq!(sql, "SELECT * FROM orders LIMIT 10 OFFSET 0");
+
+        let parsed = UrlQuery::new("limit=10&offset=0", []).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed).timeout_policy(policy);
+        assert_eq!(builder.timeout_statement(), None);
+    }
+
+    #[test]
+    fn test_set_dialect() {
+        struct Cockroach;
+
+        impl Dialect for Cockroach {
+            fn placeholder(&self, index: usize) -> String {
+                format!("${}", index)
+            }
+
+            fn quote_ident(&self, ident: &str) -> String {
+                format!("\"{}\"", ident)
+            }
+
+            fn placeholder_prefix(&self) -> Option<&str> {
+                Some("$")
+            }
+
+            fn supports_shared_placeholders(&self) -> bool {
+                true
+            }
+
+            fn timeout_statement(&self, timeout: Duration) -> Option<String> {
+                Some(format!("SET statement_timeout = {}", timeout.as_millis()))
+            }
+
+            fn supports_mutation_limit(&self) -> bool {
+                true
+            }
+        }
+
+        let query = "status=active&filter[]=visibility-eq-active&limit=10";
+        let parsed = UrlQuery::new(query, ["status", "visibility"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_dialect(Box::new(Cockroach))
+            .field_types(HashMap::from([
+                ("status", FieldType::Text),
+                ("visibility", FieldType::Text),
+            ]))
+            .lock(LockMode::ForUpdate { skip_locked: true })
+            .timeout_policy(|_| Some(Duration::from_millis(500)))
+            .dedupe_binds();
+
+        assert_eq!(
+            builder.timeout_statement().as_deref(),
+            Some("SET statement_timeout = 500")
+        );
+
+        let (sql, args) = builder.clone().build();
+        let expected = "SELECT * FROM orders WHERE status = $1 AND visibility = $1 \
+        LIMIT 10 FOR UPDATE SKIP LOCKED";
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 1);
+
+        let (sql, _) = builder.placeholder_style(PlaceholderStyle::Named).build();
+        let expected = "SELECT * FROM orders WHERE status = :status AND visibility = :visibility \
+        LIMIT 10 FOR UPDATE SKIP LOCKED";
+        assert_eq!(sql, expected);
+
+        // The limit of a mutation is bound with the placeholders of the dialect.
+        let parsed = UrlQuery::new("status=archived&limit=100", ["status"]).unwrap();
+        let (sql, args) = QueryBuilder::delete("orders", parsed)
+            .set_dialect(Box::new(Cockroach))
+            .bind_pagination(true)
+            .try_build()
+            .unwrap();
+        assert_eq!(sql, "DELETE FROM orders WHERE status = $1 LIMIT $2");
+        assert_eq!(args[1], Arg::new("limit", "100", None, 2));
+    }
+
+    #[test]
+    fn test_query_info() {
+        let query = "userId=1&filter[]=price-ge-200&sort=price-desc&limit=10";
+        let parsed = UrlQuery::new(query, ["userId", "price"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed);
+        let info = builder.query_info();
+
+        assert_eq!(
+            info.filters,
+            vec![("userId", Operator::EQ), ("price", Operator::GE)]
+        );
+        assert_eq!(info.sorts[0].field, "price");
+        assert_eq!(info.limit, Some(10));
+        assert_eq!(info.offset, None);
+    }
+
+    #[test]
+    fn test_build_count() {
+        let query = "id=1&sort=createdAt-desc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["id", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str(
+            "SELECT orders.id, (SELECT COUNT(*) FROM order_items) AS items, orders.created_at \
+             FROM orders",
+            parsed,
+        )
+        .append("JOIN order_items ON orders.id = order_items.order_id")
+        .append("JOIN inventory ON order_items.inventory_id = inventory.id")
+        .map_columns(HashMap::from([("id", "orders"), ("createdAt", "orders")]))
+        .convert_case(NameCase::Snake)
+        .shift_bind(1)
+        .bind_pagination(true)
+        .build_count();
+
+        let expected = "SELECT COUNT(*) FROM orders \
+             JOIN order_items ON orders.id = order_items.order_id \
+             JOIN inventory ON order_items.inventory_id = inventory.id \
+             WHERE orders.id = $2";
+
+        assert_eq!(sql, expected);
+        assert_eq!(into_tuples(args), vec![("id".to_owned(), "1".to_owned())]);
+    }
+
+    #[test]
+    fn test_build_count_group() {
+        let query = "filter[]=status-ne-cancelled&group=status&having=status-ne-pending";
+
+        let parsed = UrlQuery::new(query, ["status"]).unwrap();
+
+        let (sql, args) =
+            QueryBuilder::from_str("SELECT status, COUNT(*) FROM orders", parsed).build_count();
+
+        let expected = "SELECT COUNT(*) FROM (SELECT status, COUNT(*) FROM orders \
+        WHERE status != $1 \
+        GROUP BY status \
+        HAVING status != $2) AS count_query";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn test_build_count_distinct() {
+        let parsed = UrlQuery::new("status=paid", ["status"]).unwrap();
+
+        let (sql, args) =
+            QueryBuilder::from_str("SELECT DISTINCT customer_id FROM orders", parsed.clone())
+                .build_count();
+
+        let expected = "SELECT COUNT(*) FROM (SELECT DISTINCT customer_id FROM orders \
+        WHERE status = $1) AS count_query";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 1);
+
+        let (sql, _) = QueryBuilder::from_str("select distinct\ncustomer_id FROM orders", parsed)
+            .build_count();
+        assert!(
+            sql.starts_with("SELECT COUNT(*) FROM (select distinct"),
+            "{}",
+            sql
+        );
+
+        assert!(!selects_distinct("SELECT distinct_id FROM orders"));
+        assert!(selects_distinct(
+            "SELECT /*+ MAX_EXECUTION_TIME(100) */ DISTINCT a FROM t"
+        ));
+    }
+
+    #[test]
+    fn test_find_top_level_from() {
+        assert_eq!(find_top_level_from("SELECT * FROM orders"), Some(9));
+        assert_eq!(
+            find_top_level_from("select 'from' AS fromage from orders"),
+            Some(25)
+        );
+        assert_eq!(
+            find_top_level_from("SELECT (SELECT 1 FROM a) FROM orders"),
+            Some(25)
+        );
+        assert_eq!(
+            find_top_level_from("WITH a AS (SELECT 1) SELECT * FROM a"),
+            None
+        );
+        assert_eq!(find_top_level_from("SELECT 1"), None);
+    }
+
+    #[test]
+    fn test_with_total() {
+        let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+        let (sql, _) = QueryBuilder::new("orders", vec!["id", "price * 2 AS double"], parsed)
+            .append("JOIN users ON users.id = orders.user_id")
+            .with_total()
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT id, price * 2 AS double, COUNT(*) OVER() AS total_count FROM orders \
+            JOIN users ON users.id = orders.user_id WHERE userId = $1 LIMIT 10"
+        );
+
+        let parsed = UrlQuery::new("", []).unwrap();
+        let (sql, _) = QueryBuilder::new("orders", vec!["id"], parsed)
+            .set_database(Database::MySQL)
+            .with_total()
+            .build();
+        assert_eq!(sql, "SELECT id, COUNT(*) OVER() AS total_count FROM orders");
+
+        let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT id FROM orders", parsed)
+            .with_total()
+            .build();
+        assert_eq!(sql, "SELECT id FROM orders WHERE userId = $1 LIMIT 10");
+
+        let parsed = UrlQuery::new("", []).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT id FROM orders", parsed)
+            .with_total()
+            .build();
+        assert_eq!(sql, "SELECT id FROM orders");
+    }
+
+    #[test]
+    fn test_build_paginated() {
+        let query = "id=1&sort=createdAt-desc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["id", "createdAt"]).unwrap();
+
+        let paginated =
+            QueryBuilder::from_str("SELECT orders.id, orders.created_at FROM orders", parsed)
+                .append("JOIN order_items ON orders.id = order_items.order_id")
+                .map_columns(HashMap::from([("id", "orders"), ("createdAt", "orders")]))
+                .convert_case(NameCase::Snake)
+                .shift_bind(1)
+                .bind_pagination(true)
+                .build_paginated();
+
+        assert_eq!(
+            paginated.data_sql,
+            "SELECT orders.id, orders.created_at FROM orders \
+            JOIN order_items ON orders.id = order_items.order_id \
+            WHERE orders.id = $2 \
+            ORDER BY orders.created_at DESC \
+            LIMIT $3 OFFSET $4"
+        );
+        assert_eq!(
+            paginated.count_sql,
+            "SELECT COUNT(*) FROM orders \
+            JOIN order_items ON orders.id = order_items.order_id \
+            WHERE orders.id = $2"
+        );
+        assert_eq!(paginated.args.len(), 3);
+        assert_eq!(
+            into_tuples(paginated.count_args().to_vec()),
+            [("id".to_owned(), "1".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_multiple_sorts() {
+        let query = "sort=price-desc,createdAt-asc";
+
+        let parsed = UrlQuery::new(query, ["price", "createdAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_columns(HashMap::from([("createdAt", "orders")]))
+            .convert_case(NameCase::Snake)
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders ORDER BY price DESC, orders.created_at ASC"
+        );
+    }
+
+    #[test]
+    fn test_seek_on_cursor_multiple_sorts() {
+        let token = Cursor::encode(["100", "2024-05-01", "42"]);
+        let query = format!("sort=price-desc,createdAt-desc&cursor={}", token);
+
+        let parsed = UrlQuery::new(&query, ["price", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .seek_on_cursor("id")
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE (price, created_at, id) < ($1, $2, $3) \
+        ORDER BY price DESC, created_at DESC, id DESC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 3);
+
+        let query = format!("sort=price-desc,createdAt-asc&cursor={}", token);
+
+        let parsed = UrlQuery::new(&query, ["price", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .seek_on_cursor("id")
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE (price < $1 \
+        OR (price = $2 AND created_at > $3) \
+        OR (price = $4 AND created_at = $5 AND id > $6)) \
+        ORDER BY price DESC, created_at ASC, id ASC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            args.iter()
+                .map(|arg| arg.value.as_str())
+                .collect::<Vec<_>>(),
+            vec!["100", "100", "2024-05-01", "100", "2024-05-01", "42"]
+        );
+    }
+
+    #[test]
+    fn test_computed_sortable() {
+        let last_order_at =
+            "(SELECT MAX(o.created_at) FROM orders o WHERE o.customer_id = customers.id)";
+
+        let parsed = UrlQuery::new("sort=-lastOrderAt,name", ["lastOrderAt", "name"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM customers", parsed)
+            .computed_sortable("lastOrderAt", last_order_at);
+
+        assert_eq!(builder.check_computed_sorts(), Ok(()));
+
+        let expected = "SELECT * FROM customers \
+        ORDER BY (SELECT MAX(o.created_at) FROM orders o WHERE o.customer_id = customers.id) DESC, \
+        name ASC";
+
+        assert_eq!(builder.build().0, expected);
+    }
+
+    #[test]
+    fn test_check_computed_sorts() {
+        let last_order_at =
+            "(SELECT MAX(o.created_at) FROM orders o WHERE o.customer_id = customers.id)";
+        let allowed = ["lastOrderAt", "id"];
+
+        let parsed = UrlQuery::new("sort=-lastOrderAt", allowed).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM customers", parsed).computed_sortable(
+            "lastOrderAt",
+            "(SELECT MAX(created_at) FROM orders WHERE id = $1)",
+        );
+        assert_eq!(
+            builder.check_computed_sorts(),
+            Err(ParseError::ComputedSortPlaceholder("lastOrderAt".into()))
+        );
+
+        let parsed = UrlQuery::new("filter[]=lastOrderAt-gt-2024", allowed).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM customers", parsed)
+            .computed_sortable("lastOrderAt", last_order_at);
+        assert_eq!(
+            builder.check_computed_sorts(),
+            Err(ParseError::ComputedSortOnly("lastOrderAt".into()))
+        );
+
+        let parsed = UrlQuery::new("sort=-lastOrderAt&after=2024", allowed).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM customers", parsed)
+            .computed_sortable("lastOrderAt", last_order_at)
+            .seek_on_sort();
+        assert_eq!(
+            builder.check_computed_sorts(),
+            Err(ParseError::ComputedSortKeyset("lastOrderAt".into()))
+        );
+        assert_eq!(
+            builder.build().0,
+            format!("SELECT * FROM customers ORDER BY {} DESC", last_order_at)
+        );
+
+        let token = Cursor::encode(["2024", "42"]);
+        let query = format!("sort=-lastOrderAt&cursor={}", token);
+        let parsed = UrlQuery::new(&query, allowed).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM customers", parsed)
+            .computed_sortable("lastOrderAt", last_order_at)
+            .seek_on_cursor("id");
+        assert_eq!(
+            builder.check_computed_sorts(),
+            Err(ParseError::ComputedSortKeyset("lastOrderAt".into()))
+        );
+        assert_eq!(builder.build().1, vec![]);
+    }
+
+    #[test]
+    fn test_columns() {
+        let parsed = UrlQuery::new("", ["id"]).unwrap();
+
+        let builder = QueryBuilder::new(
+            "users",
+            vec!["users.id", "first_name AS firstName", "COUNT(*) as total"],
+            parsed,
+        )
+        .with_total();
+
+        let names: Vec<_> = builder.columns().iter().map(|c| c.output_name()).collect();
+        assert_eq!(names, vec!["id", "firstName", "total", "total_count"]);
+
+        // Only a top-level AS gives an alias.
+        let cases = [
+            ("CAST(price AS numeric)", "CAST(price AS numeric)", None),
+            (
+                "CAST(price AS numeric) AS price",
+                "CAST(price AS numeric)",
+                Some("price"),
+            ),
+            (
+                "COALESCE(nickname, 'a as b') AS name",
+                "COALESCE(nickname, 'a as b')",
+                Some("name"),
+            ),
+            ("\"a AS b\"", "\"a AS b\"", None),
+        ];
+        for (column, name, alias) in cases {
+            let info = ColumnInfo::new(column);
+            assert_eq!(info.name, name, "{}", column);
+            assert_eq!(info.alias.as_deref(), alias, "{}", column);
+        }
+        assert_eq!(
+            builder.columns()[1],
+            ColumnInfo {
+                name: "first_name".into(),
+                alias: Some("firstName".into()),
+            }
+        );
+
+        let mut options = Options::default();
+        options
+            .selectable_fields
+            .insert("users".into(), vec!["id".into(), "email".into()]);
+        let parsed = UrlQuery::with_options("fields[users]=email", ["id"], options).unwrap();
+
+        let builder = QueryBuilder::new("users", vec!["id", "first_name"], parsed);
+        let names: Vec<_> = builder.columns().iter().map(|c| c.output_name()).collect();
+        assert_eq!(names, vec!["email"]);
+
+        let parsed = UrlQuery::new("", ["id"]).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM users", parsed).with_total();
+        assert!(builder.columns().is_empty());
+    }
+
+    #[test]
+    fn test_same_field_in_every_clause() {
+        let clauses = [
+            "filter[]=userName-ne-bob",
+            "userName=alice",
+            "sort=userName-desc",
+            "group=userName",
+            "having=userName-ne-carol",
+        ];
+
+        for mask in 1..(1 << clauses.len()) {
+            let parts: Vec<&str> = (0..clauses.len())
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| clauses[i])
+                .collect();
+            let grouped = parts.contains(&"group=userName");
+            if parts.contains(&"having=userName-ne-carol") && !grouped {
+                continue;
+            }
+
+            // Every clause twice, which must not repeat predicates or sort keys.
+            let query = [parts.clone(), parts.clone()].concat().join("&");
+
+            let parsed = UrlQuery::new(&query, ["userName"]).unwrap();
+            let (sql, args) = QueryBuilder::from_str("SELECT user_name FROM users", parsed)
+                .map_columns(HashMap::from([("userName", "users")]))
+                .convert_case(NameCase::Snake)
+                .try_build()
+                .unwrap();
+
+            assert!(!sql.contains("userName"), "{}", sql);
+
+            let predicates = parts
+                .iter()
+                .filter(|p| !p.starts_with("sort") && !p.starts_with("group"));
+            assert_eq!(args.len(), predicates.count(), "{}", sql);
+            assert_eq!(sql.matches('$').count(), args.len(), "{}", sql);
+
+            let sorted = parts.contains(&"sort=userName-desc");
+            for (clause, expected) in [
+                ("GROUP BY users.user_name", grouped),
+                ("ORDER BY users.user_name DESC", sorted),
+            ] {
+                assert_eq!(sql.contains(clause), expected, "{}", sql);
+                assert!(!sql.contains(&format!("{}, ", clause)), "{}", sql);
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_sort() {
+        let parsed = UrlQuery::new("limit=10&offset=20", ["price"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_columns(HashMap::from([("createdAt", "orders")]))
+            .convert_case(NameCase::Snake)
+            .default_sort("createdAt", SortBy::DESC)
+            .default_sort("id", SortBy::ASC)
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders ORDER BY orders.created_at DESC, id ASC LIMIT 10 OFFSET 20"
+        );
+
+        let parsed = UrlQuery::new("sort=price-asc&limit=10", ["price"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .default_sort("createdAt", SortBy::DESC)
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders ORDER BY price ASC LIMIT 10");
+    }
+
+    #[test]
+    fn test_tie_breaker() {
+        let parsed = UrlQuery::new("sort=price-desc&limit=10", ["price", "id"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_columns(HashMap::from([("id", "orders")]))
+            .tie_breaker("id", SortBy::ASC)
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders ORDER BY price DESC, orders.id ASC LIMIT 10"
+        );
+
+        let parsed = UrlQuery::new("sort=id-desc", ["price", "id"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .tie_breaker("id", SortBy::ASC)
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders ORDER BY id DESC");
+
+        let parsed = UrlQuery::new("", ["price", "id"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .default_sort("createdAt", SortBy::DESC)
+            .tie_breaker("id", SortBy::DESC)
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders ORDER BY createdAt DESC, id DESC");
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .tie_breaker("id", SortBy::ASC);
+        assert_eq!(builder.build().0, "SELECT * FROM orders");
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .tie_breaker("id", SortBy::ASC)
+            .tie_breaker_always();
+        assert_eq!(builder.build().0, "SELECT * FROM orders ORDER BY id ASC");
+    }
+
+    #[test]
+    fn test_map_expressions() {
+        let query = "filter[]=total-ge-100&sort=total-desc,userEmail-asc";
+
+        let parsed = UrlQuery::new(query, ["total", "userEmail"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_expressions(HashMap::from([
+                ("total", "(price * quantity)"),
+                ("userEmail", "LOWER(users.email)"),
+            ]))
+            .map_columns(HashMap::from([("total", "orders")]))
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE (price * quantity) >= $1 \
+        ORDER BY (price * quantity) DESC, LOWER(users.email) ASC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args),
+            vec![("total".to_owned(), "100".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_no_convert_case() {
+        let query = "filter[]=createdAt-ge-2024&group=createdAt&sort=createdAt-desc";
+
+        let parsed = UrlQuery::new(query, ["createdAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT createdAt FROM orders", parsed).build();
+
+        let expected = "SELECT createdAt FROM orders \
+        WHERE createdAt >= $1 \
+        GROUP BY createdAt \
+        ORDER BY createdAt DESC";
+
+        assert_eq!(sql, expected);
+    }
+
+    #[test]
+    fn test_map_aggregates() {
+        let options = Options {
+            aggregates: vec!["count".into()],
+            ..Default::default()
+        };
+        let query = "filter[]=region-ne-eu&group=status&having[]=count-ge-10&sort=status-asc";
+
+        let parsed = UrlQuery::with_options(query, ["status", "region"], options).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT status, COUNT(*) FROM orders", parsed)
+            .map_aggregates(HashMap::from([("count", "COUNT(*)")]))
+            .build();
+
+        let expected = "SELECT status, COUNT(*) FROM orders \
+        WHERE region != $1 \
+        GROUP BY status \
+        HAVING COUNT(*) >= $2 \
+        ORDER BY status ASC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args),
+            vec![
+                ("region".to_owned(), "eu".to_owned()),
+                ("count".to_owned(), "10".to_owned())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quote_identifiers() {
+        let query = "order=1&group=user,group&sort=group-desc";
+        let allowed = ["order", "user", "group"];
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+
+        let (sql, _) = QueryBuilder::new("orders", vec!["id", "order", "COUNT(*) AS n"], parsed)
+            .map_columns(HashMap::from([("order", "orders")]))
+            .quote_identifiers(true)
+            .with_total()
+            .build();
+
+        let expected = "SELECT \"id\", \"order\", COUNT(*) AS n, COUNT(*) OVER() AS total_count \
+        FROM \"orders\" \
+        WHERE \"orders\".\"order\" = $1 \
+        GROUP BY \"user\", \"group\" \
+        ORDER BY \"group\" DESC";
+
+        assert_eq!(sql, expected);
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+
+        let (sql, _) = QueryBuilder::new("orders", vec!["id", "order"], parsed)
+            .quote_identifiers(true)
+            .map_columns(HashMap::from([("order", "orders")]))
+            .set_database(Database::MySQL)
+            .build();
+
+        let expected = "SELECT `id`, `order` FROM `orders` \
+        WHERE `orders`.`order` = ? \
+        GROUP BY `user`, `group` \
+        ORDER BY `group` DESC";
+
+        assert_eq!(sql, expected);
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+
+        let (sql, _) = QueryBuilder::new("orders", vec!["id", "order"], parsed)
+            .map_columns(HashMap::from([("order", "orders")]))
+            .build();
+
+        let expected = "SELECT id, order FROM orders \
+        WHERE orders.order = $1 \
+        GROUP BY user, group \
+        ORDER BY group DESC";
+
+        assert_eq!(sql, expected);
+    }
+
+    #[test]
+    fn test_rename_columns() {
+        let query = "customer=7&group=customer&sort=customer-desc";
+
+        let parsed = UrlQuery::new(query, ["customer"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .rename_columns(HashMap::from([("customer", "clientId")]))
+            .map_columns(HashMap::from([("customer", "orders")]))
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE orders.client_id = $1 \
+        GROUP BY orders.client_id \
+        ORDER BY orders.client_id DESC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            into_tuples(args),
+            vec![("customer".to_owned(), "7".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_skip_case_for() {
+        let query = "iOSVersion=17&userName=bob&group=iOSVersion,userName&sort=iOSVersion-desc";
+
+        let parsed = UrlQuery::new(query, ["iOSVersion", "userName"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM devices", parsed)
+            .convert_case(NameCase::Snake)
+            .skip_case_for(["iOSVersion"])
+            .quote_identifiers(true)
+            .build();
+
+        let expected = "SELECT * FROM devices \
+        WHERE \"iOSVersion\" = $1 AND \"user_name\" = $2 \
+        GROUP BY \"iOSVersion\", \"user_name\" \
+        ORDER BY \"iOSVersion\" DESC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            (args[0].field.as_str(), args[0].value.as_str()),
+            ("iOSVersion", "17")
+        );
+        assert_eq!(
+            (args[1].field.as_str(), args[1].value.as_str()),
+            ("userName", "bob")
+        );
+    }
+
+    #[test]
+    fn test_base_filter() {
+        let parsed = UrlQuery::new("userId=1&filter[]=price-ge-10", ["userId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .base_filter("status", Operator::NE, "deleted")
+            .convert_case(NameCase::Snake)
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE tenant_id = $1 AND status != $2 AND user_id = $3 AND price >= $4";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            args[1],
+            Arg {
+                field: "status".to_owned(),
+                value: "deleted".to_owned(),
+                comparator: Some(Operator::NE),
+                index: 2,
+            }
+        );
+        assert_eq!(
+            into_tuples(args)[0],
+            ("tenant_id".to_owned(), "42".to_owned())
+        );
+
+        let parsed = UrlQuery::new("limit=10", ["userId"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders WHERE tenant_id = $1 LIMIT 10");
+        assert_eq!(args.len(), 1);
+    }
+
+    #[test]
+    fn test_and_where() {
+        let parsed = UrlQuery::new("userId=1", ["userId"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM stores", parsed)
+            .and_where(
+                "ST_DWithin(location, {}, {})",
+                vec![("point", "POINT(1 2)"), ("radius", "500")],
+            )
+            .and_where("id = ANY({})", vec![("ids", "{1,2}")])
+            .shift_bind(1)
+            .build();
+
+        let expected = "SELECT * FROM stores \
+        WHERE userId = $2 AND (ST_DWithin(location, $3, $4)) AND (id = ANY($5))";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            args.iter()
+                .map(|arg| arg.field.as_str())
+                .collect::<Vec<_>>(),
+            vec!["userId", "point", "radius", "ids"]
+        );
+
+        let parsed = UrlQuery::new("", ["userId"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM stores", parsed)
+            .and_where("id = ANY({})", vec![("ids", "{1,2}")])
+            .set_database(Database::MySQL)
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM stores WHERE (id = ANY(?))");
+        assert_eq!(
+            into_tuples(args),
+            vec![("ids".to_owned(), "{1,2}".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_and_where_mismatched_values() {
+        let parsed = UrlQuery::new("", ["userId"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM stores", parsed)
+            .base_filter("tenant_id", Operator::EQ, "7")
+            .and_where("id = {} OR id = {}", vec![("id", "1")]);
+
+        let mismatch = BuildError::PlaceholderMismatch {
+            fragment: "id = {} OR id = {}".to_owned(),
+            placeholders: 2,
+            values: 1,
+        };
+        let built = builder.clone().build_parts();
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM stores WHERE tenant_id = $1 AND (1 = 0)"
+        );
+        assert_eq!(built.args.len(), 1);
+        assert_eq!(built.rejected, vec![mismatch.clone()]);
+        assert_eq!(builder.build_checked(), Err(mismatch));
+
+        // Braces in a literal aren't markers.
+        let parsed = UrlQuery::new("", ["userId"]).unwrap();
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM stores", parsed)
+            .and_where("tags <> '{}' AND owner_id = {}", vec![("owner", "3")])
+            .build_checked()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM stores WHERE (tags <> '{}' AND owner_id = $1)"
+        );
+        assert_eq!(args.len(), 1);
+    }
+
+    #[test]
+    fn test_filter_combinator_or() {
+        let cases = [
+            ("", "SELECT * FROM orders WHERE tenant_id = $1"),
+            (
+                "status=paid",
+                "SELECT * FROM orders WHERE tenant_id = $1 AND status = $2",
+            ),
+            (
+                "status=paid&filter[]=price-ge-10&userId=3",
+                "SELECT * FROM orders \
+                WHERE tenant_id = $1 AND (status = $2 OR price >= $3 OR userId = $4)",
+            ),
+        ];
+
+        for (query, expected) in cases {
+            let parsed = UrlQuery::new(query, ["status", "price", "userId"]).unwrap();
+
+            let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .base_filter("tenant_id", Operator::EQ, "42")
+                .filter_combinator(Combinator::Or)
+                .build();
+
+            assert_eq!(sql, expected);
+        }
+
+        let parsed = UrlQuery::new("status=paid&userId=3", ["status", "userId"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .filter_combinator(Combinator::Or)
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE (status = $1 OR userId = $2)"
+        );
+    }
+
+    #[test]
+    fn test_exclude_soft_deleted() {
+        let parsed = UrlQuery::new("", ["deletedAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_columns(HashMap::from([("deleted_at", "orders")]))
+            .exclude_soft_deleted("deleted_at")
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders WHERE orders.deleted_at IS NULL");
+        assert!(args.is_empty());
+
+        let parsed = UrlQuery::new("status=paid", ["status", "deletedAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .exclude_soft_deleted("deleted_at")
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE tenant_id = $1 AND deleted_at IS NULL AND status = $2";
+
+        assert_eq!(sql, expected);
+
+        let query = "filter[]=deletedAt-gt-2024-01-01";
+        let parsed = UrlQuery::new(query, ["deletedAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .exclude_soft_deleted("deleted_at")
+            .allow_soft_deleted_filter()
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM orders WHERE deleted_at > $1");
+
+        // Without the opt-in, filters on the column keep the guard, whatever their operator.
+        for (query, condition) in [
+            ("deletedAt=2024-01-01", "deleted_at = $1"),
+            ("filter[]=deletedAt-ge-2020-01-01", "deleted_at >= $1"),
+        ] {
+            let parsed = UrlQuery::new(query, ["deletedAt"]).unwrap();
+
+            let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .convert_case(NameCase::Snake)
+                .exclude_soft_deleted("deleted_at")
+                .build();
+
+            let expected = format!(
+                "SELECT * FROM orders WHERE deleted_at IS NULL AND {}",
+                condition
+            );
+            assert_eq!(sql, expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_builder_methods_in_any_order() {
+        let query = "userName=bob&sort=createdAt-desc&limit=10";
+        let allowed = ["userName", "createdAt"];
+
+        let expected = "SELECT * FROM orders \
+        JOIN users ON users.id = orders.user_id \
+        WHERE tenant_id = $1 AND users.user_name = $2 AND (total > {}) \
+        ORDER BY created_at DESC, id ASC \
+        LIMIT 10";
+
+        let parsed = UrlQuery::new(query, allowed).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .join_raw("JOIN users ON users.id = orders.user_id")
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .and_where("total > {}", vec![("total", "0")])
+            .map_columns(HashMap::from([("userName", "users")]))
+            .convert_case(NameCase::Snake)
+            .tie_breaker("id", SortBy::ASC)
+            .build();
+
+        assert_eq!(sql, expected.replace("{}", "$3"));
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .tie_breaker("id", SortBy::ASC)
+            .convert_case(NameCase::Snake)
+            .and_where("total > {}", vec![("total", "0")])
+            .map_columns(HashMap::from([("userName", "users")]))
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .join_raw("JOIN users ON users.id = orders.user_id")
+            .build();
+
+        assert_eq!(sql, expected.replace("{}", "$3"));
+    }
+
+    #[test]
+    fn test_join() {
+        let query = "userName=bob&sort=createdAt-desc";
+
+        let parsed = UrlQuery::new(query, ["userName", "createdAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT orders.* FROM orders", parsed)
+            .append("JOIN tenants ON tenants.id = orders.tenant_id")
+            .join("users", "users.id = orders.user_id")
+            .left_join("addresses", "addresses.id = orders.address_id")
+            .join("users", "users.id = orders.user_id")
+            .join_when_used("payments", "payments.order_id = orders.id")
+            .join_when_used("shipments", "shipments.order_id = orders.id")
+            .map_columns(HashMap::from([
+                ("userName", "users"),
+                ("createdAt", "payments"),
+            ]))
+            .build();
+
+        let expected = "SELECT orders.* FROM orders \
+        JOIN tenants ON tenants.id = orders.tenant_id \
+        JOIN users ON users.id = orders.user_id \
+        LEFT JOIN addresses ON addresses.id = orders.address_id \
+        JOIN payments ON payments.order_id = orders.id \
+        WHERE users.userName = $1 \
+        ORDER BY payments.createdAt DESC";
+
+        assert_eq!(sql, expected);
+    }
+
+    #[test]
+    fn test_with_cte() {
+        let query = "filter[]=userId-eq-1&filter[]=id-eq-2";
+
+        let parsed = UrlQuery::new(query, ["userId", "id"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str(
+            "SELECT id, (SELECT postcode FROM recent) FROM orders",
+            parsed,
+        )
+        .with_cte("recent", "SELECT postcode FROM address WHERE id = $1")
+        .with_cte(
+            "active",
+            "SELECT id FROM users WHERE status = $2 AND region = $3",
+        )
+        .shift_bind(1)
+        .convert_case(NameCase::Snake)
+        .build();
+
+        let expected = "WITH recent AS (SELECT postcode FROM address WHERE id = $1), \
+        active AS (SELECT id FROM users WHERE status = $2 AND region = $3) \
+        SELECT id, (SELECT postcode FROM recent) FROM orders WHERE user_id = $5 AND id = $6";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 2);
+    }
+
+    #[test]
+    fn test_map_exists() {
+        let query = "status=paid&itemSku=A1&filter[]=itemSku-ne-B2&filter[]=price-ge-10";
+        let subquery = "SELECT 1 FROM order_items oi \
+        WHERE oi.order_id = orders.id AND oi.sku {op} {placeholder}";
+
+        let parsed = UrlQuery::new(query, ["status", "itemSku", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .map_exists("itemSku", subquery)
+            .build();
+
+        let expected = "SELECT * FROM orders WHERE status = $1 \
+        AND EXISTS (SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku = $2) \
+        AND EXISTS (SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku != $3) \
+        AND price >= $4";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            (args[1].field.as_str(), args[1].value.as_str()),
+            ("itemSku", "A1")
+        );
+        assert_eq!(
+            (args[2].field.as_str(), args[2].value.as_str()),
+            ("itemSku", "B2")
+        );
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_exists("itemSku", subquery)
+            .set_database(Database::MySQL)
+            .build();
+
+        let expected = "SELECT * FROM orders WHERE status = ? \
+        AND EXISTS (SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku = ?) \
+        AND EXISTS (SELECT 1 FROM order_items oi WHERE oi.order_id = orders.id AND oi.sku != ?) \
+        AND price >= ?";
+
+        assert_eq!(sql, expected);
+    }
+
+    #[test]
+    fn test_map_exists_operators() {
+        let query = "filter[]=qty-range-2..5&filter[]=itemSku-contains-a_b";
+        let parsed = UrlQuery::new(query, ["qty", "itemSku"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .map_exists(
+                "qty",
+                "SELECT 1 FROM order_items oi WHERE oi.qty {op} {placeholder}",
+            )
+            .map_exists(
+                "itemSku",
+                "SELECT 1 FROM order_items oi WHERE oi.sku {op} {placeholder}",
+            )
+            .build();
+
+        let expected = "SELECT * FROM orders \
+        WHERE EXISTS (SELECT 1 FROM order_items oi WHERE oi.qty BETWEEN $1 AND $2) \
+        AND EXISTS (SELECT 1 FROM order_items oi WHERE oi.sku LIKE $3 ESCAPE '\\')";
+
+        assert_eq!(sql, expected);
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(values, vec!["2", "5", r"%a\_b%"]);
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_exists(
+                "itemSku",
+                "SELECT 1 FROM order_items oi WHERE oi.sku {op} {placeholder}",
+            )
+            .set_database(Database::MySQL)
+            .build();
+
+        assert!(sql.ends_with("oi.sku LIKE ? ESCAPE '\\\\')"));
+
+        // `has`, `overlaps` and `cont` don't fit the subquery.
+        let queries = [
+            ("tags-has-a", Operator::HAS),
+            ("tags-overlaps-a,b", Operator::OVERLAPS),
+            ("tags-cont-{}", Operator::CONT),
+        ];
+        for (query, operator) in queries {
+            let options = Options {
+                json_fields: vec!["tags".into()],
+                ..Default::default()
+            };
+            let query = format!("filter[]={}", query);
+            let parsed = UrlQuery::with_options(&query, ["tags"], options).unwrap();
+            let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .map_exists(
+                    "tags",
+                    "SELECT 1 FROM tags t WHERE t.name {op} {placeholder}",
+                )
+                .array_column("tags");
+
+            let unsupported = BuildError::UnsupportedOperator {
+                field: "tags".to_owned(),
+                operator,
+            };
+            assert_eq!(builder.clone().build_checked(), Err(unsupported.clone()));
+
+            let built = builder.build_parts();
+            assert_eq!(built.sql, "SELECT * FROM orders WHERE (1 = 0)");
+            assert_eq!(built.rejected, vec![unsupported]);
+        }
+    }
+
+    #[test]
+    fn test_relation() {
+        let query = "user.firstName=bob&filter[]=user.age-ge-18&sort=user.firstName-asc";
+
+        let parsed = UrlQuery::new(query, ["user.firstName", "user.age"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT orders.* FROM orders", parsed)
+            .relation("user", "users", "users.id = orders.user_id")
+            .convert_case(NameCase::Snake);
+        assert_eq!(builder.check_relations(), Ok(()));
+
+        let (sql, args) = builder.build();
+
+        let expected = "SELECT orders.* FROM orders JOIN users ON users.id = orders.user_id \
+        WHERE users.first_name = $1 AND users.age >= $2 ORDER BY users.first_name ASC";
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            (args[0].field.as_str(), args[0].value.as_str()),
+            ("user.firstName", "bob")
+        );
+
+        let parsed = UrlQuery::new("status=paid", ["status"]).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT orders.* FROM orders", parsed)
+            .relation("user", "users", "users.id = orders.user_id")
+            .build();
+
+        assert_eq!(sql, "SELECT orders.* FROM orders WHERE status = $1");
+    }
+
+    #[test]
+    fn test_check_relations() {
+        let parsed = UrlQuery::new("customer.name=bob", ["customer.name"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT orders.* FROM orders", parsed).relation(
+            "user",
+            "users",
+            "users.id = orders.user_id",
+        );
+
+        assert_eq!(
+            builder.check_relations(),
+            Err(ParseError::UnknownRelation("customer.name".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_delete() {
+        let query = "userId=1&status=paid&sort=createdAt-asc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["userId", "status", "createdAt"]).unwrap();
+
+        let (sql, args) = QueryBuilder::delete("orders", parsed.clone())
+            .convert_case(NameCase::Snake)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(sql, "DELETE FROM orders WHERE user_id = $1 AND status = $2");
+        assert_eq!(args.len(), 2);
+
+        let (sql, args) = QueryBuilder::delete("orders", parsed)
+            .convert_case(NameCase::Snake)
+            .set_database(Database::MySQL)
+            .bind_pagination(true)
+            .try_build()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "DELETE FROM orders WHERE user_id = ? AND status = ? ORDER BY created_at ASC LIMIT ?"
+        );
+        assert_eq!(
+            (args[2].field.as_str(), args[2].value.as_str()),
+            ("limit", "10")
+        );
+    }
+
+    #[test]
+    fn test_delete_unfiltered() {
+        let parsed = UrlQuery::new("", ["userId"]).unwrap();
+
+        let result = QueryBuilder::delete("orders", parsed.clone()).try_build();
+        assert_eq!(result, Err(SqlSafetyError::UnfilteredDelete));
+
+        let (sql, args) = QueryBuilder::delete("orders", parsed.clone()).build();
+        assert_eq!(sql, "DELETE FROM orders WHERE 1 = 0");
+        assert!(args.is_empty());
+
+        // SQL Server and Oracle have no boolean literals.
+        for database in [Database::Mssql, Database::Oracle] {
+            let (sql, _) = QueryBuilder::delete("orders", parsed.clone())
+                .set_database(database)
+                .build();
+            assert_eq!(sql, "DELETE FROM orders WHERE 1 = 0");
+        }
+
+        let (sql, _) = QueryBuilder::delete("orders", parsed.clone())
+            .allow_unfiltered()
+            .try_build()
+            .unwrap();
+        assert_eq!(sql, "DELETE FROM orders");
+
+        // A base filter scopes the delete to the tenant, but would still delete all its rows.
+        let builder = QueryBuilder::delete("orders", parsed)
+            .base_filter("tenant_id", Operator::EQ, "7")
+            .exclude_soft_deleted("deleted_at");
+        assert_eq!(
+            builder.clone().try_build(),
+            Err(SqlSafetyError::UnfilteredDelete)
+        );
+        let (sql, _) = builder.clone().build();
+        assert_eq!(
+            sql,
+            "DELETE FROM orders WHERE tenant_id = $1 AND deleted_at IS NULL AND 1 = 0"
+        );
+
+        let (sql, _) = builder.allow_unfiltered().try_build().unwrap();
+        assert_eq!(
+            sql,
+            "DELETE FROM orders WHERE tenant_id = $1 AND deleted_at IS NULL"
+        );
+    }
+
+    #[test]
+    fn test_update() {
+        let query = "userId=1&filter[]=status-ne-shipped";
+
+        let parsed = UrlQuery::new(query, ["userId", "status"]).unwrap();
+
+        let (sql, args) = QueryBuilder::update("orders", vec!["status", "note"], parsed.clone())
+            .convert_case(NameCase::Snake)
+            .with_cte("recent", "SELECT id FROM orders WHERE created_at > $1")
+            .try_build()
+            .unwrap();
+
+        assert_eq!(
+            sql,
+            "WITH recent AS (SELECT id FROM orders WHERE created_at > $1) \
+            UPDATE orders SET status = $2, note = $3 WHERE user_id = $4 AND status != $5"
+        );
+        assert_eq!(
+            (args[0].field.as_str(), args[0].value.as_str()),
+            ("userId", "1")
+        );
+        assert_eq!(args.len(), 2);
+
+        let (sql, _) = QueryBuilder::update("orders", vec!["status"], parsed)
+            .set_database(Database::MySQL)
+            .quote_identifiers(true)
+            .build();
+
+        assert_eq!(
+            sql,
+            "UPDATE orders SET `status` = ? WHERE `userId` = ? AND `status` != ?"
+        );
+
+        let parsed = UrlQuery::new("", ["userId"]).unwrap();
+        let result = QueryBuilder::update("orders", vec!["status"], parsed.clone()).try_build();
+        assert_eq!(result, Err(SqlSafetyError::UnfilteredUpdate));
+
+        // Base filters and soft deletes don't pick the rows of a bulk update.
+        let builder = QueryBuilder::update("orders", vec!["status"], parsed)
+            .base_filter("tenant_id", Operator::EQ, "7")
+            .exclude_soft_deleted("deleted_at");
+        assert_eq!(
+            builder.clone().try_build(),
+            Err(SqlSafetyError::UnfilteredUpdate)
+        );
+        let (sql, _) = builder.clone().build();
+        assert_eq!(
+            sql,
+            "UPDATE orders SET status = $1 WHERE tenant_id = $2 AND deleted_at IS NULL AND 1 = 0"
+        );
+        assert!(builder.allow_unfiltered().try_build().is_ok());
+    }
+
+    #[test]
+    fn test_lock() {
+        let query = "status=pending&sort=createdAt-asc&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["status", "createdAt"]).unwrap();
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM jobs", parsed.clone())
+            .lock(LockMode::ForUpdate { skip_locked: true })
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM jobs WHERE status = $1 ORDER BY createdAt ASC LIMIT 10 OFFSET 0 \
+            FOR UPDATE SKIP LOCKED"
+        );
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM jobs", parsed.clone())
+            .lock(LockMode::ForShare { skip_locked: false })
+            .set_database(Database::MySQL)
+            .build();
+
+        assert!(sql.ends_with(" LIMIT 10 OFFSET 0 FOR SHARE"), "{}", sql);
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM jobs", parsed)
+            .lock(LockMode::ForUpdate { skip_locked: true })
+            .set_database(Database::MySQL)
+            .legacy_mysql()
+            .build();
+
+        assert!(sql.ends_with(" LIMIT 10 OFFSET 0 FOR UPDATE"), "{}", sql);
+    }
+
+    #[test]
+    fn test_skip_pagination() {
+        let allowed = ["status", "createdAt"];
+        let with = UrlQuery::new(
+            "status=paid&sort=createdAt-desc&limit=25&offset=50",
+            allowed,
+        );
+        let without = UrlQuery::new("status=paid&sort=createdAt-desc", allowed);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", with.unwrap())
+            .bind_pagination(true)
+            .skip_pagination()
+            .build();
+        let (expected, _) =
+            QueryBuilder::from_str("SELECT * FROM orders", without.unwrap()).build();
+
+        assert_eq!(sql, expected);
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE status = $1 ORDER BY createdAt DESC"
+        );
+        assert_eq!(args.len(), 1);
+    }
+
+    #[test]
+    fn test_max_limit() {
+        let cases = [
+            ("limit=5000&offset=10", Some(100), " LIMIT 100 OFFSET 10"),
+            ("", Some(100), " LIMIT 100"),
+            ("limit=25", Some(25), " LIMIT 25"),
+        ];
+
+        for (query, applied, expected) in cases {
+            let parsed = UrlQuery::new(query, ["status"]).unwrap();
+
+            let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed).max_limit(100);
+            assert_eq!(builder.applied_limit(), applied, "{}", query);
+
+            let (sql, _) = builder.build();
+            assert_eq!(
+                sql,
+                format!("SELECT * FROM orders{}", expected),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_default_limit() {
+        let cases = [
+            ("", " LIMIT 25"),
+            ("limit=50", " LIMIT 50"),
+            ("offset=50", " LIMIT 25 OFFSET 50"),
+            ("limit=200", " LIMIT 100"),
+        ];
+
+        for (query, expected) in cases {
+            let parsed = UrlQuery::new(query, ["status"]).unwrap();
+
+            let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+                .default_limit(25)
+                .max_limit(100)
+                .build();
+            assert_eq!(
+                sql,
+                format!("SELECT * FROM orders{}", expected),
+                "{}",
+                query
+            );
+        }
+
+        let parsed = UrlQuery::new("offset=50", ["status"]).unwrap();
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .default_limit(25)
+            .skip_pagination()
+            .build();
+        assert_eq!(sql, "SELECT * FROM orders");
+    }
+
+    #[test]
+    fn test_build_checked() {
+        let query = "userId=1&group=status&having=status-ne-pending&sort=price-desc&offset=10";
+        let allowed = ["userId", "status", "price"];
+
+        let builder = || {
+            let parsed = UrlQuery::new(query, allowed).unwrap();
+            QueryBuilder::from_str("SELECT status, MAX(price) FROM orders", parsed)
+                .map_columns(HashMap::from([("userId", "orders")]))
+                .pass_through(["status"])
+        };
+
+        assert_eq!(
+            builder().build_checked(),
+            Err(BuildError::UnmappedField {
+                field: "price".to_owned(),
+                clause: "ORDER BY"
+            })
+        );
+
+        let builder = || builder().map_expressions(HashMap::from([("price", "MAX(price)")]));
+        assert_eq!(
+            builder().build_checked(),
+            Err(BuildError::OffsetWithoutLimit)
+        );
+
+        let result = builder()
+            .default_limit(25)
+            .and_where("total > {}", vec![])
+            .build_checked();
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "condition total > {} has 1 placeholders but 0 values"
+        );
+
+        let (sql, args) = builder().default_limit(25).build_checked().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT status, MAX(price) FROM orders WHERE orders.userId = $1 GROUP BY status \
+            HAVING status != $2 ORDER BY MAX(price) DESC LIMIT 25 OFFSET 10"
+        );
+        assert_eq!(args.len(), 2);
+
+        let mut parsed = UrlQuery::new(query, allowed).unwrap();
+        parsed.groups_mut().clear();
+        let result = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .pass_through(allowed)
+            .skip_pagination()
+            .build_checked();
+        assert_eq!(
+            result,
+            Err(BuildError::HavingWithoutGroup("status".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_build_parts() {
+        let parsed = UrlQuery::new("sort=id-desc", ["id"]).unwrap();
+
+        let built = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .shift_bind(2)
+            .build_parts();
+
+        assert_eq!(built.sql, "SELECT * FROM orders ORDER BY id DESC");
+        assert_eq!(built.next_bind_index, 3);
+        assert_eq!((built.limit, built.offset), (None, None));
+        assert!(!built.has_where);
+
+        let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+        let built = QueryBuilder::update("orders", vec!["status", "note"], parsed)
+            .set_database(Database::MySQL)
+            .build_parts();
+
+        assert_eq!(
+            built.sql,
+            "UPDATE orders SET status = ?, note = ? WHERE userId = ? LIMIT 10"
+        );
+        assert_eq!(built.next_bind_index, 4);
+        assert_eq!(built.limit, Some(10));
+        assert!(built.has_where);
+    }
+
+    #[test]
+    fn test_clone() {
+        let query = "userId=1&sort=createdAt-desc&limit=10";
+
+        let parsed = UrlQuery::new(query, ["userId", "createdAt"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .timeout_policy(|_| Some(Duration::from_secs(1)));
+
+        let (sql, args) = builder.clone().build();
+        assert_eq!(builder.clone().build(), (sql.clone(), args.clone()));
+
+        let (count_sql, count_args) = builder.clone().build_count();
+        assert_eq!(count_sql, "SELECT COUNT(*) FROM orders WHERE user_id = $1");
+        assert_eq!(count_args, args);
+
+        let (mysql, _) = builder.set_database(Database::MySQL).build();
+        assert_eq!(
+            mysql,
+            "SELECT /*+ MAX_EXECUTION_TIME(1000) */ * FROM orders WHERE user_id = ? \
+            ORDER BY created_at DESC LIMIT 10"
+        );
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE user_id = $1 ORDER BY created_at DESC LIMIT 10"
+        );
+    }
+
+    #[test]
+    fn test_debug_sql() {
+        let query = "name=O'Brien&filter[]=note-eq-%25off%0Anow&filter[]=path-eq-C:\\";
+
+        let parsed = UrlQuery::new(query, ["name", "note", "path"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM users", parsed.clone())
+            .and_where("name != '$9?'", vec![]);
+
+        assert_eq!(
+            builder.debug_sql(),
+            "SELECT * FROM users WHERE name = 'O''Brien' AND note = '%off\nnow' \
+            AND path = 'C:\\' AND (name != '$9?')"
+        );
+        assert_eq!(
+            builder.set_database(Database::MySQL).debug_sql(),
+            "SELECT * FROM users WHERE name = 'O''Brien' AND note = '%off\nnow' \
+            AND path = 'C:\\\\' AND (name != '$9?')"
+        );
+
+        let sql = "SELECT * FROM users JOIN teams ON teams.id = $1";
+        let builder = QueryBuilder::from_str(sql, parsed).shift_bind(1);
+        assert!(builder.debug_sql().starts_with(
+            "SELECT * FROM users JOIN teams ON teams.id = $1 WHERE name = 'O''Brien'"
+        ));
+    }
+
+    #[test]
+    fn test_query_builder_set_database_sqlite() {
+        let query =
+            "userId=123&filter[]=orderId-ne-1&filter[]=price-gt-200&filter[]=price-le-500&filter[]=total-ge-1&filter[]=total-lt-9&sort=price-desc-nullslast&limit=10&offset=0";
+
+        let parsed = UrlQuery::new(query, ["userId", "orderId", "price", "total"]).unwrap();
+
+        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
+            .convert_case(NameCase::Snake)
+            .set_database(Database::Sqlite)
+            .quote_identifiers(true)
+            .lock(LockMode::ForUpdate { skip_locked: true })
+            .build();
+
+        let expected = "SELECT \"id\", \"status\" FROM \"orders\" \
+        WHERE \"user_id\" = ? AND \"order_id\" != ? AND \"price\" > ? AND \"price\" <= ? \
+        AND \"total\" >= ? AND \"total\" < ? \
+        ORDER BY \"price\" DESC NULLS LAST \
+        LIMIT 10 \
+        OFFSET 0";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 6);
+    }
+
+    #[test]
+    fn test_sqlite_offset_without_limit() {
+        let parsed = UrlQuery::new("userId=1&offset=20", ["userId"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::Sqlite)
+            .pass_through(["userId"]);
+
+        let (sql, _) = builder.clone().build_checked().unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE userId = ? LIMIT -1 OFFSET 20"
+        );
+
+        let built = builder.clone().bind_pagination(true).build_parts();
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM orders WHERE userId = ? LIMIT -1 OFFSET ?"
+        );
+        assert_eq!(
+            (built.args[1].field.as_str(), built.args[1].value.as_str()),
+            ("offset", "20")
+        );
+        assert_eq!((built.limit, built.offset), (None, Some(20)));
+
+        let (sql, _) = builder.set_database(Database::Postgres).build();
+        assert_eq!(sql, "SELECT * FROM orders WHERE userId = $1");
+    }
+
+    #[test]
+    fn test_query_builder_set_database_mssql() {
+        let query =
+            "userId=123&userName=bob&filter[]=orderId-ne-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+
+        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed.clone())
+            .convert_case(NameCase::Snake)
+            .set_database(Database::Mssql)
+            .quote_identifiers(true)
+            .build();
+
+        let expected = "SELECT [id], [status] FROM [orders] \
+        WHERE [user_id] = @p1 AND [user_name] = @p2 \
+        AND [order_id] != @p3 AND [price] >= @p4 \
+        ORDER BY [price] DESC \
+        OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 4);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::Mssql)
+            .bind_pagination(true)
+            .build();
+
+        assert!(
+            sql.ends_with("ORDER BY price DESC OFFSET @p5 ROWS FETCH NEXT @p6 ROWS ONLY"),
+            "{}",
+            sql
+        );
+        assert_eq!(
+            (args[4].field.as_str(), args[4].value.as_str()),
+            ("offset", "20")
+        );
+        assert_eq!(
+            (args[5].field.as_str(), args[5].value.as_str()),
+            ("limit", "10")
+        );
+    }
+
+    #[test]
+    fn test_mssql_pagination_without_sort() {
+        let parsed = UrlQuery::new("userId=1&limit=10", ["userId"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .set_database(Database::Mssql)
+            .pass_through(["userId"]);
+
+        assert_eq!(
+            builder.debug_sql(),
+            "SELECT * FROM orders WHERE userId = N'1' \
+            ORDER BY (SELECT NULL) OFFSET 0 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+        assert_eq!(
+            builder.clone().build_checked(),
+            Err(BuildError::PaginationWithoutSort)
+        );
+
+        let (sql, _) = builder
+            .default_sort("id", SortBy::ASC)
+            .build_checked()
+            .unwrap();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE userId = @p1 \
+            ORDER BY id ASC OFFSET 0 ROWS FETCH NEXT 10 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn test_query_builder_set_database_oracle() {
+        let query = "userId=123&filter[]=price-ge-200&group=status&having=status-ne-pending\
+        &sort=status-desc-nullslast&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["userId", "price", "status"]).unwrap();
+
+        let builder = QueryBuilder::new("orders", vec!["status", "COUNT(*)"], parsed)
+            .convert_case(NameCase::Snake)
+            .set_database(Database::Oracle)
+            .quote_identifiers(true)
+            .shift_bind(1);
+
+        let (sql, args) = builder.clone().bind_pagination(true).build();
+
+        let expected = "SELECT \"status\", COUNT(*) FROM \"orders\" \
+        WHERE \"user_id\" = :2 AND \"price\" >= :3 \
+        GROUP BY \"status\" HAVING \"status\" != :4 \
+        ORDER BY \"status\" DESC NULLS LAST \
+        OFFSET :5 ROWS FETCH NEXT :6 ROWS ONLY";
+
+        assert_eq!(sql, expected);
+        assert_eq!(args.len(), 5);
+
+        let (sql, _) = builder.clone().build_count();
+        assert!(sql.ends_with(") count_query"), "{}", sql);
+
+        let (sql, _) = builder
+            .lock(LockMode::ForUpdate { skip_locked: true })
+            .build();
+        assert!(
+            sql.ends_with("OFFSET 20 ROWS FETCH NEXT 10 ROWS ONLY FOR UPDATE SKIP LOCKED"),
+            "{}",
+            sql
+        );
     }
 
     #[test]
-    fn test_query_builder_new_map_columns() {
-        let query = "id=1&group=id&sort=createdAt-desc";
+    fn test_placeholder_style_named() {
+        let query = "userId=1&filter[]=price-ge-10&filter[]=price-le-20&limit=10";
 
-        let parsed = UrlQuery::new(query, ["id", "createdAt"]).unwrap();
+        let parsed = UrlQuery::new(query, ["userId", "price"]).unwrap();
 
-        let (sql, args) = QueryBuilder::from_str(
-            "SELECT orders.id, user_id, status, address_id, orders.created_at FROM orders",
-            parsed,
-        )
-        .append("JOIN order_items ON orders.id = order_items.order_id")
-        .append("JOIN inventory ON order_items.inventory_id = inventory.id")
-        .map_columns(HashMap::from([("id", "orders"), ("createdAt", "orders")]))
-        .convert_case(Case::Snake)
-        .build();
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .convert_case(NameCase::Snake)
+            .placeholder_style(PlaceholderStyle::Named)
+            .bind_pagination(true)
+            .shift_bind(3)
+            .build();
 
-        let expected =
-            "SELECT orders.id, user_id, status, address_id, orders.created_at FROM orders \
-             JOIN order_items ON orders.id = order_items.order_id \
-             JOIN inventory ON order_items.inventory_id = inventory.id \
-             WHERE orders.id = $1 GROUP BY orders.id ORDER BY orders.created_at DESC";
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE user_id = :user_id \
+            AND price >= :price_1 AND price <= :price_2 LIMIT :limit"
+        );
+        let names: Vec<&str> = args.iter().map(|arg| arg.field.as_str()).collect();
+        assert_eq!(names, ["user_id", "price_1", "price_2", "limit"]);
+
+        let (sql, args) = QueryBuilder::update("orders", vec!["price"], parsed)
+            .set_database(Database::MySQL)
+            .placeholder_style(PlaceholderStyle::NamedAt)
+            .build();
+
+        assert_eq!(
+            sql,
+            "UPDATE orders SET price = @price_1 WHERE userId = @user_id \
+            AND price >= @price_2 AND price <= @price_3 LIMIT 10"
+        );
+        assert_eq!(args[1].field, "price_2");
+    }
+
+    #[test]
+    fn test_dedupe_binds() {
+        let query = "filter[]=status-eq-active&filter[]=visibility-eq-active&code=7&limit=42";
+
+        let parsed = UrlQuery::new(query, ["status", "visibility", "code"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([
+                ("status", FieldType::Text),
+                ("visibility", FieldType::Text),
+            ]))
+            .base_filter("tenant_id", Operator::EQ, "42")
+            .and_where(
+                "owner_id = {} OR tenant_id = {}",
+                vec![("owner", "7"), ("tenant_id", "42")],
+            )
+            .bind_pagination(true)
+            .dedupe_binds();
+
+        let (sql, args) = builder.clone().build();
+
+        let expected = "SELECT * FROM orders WHERE tenant_id = $1 \
+        AND status = $2 AND visibility = $2 AND code = $3 \
+        AND (owner_id = $4 OR tenant_id = $1) LIMIT $5";
 
         assert_eq!(sql, expected);
-        assert_eq!(args.len(), 1);
+        let names: Vec<&str> = args.iter().map(|arg| arg.field.as_str()).collect();
+        assert_eq!(names, ["tenant_id", "status", "code", "owner", "limit"]);
+
+        let paginated = builder.clone().build_paginated();
+        assert!(paginated.count_sql.ends_with("tenant_id = $1)"));
+        assert_eq!(paginated.count_args().len(), 4);
+
+        let (sql, args) = builder.set_database(Database::MySQL).build();
+
+        assert!(sql.contains("status = ? AND visibility = ?"), "{}", sql);
+        assert_eq!(args.len(), 7);
     }
 
     #[test]
-    fn test_append_where() {
-        let query = "filter[]=userId-eq-1&filter[]=id-eq-2";
+    fn test_build_typed() {
+        let query = "filter[]=price-ge-1.5&active=true&limit=10";
 
-        let parsed = UrlQuery::new(query, ["userId", "id"]).unwrap();
+        let parsed = UrlQuery::new(query, ["price", "active"]).unwrap();
 
-        let mut builder = QueryBuilder::from_str("", parsed);
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([
+                ("price", FieldType::Float),
+                ("active", FieldType::Bool),
+            ]))
+            .bind_pagination(true)
+            .placeholder_style(PlaceholderStyle::Named);
 
-        let mut args = builder.append_where().into_iter();
+        let (sql, args) = builder.clone().build_typed().unwrap();
 
-        let user_id = args.next().unwrap().1;
-        assert_eq!(user_id, "1");
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE price >= :price AND active = :active LIMIT :limit"
+        );
+        assert_eq!(
+            args,
+            vec![
+                ("price".to_owned(), ArgValue::Float(1.5)),
+                ("active".to_owned(), ArgValue::Bool(true)),
+                ("limit".to_owned(), ArgValue::Int(10)),
+            ]
+        );
 
-        let id = args.next().unwrap().1;
-        assert_eq!(id, "2");
+        let result = builder
+            .field_types(HashMap::from([("price", FieldType::Int)]))
+            .build_typed();
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidValue {
+                field: "price".to_owned(),
+                value: "1.5".to_owned(),
+                raw: None,
+            })
+        );
     }
 
     #[test]
-    fn test_shift_bind() {
-        let query = "filter[]=userId-eq-1&filter[]=id-eq-2";
+    fn test_refused_value_matches_nothing() {
+        let parsed = UrlQuery::new("status=bogus", ["status"]).unwrap();
+        let built = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .validate_value("status", |v| v == "paid")
+            .build_parts();
 
-        let parsed = UrlQuery::new(query, ["userId", "id"]).unwrap();
+        assert_eq!(built.sql, "SELECT * FROM orders WHERE (1 = 0)");
+        assert!(built.args.is_empty());
+        assert_eq!(built.rejected.len(), 1);
 
-        let (sql, args) = QueryBuilder::from_str(
-            "SELECT id, (SELECT postcode FROM address WHERE id = $1) FROM orders",
-            parsed,
-        )
-        .shift_bind(1)
-        .convert_case(Case::Snake)
-        .build();
+        let parsed = UrlQuery::new("deletedAt=abc", ["deletedAt"]).unwrap();
+        let built = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("deletedAt", FieldType::DateTime)]))
+            .exclude_soft_deleted("deletedAt")
+            .build_parts();
 
-        let expected = "SELECT id, (SELECT postcode FROM address WHERE id = $1) FROM orders WHERE user_id = $2 AND id = $3";
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM orders WHERE deletedAt IS NULL AND (1 = 0)"
+        );
+        assert_eq!(built.rejected.len(), 1);
+    }
 
-        assert_eq!(sql, expected);
-        assert_eq!(args.len(), 2);
+    #[test]
+    fn test_transform_value() {
+        let query = "email=Bob@Example.com&filter[]=status-ne-archived&country=UK";
+
+        let parsed = UrlQuery::new(query, ["email", "status", "country"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM users", parsed)
+            .pass_through(["email", "status", "country"])
+            .transform_value("email", |v| Ok(v.trim().to_lowercase()))
+            .transform_value("country", |v| match v {
+                "UK" => Ok("GB".to_owned()),
+                _ => Ok(v.to_owned()),
+            })
+            .validate_value("status", |v| ["active", "disabled"].contains(&v));
+
+        let built = builder.clone().build_parts();
+
+        let invalid = BuildError::InvalidValue {
+            field: "status".to_owned(),
+            value: "archived".to_owned(),
+            reason: "not allowed".to_owned(),
+        };
+
+        // The refused value matches nothing instead of being bound.
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM users WHERE email = $1 AND (1 = 0) AND country = $2"
+        );
+        let values: Vec<&str> = built.args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(values, ["bob@example.com", "GB"]);
+        assert_eq!(built.rejected, vec![invalid.clone()]);
+
+        assert_eq!(builder.build_checked(), Err(invalid));
+
+        // Leaving the filter out would widen a mutation, so it is refused instead.
+        let parsed = UrlQuery::new("filter[]=status-eq-archived", ["status"]).unwrap();
+        let result = QueryBuilder::delete("users", parsed)
+            .pass_through(["status"])
+            .validate_value("status", |v| ["active", "disabled"].contains(&v))
+            .allow_unfiltered()
+            .try_build();
+        assert_eq!(result, Err(SqlSafetyError::UnfilteredDelete));
     }
 
     #[test]
-    fn test_query_builder_set_database_mysql() {
-        let query =
-            "userId=123&userName=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&sort=price-desc&limit=10&offset=0";
+    fn test_substitute_value() {
+        let query = "userId=me&filter[]=assignee-ne-me&filter[]=reviewer-eq-meg";
 
-        let parsed = UrlQuery::new(query, ["userId", "userName", "orderId", "price"]).unwrap();
+        let parsed = UrlQuery::new(query, ["userId", "assignee", "reviewer"]).unwrap();
 
-        let (sql, args) = QueryBuilder::new("orders", vec!["id", "status"], parsed)
-            .convert_case(Case::Snake)
-            .set_database(Database::MySQL)
+        let current_user_id = 42;
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM tickets", parsed)
+            .substitute_value("userId", "me", current_user_id.to_string())
+            .substitute_value("assignee", "me", current_user_id.to_string())
+            .substitute_value("reviewer", "me", current_user_id.to_string())
             .build();
 
-        let expected = "SELECT id, status FROM orders \
-        WHERE user_id = ? AND user_name = ? \
-        AND order_id = ? AND price >= ? \
-        ORDER BY price DESC \
-        LIMIT 10 \
-        OFFSET 0";
+        assert_eq!(
+            sql,
+            "SELECT * FROM tickets WHERE userId = $1 AND assignee != $2 AND reviewer = $3"
+        );
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(values, ["42", "42", "meg"]);
+    }
 
-        assert_eq!(sql, expected);
-        assert_eq!(args.len(), 4);
+    #[test]
+    fn test_normalize_bools() {
+        let query = "isActive=1&filter[]=isAdmin-ne-YES&archived=no";
+
+        let parsed = UrlQuery::new(query, ["isActive", "isAdmin", "archived"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM users", parsed)
+            .pass_through(["isActive", "isAdmin", "archived"])
+            .field_types(HashMap::from([
+                ("isActive", FieldType::Bool),
+                ("isAdmin", FieldType::Bool),
+            ]));
+
+        let (_, args) = builder.clone().build_checked().unwrap();
+
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(values, ["true", "true", "no"]);
+
+        let parsed = UrlQuery::new("isActive=maybe", ["isActive"]).unwrap();
+
+        let result = QueryBuilder::from_str("SELECT * FROM users", parsed)
+            .pass_through(["isActive"])
+            .field_types(HashMap::from([("isActive", FieldType::Bool)]))
+            .build_checked();
+        assert_eq!(
+            result,
+            Err(BuildError::Parse(ParseError::InvalidValue {
+                field: "isActive".to_owned(),
+                value: "maybe".to_owned(),
+                raw: Some("isActive=maybe".to_owned()),
+            }))
+        );
+
+        // `build` matches nothing instead of binding the value as is.
+        let parsed = UrlQuery::new("isPaid=maybe&userId=1", ["isPaid", "userId"]).unwrap();
+
+        let built = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("isPaid", FieldType::Bool)]))
+            .build_parts();
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM orders WHERE (1 = 0) AND userId = $1"
+        );
+        assert!(built.args.iter().all(|arg| arg.value != "maybe"));
+        assert_eq!(
+            built.rejected,
+            vec![BuildError::Parse(ParseError::InvalidValue {
+                field: "isPaid".to_owned(),
+                value: "maybe".to_owned(),
+                raw: Some("isPaid=maybe".to_owned()),
+            })]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_expand_dates() {
+        let query = "createdAt=2024-02-29&filter[]=updatedAt-ge-2024-05-01T10:00:00+02:00";
+
+        let parsed = UrlQuery::new(query, ["createdAt", "updatedAt"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .field_types(HashMap::from([
+                ("createdAt", FieldType::DateTime),
+                ("updatedAt", FieldType::DateTime),
+            ]));
+
+        let (sql, args) = builder.clone().expand_dates().build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE (created_at >= $1 AND created_at < $2) \
+            AND updated_at >= $3"
+        );
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(
+            values,
+            [
+                "2024-02-29T00:00:00Z",
+                "2024-03-01T00:00:00Z",
+                "2024-05-01T08:00:00Z"
+            ]
+        );
+
+        let (sql, args) = builder.build();
+
+        assert!(sql.contains("created_at = $1"), "{}", sql);
+        assert_eq!(args[0].value, "2024-02-29T00:00:00Z");
+
+        // The value goes through the transforms and validations of the field first.
+        let parsed = UrlQuery::new("createdAt=leapDay", ["createdAt"]).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("createdAt", FieldType::DateTime)]))
+            .substitute_value("createdAt", "leapDay", "2024-02-29")
+            .pass_through(["createdAt"])
+            .expand_dates();
+
+        let (sql, args) = builder.clone().build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE (createdAt >= $1 AND createdAt < $2)"
+        );
+        assert_eq!(args[0].value, "2024-02-29T00:00:00Z");
+
+        let builder = builder.validate_value("createdAt", |v| v != "2024-02-29");
+        let built = builder.clone().build_parts();
+        let invalid = BuildError::InvalidValue {
+            field: "createdAt".to_owned(),
+            value: "leapDay".to_owned(),
+            reason: "not allowed".to_owned(),
+        };
+        assert_eq!(built.sql, "SELECT * FROM orders WHERE (1 = 0)");
+        assert_eq!(built.rejected, vec![invalid.clone()]);
+        assert_eq!(builder.build_checked(), Err(invalid));
+    }
+
+    #[test]
+    #[cfg(feature = "uuid")]
+    fn test_uuid_values() {
+        let id = "8BD8A6FB-E2B2-47AB-B3DB-4F47C067BA5E";
+        let token = Cursor::encode(["{8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e}", "42"]);
+        let query = format!("userId={}&sort=orderId-asc&cursor={}", id, token);
+
+        let parsed = UrlQuery::new(&query, ["userId", "orderId"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([
+                ("userId", FieldType::Uuid),
+                ("orderId", FieldType::Uuid),
+            ]))
+            .pass_through(["userId", "orderId"])
+            .seek_on_cursor("id");
+
+        let (sql, args) = builder.build_checked().unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE userId = $1 AND (orderId, id) > ($2, $3) \
+            ORDER BY orderId ASC, id ASC"
+        );
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(
+            values,
+            [
+                "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e",
+                "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e",
+                "42"
+            ]
+        );
+
+        let token = Cursor::encode(["abc", "42"]);
+        let query = format!("sort=orderId-asc&cursor={}", token);
+        let parsed = UrlQuery::new(&query, ["orderId"]).unwrap();
+
+        let result = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("orderId", FieldType::Uuid)]))
+            .pass_through(["orderId"])
+            .seek_on_cursor("id")
+            .build_checked();
+
+        assert_eq!(
+            result,
+            Err(BuildError::Parse(ParseError::InvalidValue {
+                field: "orderId".to_owned(),
+                value: "abc".to_owned(),
+                raw: None,
+            }))
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_relative_dates() {
+        let query = "filter[]=createdAt-ge-last7days&filter[]=createdAt-lt-today\
+        &filter[]=dueOn-ge-startOfMonth&status=today";
+
+        let parsed = UrlQuery::new(query, ["createdAt", "dueOn", "status"]).unwrap();
+
+        let now = chrono::DateTime::parse_from_rfc3339("2024-05-15T10:00:00Z").unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([
+                ("createdAt", FieldType::DateTime),
+                ("dueOn", FieldType::Date),
+            ]))
+            .relative_dates(chrono::FixedOffset::east_opt(2 * 3600).unwrap())
+            .now(now.to_utc());
+
+        let (_, args) = builder.build();
+
+        let values: Vec<&str> = args.iter().map(|arg| arg.value.as_str()).collect();
+        assert_eq!(
+            values,
+            [
+                "2024-05-07T22:00:00Z",
+                "2024-05-14T22:00:00Z",
+                "2024-05-01",
+                "today"
+            ]
+        );
+
+        let parsed = UrlQuery::new("filter[]=createdAt-ge-tomorrow", ["createdAt"]).unwrap();
+
+        let result = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("createdAt", FieldType::DateTime)]))
+            .pass_through(["createdAt"])
+            .relative_dates(chrono::FixedOffset::east_opt(0).unwrap())
+            .now(now.to_utc())
+            .build_checked();
+
+        assert_eq!(
+            result,
+            Err(BuildError::Parse(ParseError::InvalidFormat {
+                field: "createdAt".to_owned(),
+                value: "tomorrow".to_owned(),
+                format: "RFC 3339",
+                raw: Some("filter[]=createdAt-ge-tomorrow".to_owned()),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_range_filter() {
+        let query = "filter[]=price-range-100..500&filter[]=createdAt-range-2024-01-01..\
+        &filter[]=status-eq-paid";
+
+        let parsed = UrlQuery::new(query, ["price", "createdAt", "status"]).unwrap();
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .filter_combinator(Combinator::Or)
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE ((price >= $1 AND price <= $2) \
+            OR created_at >= $3 OR status = $4)"
+        );
+        assert_eq!(args[1], Arg::new("price", "500", Some(Operator::LE), 2));
+        assert_eq!(
+            into_tuples(args),
+            [
+                ("price".to_owned(), "100".to_owned()),
+                ("price".to_owned(), "500".to_owned()),
+                ("createdAt".to_owned(), "2024-01-01".to_owned()),
+                ("status".to_owned(), "paid".to_owned()),
+            ]
+        );
+
+        let parsed = UrlQuery::new("filter[]=dueOn-range-..2024-13-01", ["dueOn"]).unwrap();
+
+        let result = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .field_types(HashMap::from([("dueOn", FieldType::Date)]))
+            .pass_through(["dueOn"])
+            .build_checked();
+
+        assert_eq!(
+            result,
+            Err(BuildError::Parse(ParseError::InvalidFormat {
+                field: "dueOn".to_owned(),
+                value: "2024-13-01".to_owned(),
+                format: "YYYY-MM-DD",
+                raw: Some("filter[]=dueOn-range-..2024-13-01".to_owned()),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_json_column() {
+        let options = Options {
+            json_fields: vec!["metadata".into()],
+            ..Default::default()
+        };
+        let query = "filter[]=metadata.color-eq-red&filter[]=metadata.size.width-ge-10\
+        &filter[]=metadata.releasedOn-lt-2024-01-01&filter[]=metadata.tags.0-ge-b\
+        &sort=metadata.rank-desc";
+
+        let cases = [
+            (
+                Database::Postgres,
+                "SELECT * FROM products WHERE attrs.metadata->>'color' = $1 \
+                AND (attrs.metadata->'size'->>'width')::numeric >= $2 \
+                AND attrs.metadata->>'releasedOn' < $3 \
+                AND attrs.metadata->'tags'->>0 >= $4 \
+                ORDER BY attrs.metadata->>'rank' DESC",
+            ),
+            (
+                Database::MySQL,
+                "SELECT * FROM products WHERE attrs.metadata->>'$.color' = ? \
+                AND CAST(attrs.metadata->>'$.size.width' AS DECIMAL(65, 30)) >= ? \
+                AND attrs.metadata->>'$.releasedOn' < ? \
+                AND attrs.metadata->>'$.tags[0]' >= ? \
+                ORDER BY attrs.metadata->>'$.rank' DESC",
+            ),
+        ];
+
+        for (database, expected) in cases {
+            let parsed = UrlQuery::with_options(query, ["id"], options.clone()).unwrap();
+
+            let builder = QueryBuilder::from_str("SELECT * FROM products", parsed)
+                .set_database(database)
+                .convert_case(NameCase::Snake)
+                .map_columns(HashMap::from([("metadata", "attrs")]))
+                .field_types(HashMap::from([
+                    ("metadata.size.width", FieldType::Int),
+                    ("metadata.releasedOn", FieldType::Date),
+                ]))
+                .json_column("metadata");
+
+            assert_eq!(builder.check_relations(), Ok(()));
+            assert_eq!(builder.check_case_collisions(), Ok(()));
+
+            let (sql, args) = builder.build_checked().unwrap();
+
+            assert_eq!(sql, expected, "{:?}", database);
+            assert_eq!(args[0].field, "metadata.color");
+        }
+    }
+
+    #[test]
+    fn test_json_contains() {
+        let options = Options {
+            json_fields: vec!["attributes".into()],
+            ..Default::default()
+        };
+        let query = "filter[]=attributes-cont-%7B%22size%22%3A%7B%22eu%22%3A44%7D%7D&name=shoe";
+
+        let cases = [
+            (
+                Database::Postgres,
+                "SELECT * FROM products WHERE attributes @> $1::jsonb AND name = $2",
+            ),
+            (
+                Database::MySQL,
+                "SELECT * FROM products WHERE JSON_CONTAINS(attributes, ?) AND name = ?",
+            ),
+        ];
+
+        for (database, expected) in cases {
+            let parsed = UrlQuery::with_options(query, ["attributes", "name"], options.clone());
+
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM products", parsed.unwrap())
+                .set_database(database)
+                .json_column("attributes")
+                .build();
+
+            assert_eq!(sql, expected, "{:?}", database);
+            assert_eq!(
+                args[0],
+                Arg::new(
+                    "attributes",
+                    r#"{"size":{"eu":44}}"#,
+                    Some(Operator::CONT),
+                    1
+                )
+            );
+        }
+
+        // The other databases have no containment operator.
+        for database in [Database::Sqlite, Database::Mssql, Database::Oracle] {
+            let parsed = UrlQuery::with_options(query, ["attributes", "name"], options.clone());
+            let builder = QueryBuilder::from_str("SELECT * FROM products", parsed.unwrap())
+                .set_database(database)
+                .json_column("attributes")
+                .pass_through(["attributes", "name"]);
+
+            let unsupported = BuildError::UnsupportedOperator {
+                field: "attributes".to_owned(),
+                operator: Operator::CONT,
+            };
+            assert_eq!(builder.clone().build_checked(), Err(unsupported.clone()));
+
+            let built = builder.build_parts();
+            assert!(!built.sql.contains("attributes"), "{}", built.sql);
+            assert_eq!(built.rejected, vec![unsupported], "{:?}", database);
+        }
+    }
+
+    #[test]
+    fn test_array_column() {
+        let query = "filter[]=tags-has-rust&filter[]=releasedOn-overlaps-2024-01-01,2024-02-01";
+
+        let parsed = UrlQuery::new(query, ["tags", "releasedOn"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM posts", parsed)
+            .field_types(HashMap::from([("releasedOn", FieldType::Date)]))
+            .pass_through(["tags", "releasedOn"])
+            .array_column("tags")
+            .array_column("releasedOn");
+
+        let (sql, args) = builder.clone().build_checked().unwrap();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM posts WHERE $1 = ANY(tags) AND releasedOn && $2"
+        );
+        assert_eq!(
+            args[1],
+            Arg::new(
+                "releasedOn",
+                "2024-01-01,2024-02-01",
+                Some(Operator::OVERLAPS),
+                2
+            )
+        );
+
+        let (_, args) = builder.clone().build_typed().unwrap();
+
+        assert_eq!(
+            args[0],
+            ("tags".to_owned(), ArgValue::Text("rust".to_owned()))
+        );
+        assert_eq!(
+            args[1].1,
+            ArgValue::Array(vec![
+                ArgValue::Date("2024-01-01".to_owned()),
+                ArgValue::Date("2024-02-01".to_owned()),
+            ])
+        );
+
+        // MySQL has no arrays: `build` matches nothing and `build_checked` fails.
+        let builder = builder.set_database(Database::MySQL);
+        let built = builder.clone().build_parts();
+        assert_eq!(built.sql, "SELECT * FROM posts WHERE (1 = 0) AND (1 = 0)");
+        assert!(built.args.is_empty());
+        assert_eq!(
+            built.rejected,
+            vec![
+                BuildError::UnsupportedOperator {
+                    field: "tags".to_owned(),
+                    operator: Operator::HAS,
+                },
+                BuildError::UnsupportedOperator {
+                    field: "releasedOn".to_owned(),
+                    operator: Operator::OVERLAPS,
+                },
+            ]
+        );
+        assert_eq!(
+            builder.build_checked(),
+            Err(BuildError::UnsupportedOperator {
+                field: "tags".to_owned(),
+                operator: Operator::HAS,
+            })
+        );
+
+        let parsed = UrlQuery::new("filter[]=title-has-rust", ["title"]).unwrap();
+
+        let result = QueryBuilder::from_str("SELECT * FROM posts", parsed)
+            .pass_through(["title"])
+            .array_column("tags")
+            .build_checked();
+
+        assert_eq!(
+            result,
+            Err(BuildError::Parse(ParseError::OperatorNotAllowed {
+                field: "title".to_owned(),
+                operator: Operator::HAS,
+                raw: Some("filter[]=title-has-rust".to_owned()),
+            }))
+        );
+        assert_eq!(
+            UrlQuery::new("filter[]=tags-overlaps-rust,,sql", ["tags"]),
+            Err(ParseError::InvalidFilter)
+        );
+    }
+
+    #[test]
+    fn test_full_text_search() {
+        let query = "q=rust%20async&status=published&sort=relevance-desc&limit=10";
+
+        let cases = [
+            (
+                Database::Postgres,
+                "SELECT * FROM posts WHERE status = $1 \
+                AND to_tsvector('english', coalesce(title, '') || ' ' || coalesce(body, '')) \
+                @@ plainto_tsquery('english', $2) \
+                ORDER BY ts_rank(to_tsvector('english', coalesce(title, '') || ' ' || \
+                coalesce(body, '')), plainto_tsquery('english', $2)) DESC LIMIT 10",
+            ),
+            (
+                Database::MySQL,
+                "SELECT * FROM posts WHERE status = ? \
+                AND MATCH(title, body) AGAINST (? IN NATURAL LANGUAGE MODE) LIMIT 10",
+            ),
+        ];
+
+        let options = Options {
+            search: true,
+            ..Default::default()
+        };
+
+        for (database, expected) in cases {
+            let parsed = UrlQuery::with_options(query, ["status"], options.clone()).unwrap();
+
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM posts", parsed)
+                .set_database(database)
+                .full_text_search(&["title", "body"], "english")
+                .pass_through(["status"])
+                .build_checked()
+                .unwrap();
+
+            assert_eq!(sql, expected, "{:?}", database);
+            assert_eq!(
+                into_tuples(args),
+                [
+                    ("status".to_owned(), "published".to_owned()),
+                    ("q".to_owned(), "rust async".to_owned()),
+                ]
+            );
+        }
+
+        let parsed = UrlQuery::with_options("status=published", ["status"], options.clone());
+
+        let (sql, _) = QueryBuilder::from_str("SELECT * FROM posts", parsed.unwrap())
+            .full_text_search(&["title", "body"], "english")
+            .build();
+
+        assert_eq!(sql, "SELECT * FROM posts WHERE status = $1");
+
+        let result = UrlQuery::with_options("sort=relevance-desc", ["status"], options.clone());
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidSortField("relevance".into()))
+        );
+
+        // Databases without a full-text search match nothing, and fail `build_checked`.
+        let parsed = UrlQuery::with_options(query, ["status"], options).unwrap();
+        let builder = QueryBuilder::from_str("SELECT * FROM posts", parsed)
+            .set_database(Database::Sqlite)
+            .full_text_search(&["title", "body"], "english")
+            .pass_through(["status"]);
+
+        let built = builder.clone().build_parts();
+        assert_eq!(
+            built.sql,
+            "SELECT * FROM posts WHERE status = ? AND (1 = 0) LIMIT 10"
+        );
+        assert_eq!(built.rejected, vec![BuildError::UnsupportedSearch]);
+        assert_eq!(builder.build_checked(), Err(BuildError::UnsupportedSearch));
+
+        // Without `Options::search`, `q` and `relevance` are fields like any other.
+        let parsed = UrlQuery::new("q=rust&sort=relevance-desc", ["q", "relevance"]).unwrap();
+        assert_eq!(parsed.search, None);
+        assert_eq!(parsed.filters, vec![Filter::new("q-eq-rust").unwrap()]);
+
+        let result = UrlQuery::new("q=rust", ["status"]);
+        assert_eq!(result, Err(ParseError::InvalidField));
+    }
+
+    #[test]
+    fn test_like_filters() {
+        let query = "filter[]=name-contains-50%25_off&filter[]=code-like-AB%25";
+
+        let cases = [
+            (
+                Database::Postgres,
+                r"SELECT * FROM products WHERE name LIKE $1 ESCAPE '\' AND code LIKE $2 ESCAPE '\'",
+            ),
+            (
+                Database::MySQL,
+                r"SELECT * FROM products WHERE name LIKE ? ESCAPE '\\' AND code LIKE ? ESCAPE '\\'",
+            ),
+        ];
+
+        for (database, expected) in cases {
+            let parsed = UrlQuery::new(query, ["name", "code"]).unwrap();
+
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM products", parsed)
+                .set_database(database)
+                .build();
+
+            assert_eq!(sql, expected, "{:?}", database);
+            assert_eq!(args[0].value, r"%50\%\_off%");
+            assert_eq!(args[1].value, "AB%");
+        }
+
+        let parsed = UrlQuery::new(query, ["name", "code"]).unwrap();
+
+        let (_, args) = QueryBuilder::from_str("SELECT * FROM products", parsed)
+            .escape_like()
+            .build();
+
+        assert_eq!(args[1].value, r"AB\%");
+    }
+
+    #[test]
+    fn test_map_values() {
+        let query = "status=open&filter[]=country-ne-France&filter[]=region-overlaps-north,west";
+        let countries = [("France", "FR"), ("Germany", "DE")];
+
+        let parsed = UrlQuery::new(query, ["status", "country", "region"]).unwrap();
+
+        let builder = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .map_values("status", [("open", "0"), ("closed", "1")])
+            .map_values("country", countries)
+            .map_values("region", [("north", "N"), ("south", "S")]);
+
+        let (sql, args) = builder.clone().build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE status = $1 AND country != $2 AND region && $3"
+        );
+        assert_eq!(
+            into_tuples(args),
+            [
+                ("status".to_owned(), "0".to_owned()),
+                ("country".to_owned(), "FR".to_owned()),
+                ("region".to_owned(), "N,west".to_owned()),
+            ]
+        );
+
+        let result = builder
+            .map_values_strict("region", [("N", "1"), ("S", "2")])
+            .pass_through(["status", "country", "region"])
+            .array_column("region")
+            .build_checked();
+
+        assert_eq!(
+            result,
+            Err(BuildError::InvalidValue {
+                field: "region".to_owned(),
+                value: "west".to_owned(),
+                reason: "not mapped".to_owned(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_sqlx_bind() {
+        use sqlx::{Arguments, Execute, Postgres};
+
+        fn bind(query: &str) -> Result<usize, String> {
+            let parsed = UrlQuery::new(query, ["userId", "status", "note"]).unwrap();
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+
+            let mut query = sqlx::query::<Postgres>(&sql);
+            crate::sqlx_bind!(
+                args => query,
+                error: |field, value| format!("invalid {}: {}", field, value),
+                "userId" => i64,
+                "note" => Option<i64>,
+                _ => String
+            );
+
+            Ok(query.take_arguments().unwrap().map_or(0, |args| args.len()))
+        }
+
+        assert_eq!(bind("userId=1&status=paid&note="), Ok(3));
+        assert_eq!(
+            bind("userId=x&status=paid"),
+            Err("invalid userId: x".to_owned())
+        );
+        assert_eq!(bind("note=y"), Err("invalid note: y".to_owned()));
+
+        fn skip(query: &str) -> Result<usize, String> {
+            let parsed = UrlQuery::new(query, ["userId", "status"]).unwrap();
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed).build();
+
+            let mut query = sqlx::query::<Postgres>(&sql);
+            crate::sqlx_bind!(args => query, error: "invalid".to_owned(), "userId" => i64);
+
+            Ok(query.take_arguments().unwrap().map_or(0, |args| args.len()))
+        }
+
+        assert_eq!(skip("userId=1&status=paid"), Ok(1));
+        assert_eq!(skip("userId=x"), Err("invalid".to_owned()));
+    }
+
+    #[test]
+    fn test_sqlx_bind_list() {
+        use sqlx::{Arguments, Execute, Postgres};
+
+        fn bind(query: &str) -> Result<usize, String> {
+            let parsed = UrlQuery::new(query, ["tags", "ids"]).unwrap();
+            let (sql, args) = QueryBuilder::from_str("SELECT * FROM posts", parsed)
+                .pass_through(["tags", "ids"])
+                .array_column("tags")
+                .array_column("ids")
+                .build_checked()
+                .unwrap();
+
+            let mut query = sqlx::query::<Postgres>(&sql);
+            crate::sqlx_bind!(
+                args => query,
+                error: |field, value| format!("invalid {}: {}", field, value),
+                "tags" => String,
+                "ids" => Vec<i64> (split ",")
+            );
+
+            Ok(query.take_arguments().unwrap().map_or(0, |args| args.len()))
+        }
+
+        assert_eq!(
+            bind("filter[]=tags-has-rust&filter[]=ids-overlaps-1,2,3"),
+            Ok(2)
+        );
+        assert_eq!(
+            bind("filter[]=ids-overlaps-1,x"),
+            Err("invalid ids: x".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_with_defaults() {
+        let parsed = UrlQuery::new("filter[]=isPublic-eq-false", ["isPublic", "orderStatus"])
+            .unwrap()
+            .with_defaults([("isPublic", "true"), ("orderStatus", "active")]);
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed)
+            .convert_case(NameCase::Snake)
+            .map_columns(HashMap::from([("orderStatus", "orders")]))
+            .build();
+
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE is_public = $1 AND orders.order_status = $2"
+        );
+        assert_eq!(
+            into_tuples(args),
+            vec![
+                ("isPublic".to_owned(), "false".to_owned()),
+                ("orderStatus".to_owned(), "active".to_owned()),
+            ]
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_trace() {
+        use crate::capture;
+
+        let builder = || {
+            let parsed = UrlQuery::new("id=1&limit=10", ["id"]).unwrap();
+            QueryBuilder::from_str("SELECT id FROM orders", parsed)
+        };
+
+        let events = capture::events(|| {
+            builder().build();
+        });
+        assert_eq!(
+            events,
+            vec![
+                "DEBUG message=built query sql=\"SELECT id FROM orders WHERE id = $1 LIMIT 10\" \
+                 args=1 limit=Some(10) offset=None filters=1"
+            ]
+        );
+
+        let events = capture::events(|| {
+            builder().trace_values().build();
+        });
+        assert!(events[0].contains("args=[Arg { field: \"id\", value: \"1\""));
+
+        let events = capture::events(|| {
+            builder().build_count();
+        });
+        assert_eq!(
+            events,
+            vec![
+                "DEBUG message=built query sql=\"SELECT COUNT(*) FROM orders WHERE id = $1\" \
+                 args=1 limit=None offset=None filters=1"
+            ]
+        );
+
+        let events = capture::events(|| {
+            builder().build_paginated();
+        });
+        assert_eq!(events.len(), 2);
+        assert!(events[0].contains("LIMIT 10\" args=1 limit=Some(10)"));
+        assert!(events[1].contains("SELECT COUNT(*)"));
     }
 }
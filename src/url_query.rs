@@ -1,26 +1,204 @@
-use std::collections::HashSet;
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use crate::{
-    filter::{Condition, Filter},
+    cursor::Cursor,
+    filter::{Filter, Operator},
+    schema::QuerySchema,
     sort::Sort,
     ParseError,
 };
 
-fn check_allowed_fields(field: &str, allowed_fields: &HashSet<&str>) -> Result<(), ParseError> {
-    if !allowed_fields.contains(field) {
+/// Fields end up in the SQL text, so anything but letters, digits, `_` and `.` is refused even if
+/// it's allowed.
+fn check_identifier(field: &str) -> Result<(), ParseError> {
+    let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+    if field.is_empty() || !field.chars().all(valid) {
+        Err(ParseError::InvalidIdentifier(field.to_owned()))?
+    }
+
+    Ok(())
+}
+
+/// Whether the key matches an entry of `Options::ignored`, eg `utm_source` matches `utm_*`.
+fn is_ignored(key: &str, ignored: &[String]) -> bool {
+    ignored
+        .iter()
+        .any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => key.starts_with(prefix),
+            None => key == pattern,
+        })
+}
+
+/// A dotted field is allowed if its JSON column is in `json_fields`, eg `metadata.color`.
+fn check_allowed_fields(
+    field: &str,
+    allowed_fields: &HashSet<&str>,
+    json_fields: &[String],
+) -> Result<(), ParseError> {
+    check_identifier(field)?;
+
+    let json = field.split_once('.').is_some_and(|(column, path)| {
+        json_fields.iter().any(|json| json == column) && !path.split('.').any(str::is_empty)
+    });
+    if !allowed_fields.contains(field) && !json {
         Err(ParseError::InvalidField)?
     }
 
     Ok(())
 }
 
-#[derive(Debug, PartialEq)]
+/// Names the field of an `InvalidField` error, eg `InvalidSortField("price")`.
+fn name_invalid(error: ParseError, field: &str, named: fn(String) -> ParseError) -> ParseError {
+    match error {
+        ParseError::InvalidField => named(field.to_owned()),
+        error => error,
+    }
+}
+
+/// `cont` filters compare JSON, so they are only allowed on the JSON columns.
+fn check_operator(filter: &Filter, json_fields: &[String]) -> Result<(), ParseError> {
+    if filter.operator == Operator::CONT && !json_fields.contains(&filter.field) {
+        Err(ParseError::OperatorNotAllowed {
+            field: filter.field.to_owned(),
+            operator: filter.operator,
+            raw: filter.raw.clone(),
+        })?
+    }
+
+    Ok(())
+}
+
+fn parse_page(str: &str) -> Result<u64, ParseError> {
+    match str.parse() {
+        Ok(0) | Err(_) => Err(ParseError::InvalidPage),
+        Ok(page) => Ok(page),
+    }
+}
+
+/// Percent-decodes a query string component. Invalid escapes are kept as they are.
+fn decode(str: &str) -> String {
+    let bytes = str.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(b) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(b);
+                i += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Percent-encodes everything except the unreserved characters of RFC 3986.
+fn encode(str: &str) -> String {
+    let mut encoded = String::with_capacity(str.len());
+
+    for b in str.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(b as char)
+            }
+            _ => {
+                encoded.push('%');
+                encoded.push_str(&format!("{:02X}", b));
+            }
+        }
+    }
+
+    encoded
+}
+
+/// What to do when the requested limit is above `Options::max_limit`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LimitOverflow {
+    /// Lower the limit to the maximum.
+    #[default]
+    Clamp,
+    /// Fail with `ParseError::LimitExceeded`.
+    Reject,
+}
+
+/// Options used when parsing a url query.
+///
+/// # Examples
+///
+/// ```
+/// use query::{url_query::Options, UrlQuery};
+///
+/// let options = Options {
+///     default_limit: Some(25),
+///     max_limit: Some(100),
+///     ..Default::default()
+/// };
+///
+/// let parsed = UrlQuery::with_options("limit=1000", ["id"], options).unwrap();
+///
+/// assert_eq!(parsed.check_limit(), Ok(100));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Options {
+    /// The limit used when the query has none.
+    pub default_limit: Option<u64>,
+    /// The largest limit a query may request.
+    pub max_limit: Option<u64>,
+    /// Whether a limit above `max_limit` is clamped or rejected.
+    pub limit_overflow: LimitOverflow,
+    /// The fields each resource type may select with JSON:API sparse fieldsets, eg
+    /// `fields[orders]=id,status`. Resource types not included here are rejected.
+    pub selectable_fields: HashMap<String, Vec<String>>,
+    /// Aggregate aliases which `having` may reference besides the grouped fields, eg `count`. Map
+    /// them to their SQL with `QueryBuilder::map_aggregates`.
+    pub aggregates: Vec<String>,
+    /// Don't keep the raw text of each filter and sort, see `Filter::raw` and `Sort::raw`.
+    pub discard_raw: bool,
+    /// JSON columns whose paths may be used as fields, eg `metadata` allows `metadata.color`,
+    /// and which `cont` filters may use. Render them with `QueryBuilder::json_column`.
+    pub json_fields: Vec<String>,
+    /// Parameters which are skipped instead of parsed, eg `apiKey`. A trailing `*` matches any
+    /// suffix, eg `utm_*`. Their values are kept in `UrlQuery::ignored`.
+    pub ignored: Vec<String>,
+    /// Fail with `ParseError::UnknownParameter` listing every parameter and field which isn't
+    /// allowed, instead of with `ParseError::InvalidField` on the first one.
+    pub strict: bool,
+    /// Parse `q` as a full-text search, kept in `UrlQuery::search`, and allow sorting by
+    /// `relevance`, see `QueryBuilder::full_text_search`. Otherwise both are fields like any other.
+    pub search: bool,
+}
+
+/// A parsed and validated url query.
+///
+/// `filter[]` predicates are always applied before aggregation (`WHERE`), while `having`
+/// predicates are applied after aggregation (`HAVING`) and may only reference a grouped field.
+#[derive(Debug, Clone, PartialEq)]
 pub struct UrlQuery {
     pub params: HashSet<String>,
     pub filters: Vec<Filter>,
-    pub group: Option<String>,
-    pub sort: Option<Sort>,
-    pub limit_offset: (Option<String>, Option<String>),
+    pub groups: Vec<String>,
+    pub havings: Vec<Filter>,
+    pub sorts: Vec<Sort>,
+    pub after: Option<String>,
+    pub cursor: Option<Cursor>,
+    pub limit_offset: (Option<u64>, Option<u64>),
+    /// Whether the limit is `Options::default_limit`, the url query having none.
+    pub default_limit: bool,
+    pub page: Option<u64>,
+    pub sparse_fields: HashMap<String, Vec<String>>,
+    /// The text of the `q` parameter, see `QueryBuilder::full_text_search`.
+    pub search: Option<String>,
+    /// The values of the parameters skipped by `Options::ignored`, by key.
+    pub ignored: HashMap<String, String>,
 }
 
 impl UrlQuery {
@@ -28,64 +206,276 @@ impl UrlQuery {
         str: &str,
         allowed_fields: impl Into<HashSet<&'a str>>,
     ) -> Result<Self, ParseError> {
-        let allowed_fields: HashSet<&str> = allowed_fields.into();
+        Self::with_options(str, allowed_fields, Options::default())
+    }
+
+    /// Parses a url query like `new`, applying the default and maximum limit from `options`.
+    pub fn with_options<'a>(
+        str: &str,
+        allowed_fields: impl Into<HashSet<&'a str>>,
+        options: Options,
+    ) -> Result<Self, ParseError> {
+        let mut segment = None;
+        let result = Self::parse(str, allowed_fields.into(), options, &mut segment);
+
+        #[cfg(feature = "tracing")]
+        if let Err(ref e) = result {
+            let segment = segment.unwrap_or(str);
+            tracing::warn!(error = %e, segment, "failed to parse url query");
+        }
+
+        result
+    }
+
+    /// Parses a url query like `new`, allowing the fields of the schema, and checks the operators
+    /// and values of the filters against it, see `QuerySchema::check`.
+    pub fn with_schema(str: &str, schema: &QuerySchema) -> Result<Self, ParseError> {
+        let query = Self::new(str, schema.allowed_fields())?;
+        schema.check(&query)?;
+
+        Ok(query)
+    }
 
+    /// Parses the url query, setting `segment` to the `key=value` pair being parsed, or to
+    /// `None` once every pair is parsed.
+    fn parse<'q>(
+        str: &'q str,
+        allowed_fields: HashSet<&str>,
+        options: Options,
+        segment: &mut Option<&'q str>,
+    ) -> Result<Self, ParseError> {
         let mut params = HashSet::new();
 
         let queries: Vec<&str> = str.split("&").collect();
         let mut filters = Vec::new();
-        let mut group = None;
-        let mut sort = None;
+        let mut groups: Vec<String> = Vec::new();
+        let mut havings = Vec::new();
+        let mut sorts = Vec::new();
+        let mut after = None;
+        let mut cursor = None;
         let mut limit_offset = (None, None);
+        let mut page = None;
+        let mut per_page = None;
+        let mut sparse_fields = HashMap::new();
+        let mut search = None;
+        let mut ignored = HashMap::new();
+
+        let raw = |k: &str, v: &str| (!options.discard_raw).then(|| format!("{}={}", k, v));
+
+        let mut unknown: Vec<String> = Vec::new();
+        let check = |field: &str, unknown: &mut Vec<String>| match check_allowed_fields(
+            field,
+            &allowed_fields,
+            &options.json_fields,
+        ) {
+            Err(ParseError::InvalidField) if options.strict => {
+                if !unknown.iter().any(|key| key == field) {
+                    unknown.push(field.to_owned());
+                }
+                Ok(())
+            }
+            result => result,
+        };
 
         for q in queries {
-            let (k, v) = match q.split_once("=") {
-                Some(kv) => kv,
+            *segment = Some(q);
+            let (k, v) = match q.split_once('=') {
+                Some((k, v)) => (decode(k), decode(v)),
                 None => continue,
             };
+            let (k, v) = (k.as_str(), v.as_str());
+
+            if is_ignored(k, &options.ignored) {
+                ignored.insert(k.to_owned(), v.to_owned());
+                continue;
+            }
 
             if k == "filter[]" {
-                let filter = Filter::new(v)?;
-                check_allowed_fields(&filter.field, &allowed_fields)?;
+                let mut filter = Filter::new(v).map_err(|e| e.with_raw(raw(k, v)))?;
+                check(&filter.field, &mut unknown)?;
+                filter.raw = raw(k, v);
+                check_operator(&filter, &options.json_fields)?;
                 filters.push(filter);
                 continue;
             }
 
             if k == "group" {
-                check_allowed_fields(v, &allowed_fields)?;
-                group = Some(v.to_owned());
+                for g in v.split(',') {
+                    check(g, &mut unknown)
+                        .map_err(|e| name_invalid(e, g, ParseError::InvalidGroupField))?;
+                    if !groups.iter().any(|group| group == g) {
+                        groups.push(g.to_owned());
+                    }
+                }
+                continue;
+            }
+
+            if k == "having" || k == "having[]" {
+                let mut having = Filter::new(v).map_err(|e| e.with_raw(raw(k, v)))?;
+                match options.aggregates.contains(&having.field) {
+                    true => check_identifier(&having.field)?,
+                    false => check(&having.field, &mut unknown)?,
+                }
+                having.raw = raw(k, v);
+                check_operator(&having, &options.json_fields)?;
+                havings.push(having);
                 continue;
             }
 
             if k == "sort" {
-                sort = Some(Sort::new(v)?);
-                check_allowed_fields(&sort.as_ref().unwrap().field, &allowed_fields)?;
+                for s in v.split(',') {
+                    let mut sort = Sort::new(s)?;
+                    // Sorting by relevance requires a search, which is checked once parsed.
+                    if !(options.search && sort.field == "relevance") {
+                        check(&sort.field, &mut unknown).map_err(|e| {
+                            name_invalid(e, &sort.field, ParseError::InvalidSortField)
+                        })?;
+                    }
+                    sort.raw = raw(k, s);
+                    sorts.push(sort);
+                }
+                continue;
+            }
+
+            if k == "q" && options.search {
+                search = Some(v.to_owned()).filter(|search| !search.is_empty());
+                continue;
+            }
+
+            if k == "after" {
+                after = Some(v.to_owned());
+                continue;
+            }
+
+            if k == "cursor" {
+                cursor = Some(Cursor::decode(v)?);
                 continue;
             }
 
             if k == "limit" {
-                limit_offset.0 = Some(v.to_owned());
+                limit_offset.0 = Some(v.parse().map_err(|_| ParseError::InvalidLimit)?);
                 continue;
             }
 
             if k == "offset" {
-                limit_offset.1 = Some(v.to_owned());
+                limit_offset.1 = Some(v.parse().map_err(|_| ParseError::InvalidOffset)?);
+                continue;
+            }
+
+            if let Some(resource) = k.strip_prefix("fields[").and_then(|k| k.strip_suffix(']')) {
+                let fields: Vec<String> = v
+                    .split(',')
+                    .filter(|field| !field.is_empty())
+                    .map(|field| field.to_owned())
+                    .collect();
+
+                let selectable = options.selectable_fields.get(resource);
+                for field in fields.iter() {
+                    if !selectable.is_some_and(|selectable| selectable.contains(field)) {
+                        Err(ParseError::InvalidSparseField {
+                            resource: resource.to_owned(),
+                            field: field.to_owned(),
+                        })?
+                    }
+                }
+
+                sparse_fields.insert(resource.to_owned(), fields);
+                continue;
+            }
+
+            if k == "page" {
+                page = Some(parse_page(v)?);
+                continue;
+            }
+
+            if k == "per_page" {
+                per_page = Some(parse_page(v)?);
                 continue;
             }
 
-            check_allowed_fields(k, &allowed_fields)?;
-            filters.push(Filter::from_key_value(k, v, Condition::EQ));
+            check(k, &mut unknown)?;
+            let mut filter = Filter::from_key_value(k, v, Operator::EQ);
+            filter.raw = raw(k, v);
+            filters.push(filter);
 
             // To check required:
             params.insert(k.into());
         }
+        *segment = None;
+
+        // HAVING is evaluated after aggregation, so it can only reference a grouped field or an
+        // aggregate.
+        for having in havings.iter() {
+            let aggregate = options.aggregates.contains(&having.field);
+            if groups.is_empty() || !(aggregate || groups.contains(&having.field)) {
+                Err(ParseError::InvalidHaving)?
+            }
+        }
+
+        let relevance = sorts.iter().any(|sort| sort.field == "relevance");
+        if options.search && search.is_none() && relevance {
+            check("relevance", &mut unknown)
+                .map_err(|e| name_invalid(e, "relevance", ParseError::InvalidSortField))?;
+        }
+
+        if !unknown.is_empty() {
+            Err(ParseError::UnknownParameter(unknown))?
+        }
+
+        // The seek position is relative to the sort column.
+        if after.is_some() && sorts.is_empty() {
+            Err(ParseError::InvalidAfter)?
+        }
+
+        if cursor.is_some() && sorts.is_empty() {
+            Err(ParseError::InvalidCursor)?
+        }
+
+        // Explicit limit and offset take precedence over per_page and page.
+        if limit_offset.0.is_none() {
+            limit_offset.0 = per_page;
+        }
+
+        let default_limit = limit_offset.0.is_none() && options.default_limit.is_some();
+        if default_limit {
+            limit_offset.0 = options.default_limit;
+        }
+
+        if let (Some(max_limit), Some(limit)) = (options.max_limit, limit_offset.0) {
+            if limit > max_limit {
+                match options.limit_overflow {
+                    LimitOverflow::Clamp => limit_offset.0 = Some(max_limit),
+                    LimitOverflow::Reject => Err(ParseError::LimitExceeded)?,
+                }
+            }
+        }
+
+        if limit_offset.1.is_some() {
+            page = None;
+        }
+
+        if let Some(page) = page {
+            let per_page = limit_offset.0.ok_or(ParseError::InvalidPage)?;
+            let offset = (page - 1)
+                .checked_mul(per_page)
+                .ok_or(ParseError::InvalidPage)?;
+            limit_offset.1 = Some(offset);
+        }
 
         Ok(Self {
             params,
             filters,
-            group,
-            sort,
+            groups,
+            havings,
+            sorts,
+            after,
+            cursor,
             limit_offset,
+            default_limit,
+            page,
+            sparse_fields,
+            search,
+            ignored,
         })
     }
 
@@ -94,7 +484,7 @@ impl UrlQuery {
         T: IntoIterator<Item = &'a str>,
     {
         for r in required {
-            if let None = self.params.get(r) {
+            if !self.params.contains(r) {
                 let mut res = String::new();
                 res.push_str(r);
                 res.push_str(" is required");
@@ -105,54 +495,430 @@ impl UrlQuery {
         Ok(())
     }
 
-    pub fn check_limit(&self) -> Result<&str, String> {
+    /// Returns `ParseError::MissingParams` listing every key which was passed neither as a param
+    /// nor as the field of a `filter[]`, eg for a report requiring `from` and `to`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::{ParseError, UrlQuery};
+    ///
+    /// let parsed = UrlQuery::new("filter[]=from-ge-2024-01-01", ["from", "to", "region"]).unwrap();
+    ///
+    /// let err = parsed.require(&["from", "to", "region"]).unwrap_err();
+    /// assert_eq!(err.to_string(), "missing required parameters: to, region");
+    /// ```
+    pub fn require(&self, keys: &[&str]) -> Result<(), ParseError> {
+        let missing: Vec<String> = keys
+            .iter()
+            .filter(|key| {
+                !self.params.contains(**key)
+                    && !self.filters.iter().any(|filter| filter.field == **key)
+            })
+            .map(|key| key.to_string())
+            .collect();
+
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(ParseError::MissingParams(missing)),
+        }
+    }
+
+    /// Returns the value of a param, eg `1` for `userId=1`, or `None` if it wasn't passed as a
+    /// param or other filters are on it, eg with `userId=1&filter[]=userId-eq-2`. Params are
+    /// stored as `eq` filters, see `get` to tell the two cases apart.
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.param(key).ok().flatten()
+    }
+
+    /// The value of `key` if it was passed as a param, failing if another filter is on the key,
+    /// eg `filter[]=userId-eq-2` or `filter[]=userId-ge-0`.
+    fn param(&self, key: &str) -> Result<Option<&str>, ParseError> {
+        if !self.params.contains(key) {
+            return Ok(None);
+        }
+
+        let mut value = None;
+        for filter in self.filters.iter().filter(|filter| filter.field == key) {
+            match (filter.operator, value) {
+                (Operator::EQ, None) => value = Some(filter.value.as_str()),
+                (Operator::EQ, Some(v)) if v == filter.value => {}
+                _ => Err(ParseError::AmbiguousParam(key.to_owned()))?,
+            }
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the value of a param parsed as `T`, see `get_str`. Fails with
+    /// `ParseError::InvalidValue` if the value can't be parsed, or `ParseError::AmbiguousParam`
+    /// if other filters are on the param, since with `filter_combinator(Combinator::Or)` the SQL
+    /// would match them too.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::UrlQuery;
+    ///
+    /// # let subject = 123;
+    /// let parsed = UrlQuery::new("userId=123&status=paid", ["userId", "status"]).unwrap();
+    ///
+    /// // Only let users list their own orders.
+    /// let user_id: Option<i64> = parsed.get("userId").unwrap();
+    /// assert_eq!(user_id, Some(subject));
+    ///
+    /// assert!(parsed.get::<i64>("status").is_err());
+    /// assert_eq!(parsed.get::<i64>("orderId"), Ok(None));
+    /// ```
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, ParseError> {
+        let Some(value) = self.param(key)? else {
+            return Ok(None);
+        };
+
+        match value.parse() {
+            Ok(value) => Ok(Some(value)),
+            Err(_) => Err(ParseError::InvalidValue {
+                field: key.to_owned(),
+                value: value.to_owned(),
+                raw: None,
+            }),
+        }
+    }
+
+    /// Returns the page number if the offset was derived from the `page` param.
+    pub fn page(&self) -> Option<u64> {
+        self.page
+    }
+
+    /// Returns the fields requested with `fields[resource]=...` for a resource type.
+    pub fn sparse_fields(&self, resource: &str) -> Option<&[String]> {
+        self.sparse_fields
+            .get(resource)
+            .map(|fields| fields.as_slice())
+    }
+
+    pub fn check_limit(&self) -> Result<u64, String> {
         match self.limit_offset.0 {
-            Some(ref limit) => Ok(limit),
+            Some(limit) => Ok(limit),
             None => Err(String::from("limit is required")),
         }
     }
 
-    pub fn check_offset(&self) -> Result<&str, String> {
+    pub fn check_offset(&self) -> Result<u64, String> {
         match self.limit_offset.1 {
-            Some(ref offset) => Ok(offset),
+            Some(offset) => Ok(offset),
             None => Err(String::from("offset is required")),
         }
     }
 
-    pub fn check_limit_and_offset(&self) -> Result<(&str, &str), String> {
+    pub fn check_limit_and_offset(&self) -> Result<(u64, u64), String> {
         let limit = self.check_limit()?;
         let offset = self.check_offset()?;
 
         Ok((limit, offset))
     }
 
+    /// Returns the query as a canonical, percent-encoded query string. Parsing the result with
+    /// the same allowed fields yields an equal `UrlQuery`.
+    ///
+    /// Filters are emitted in order, followed by `q`, `group`, `having`, `sort`, `after`,
+    /// `cursor`, `limit`, `fields[...]` sorted by resource type and `offset` (or `page` if the
+    /// offset was derived from it). Equality filters on plain params are emitted as
+    /// `field=value`, all others as `filter[]=...`.
+    pub fn to_query_string(&self) -> String {
+        let mut queries = Vec::new();
+
+        for filter in self.filters.iter() {
+            if filter.operator == Operator::EQ && self.params.contains(&filter.field) {
+                queries.push(format!(
+                    "{}={}",
+                    encode(&filter.field),
+                    encode(&filter.value)
+                ));
+            } else {
+                queries.push(format!("filter[]={}", encode(&filter.to_query_string())));
+            }
+        }
+
+        if let Some(ref search) = self.search {
+            queries.push(format!("q={}", encode(search)));
+        }
+
+        if !self.groups.is_empty() {
+            let groups: Vec<String> = self.groups.iter().map(|group| encode(group)).collect();
+            queries.push(format!("group={}", groups.join(",")));
+        }
+
+        for having in self.havings.iter() {
+            queries.push(format!("having={}", encode(&having.to_query_string())));
+        }
+
+        if !self.sorts.is_empty() {
+            let sorts: Vec<String> = self
+                .sorts
+                .iter()
+                .map(|sort| encode(&sort.to_query_string()))
+                .collect();
+            queries.push(format!("sort={}", sorts.join(",")));
+        }
+
+        if let Some(ref after) = self.after {
+            queries.push(format!("after={}", encode(after)));
+        }
+
+        if let Some(ref cursor) = self.cursor {
+            queries.push(format!("cursor={}", cursor.to_token()));
+        }
+
+        if let Some(limit) = self.limit_offset.0 {
+            queries.push(format!("limit={}", limit));
+        }
+
+        let mut resources: Vec<_> = self.sparse_fields.iter().collect();
+        resources.sort();
+        for (resource, fields) in resources {
+            let fields: Vec<String> = fields.iter().map(|field| encode(field)).collect();
+            queries.push(format!("fields[{}]={}", encode(resource), fields.join(",")));
+        }
+
+        match self.page {
+            Some(page) => queries.push(format!("page={}", page)),
+            None => {
+                if let Some(offset) = self.limit_offset.1 {
+                    queries.push(format!("offset={}", offset));
+                }
+            }
+        }
+
+        queries.join("&")
+    }
+
+    /// Returns the query string for the next page, or `None` if there is no limit or the next
+    /// offset would overflow.
+    pub fn next_page(&self) -> Option<String> {
+        let (limit, offset) = self.page_limit_and_offset()?;
+
+        Some(self.with_offset(offset.checked_add(limit)?))
+    }
+
+    /// Returns the query string for the next page, or `None` if there is no limit, the next
+    /// offset would overflow or the current page is the last one.
+    pub fn next_page_with_total(&self, total: u64) -> Option<String> {
+        let (limit, offset) = self.page_limit_and_offset()?;
+        let next = offset.checked_add(limit)?;
+
+        if next >= total {
+            return None;
+        }
+
+        Some(self.with_offset(next))
+    }
+
+    /// Returns the query string for the previous page, or `None` if there is no limit or this is
+    /// the first page. The offset is clamped at zero.
+    pub fn prev_page(&self) -> Option<String> {
+        let (limit, offset) = self.page_limit_and_offset()?;
+
+        if offset == 0 {
+            return None;
+        }
+
+        Some(self.with_offset(offset.saturating_sub(limit)))
+    }
+
+    fn page_limit_and_offset(&self) -> Option<(u64, u64)> {
+        let limit = self.limit_offset.0?;
+        let offset = self.limit_offset.1.unwrap_or(0);
+
+        Some((limit, offset))
+    }
+
+    fn with_offset(&self, offset: u64) -> String {
+        let mut query = self.clone();
+        query.limit_offset.1 = Some(offset);
+        query.page = None;
+
+        query.to_query_string()
+    }
+
+    /// Adds an `eq` filter for each default whose field the client passed neither as a param nor
+    /// in a `filter[]`, whatever its operator, eg `status=active` unless `filter[]=status-ne-draft`
+    /// was given. The defaults are rendered like the filters of the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::UrlQuery;
+    ///
+    /// let parsed = UrlQuery::new("status=archived", ["status", "visibility"])
+    ///     .unwrap()
+    ///     .with_defaults([("status", "active"), ("visibility", "public")]);
+    ///
+    /// assert_eq!(parsed.get_str("status"), Some("archived"));
+    /// assert_eq!(parsed.get_str("visibility"), Some("public"));
+    /// ```
+    pub fn with_defaults<'a>(
+        mut self,
+        defaults: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        for (field, value) in defaults {
+            let passed = self.params.contains(field)
+                || self.filters.iter().any(|filter| filter.field == field);
+            if passed {
+                continue;
+            }
+
+            self.params.insert(field.to_owned());
+            self.filters.push(Filter {
+                field: field.to_owned(),
+                operator: Operator::EQ,
+                value: value.to_owned(),
+                raw: None,
+            });
+        }
+
+        self
+    }
+
     pub fn filters_mut(&mut self) -> &mut Vec<Filter> {
         &mut self.filters
     }
 
-    pub fn group_mut(&mut self) -> &mut Option<String> {
-        &mut self.group
+    /// Returns the first group, which is the only one unless the query groups by several fields.
+    pub fn group(&self) -> Option<&str> {
+        self.groups.first().map(|group| group.as_str())
+    }
+
+    pub fn groups_mut(&mut self) -> &mut Vec<String> {
+        &mut self.groups
+    }
+
+    pub fn havings_mut(&mut self) -> &mut Vec<Filter> {
+        &mut self.havings
+    }
+
+    /// Returns the first sort, which decides the order before any of the others.
+    pub fn sort(&self) -> Option<&Sort> {
+        self.sorts.first()
+    }
+
+    pub fn sorts_mut(&mut self) -> &mut Vec<Sort> {
+        &mut self.sorts
+    }
+
+    /// Returns the field of the first sort.
+    pub fn sort_field(&self) -> Option<&str> {
+        self.sort().map(|sort| sort.field.as_str())
+    }
+
+    /// Whether the field is filtered on, either as a param or with `filter[]`.
+    pub fn has_filter(&self, field: &str) -> bool {
+        self.filters_on(field).next().is_some()
+    }
+
+    /// Returns the filters on the field, either passed as a param or with `filter[]`.
+    pub fn filters_on<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a Filter> + 'a {
+        self.filters
+            .iter()
+            .filter(move |filter| filter.field == field)
+    }
+
+    /// Whether the query has a limit, offset, page, `after` or cursor. A limit from
+    /// `Options::default_limit` doesn't count, since the client didn't ask for it.
+    pub fn has_pagination(&self) -> bool {
+        (self.limit_offset.0.is_some() && !self.default_limit)
+            || self.limit_offset.1.is_some()
+            || self.page.is_some()
+            || self.after.is_some()
+            || self.cursor.is_some()
     }
 
-    pub fn sort_mut(&mut self) -> &mut Option<Sort> {
-        &mut self.sort
+    /// Whether the query has no filters, search, group, having, sort or pagination.
+    pub fn is_empty(&self) -> bool {
+        self.filters.is_empty()
+            && self.search.is_none()
+            && self.groups.is_empty()
+            && self.havings.is_empty()
+            && self.sorts.is_empty()
+            && !self.has_pagination()
     }
 
-    pub fn limit_offset_mut(&mut self) -> &mut (Option<String>, Option<String>) {
+    pub fn limit_offset_mut(&mut self) -> &mut (Option<u64>, Option<u64>) {
         &mut self.limit_offset
     }
+
+    /// Adds a filter, eg a server-side `tenantId-eq-1`. Its field isn't checked against the
+    /// allowed fields, so it must not come from the client.
+    pub fn add_filter(&mut self, filter: Filter) {
+        self.filters.push(filter);
+    }
+
+    /// Removes every filter on the field, eg one the client isn't allowed to touch, and returns
+    /// them.
+    pub fn remove_filters(&mut self, field: &str) -> Vec<Filter> {
+        self.params.remove(field);
+
+        let (removed, filters) = std::mem::take(&mut self.filters)
+            .into_iter()
+            .partition(|filter| filter.field == field);
+        self.filters = filters;
+
+        removed
+    }
+
+    /// Replaces every filter on the field with one filter, in the place of the first one, or adds
+    /// it if there are none.
+    pub fn replace_filter(&mut self, field: &str, filter: Filter) {
+        let index = self.filters.iter().position(|filter| filter.field == field);
+        self.remove_filters(field);
+
+        match index {
+            Some(index) => self.filters.insert(index, filter),
+            None => self.filters.push(filter),
+        }
+    }
+
+    /// Overrides the limit, eg to force it down. With `page`, the offset is recomputed for the new
+    /// page size.
+    pub fn set_limit(&mut self, limit: u64) {
+        self.limit_offset.0 = Some(limit);
+        self.default_limit = false;
+
+        if let Some(page) = self.page {
+            self.limit_offset.1 = Some((page - 1).saturating_mul(limit));
+        }
+    }
+
+    /// Overrides the offset, which takes precedence over `page` as when parsing.
+    pub fn set_offset(&mut self, offset: u64) {
+        self.limit_offset.1 = Some(offset);
+        self.page = None;
+    }
+
+    /// Replaces the sorts with one sort. `after` and `cursor` are relative to the previous sorts,
+    /// so they are cleared.
+    pub fn set_sort(&mut self, sort: Sort) {
+        self.sorts = vec![sort];
+        self.after = None;
+        self.cursor = None;
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashSet;
+    use std::collections::{HashMap, HashSet};
 
     use crate::{
-        filter::{Condition, Filter},
+        case::NameCase,
+        cursor::Cursor,
+        filter::{Filter, Operator},
         sort::{Sort, SortBy},
+        sql::{into_tuples, QueryBuilder},
         ParseError, UrlQuery,
     };
 
+    use super::{LimitOverflow, Options};
+
     #[test]
     fn test_parse_query() {
         let query =
@@ -168,26 +934,39 @@ mod tests {
             filters: vec![
                 Filter {
                     field: "userId".into(),
-                    condition: Condition::EQ,
+                    operator: Operator::EQ,
                     value: "bob".into(),
+                    raw: None,
                 },
                 Filter {
                     field: "orderId".into(),
-                    condition: Condition::EQ,
+                    operator: Operator::EQ,
                     value: "1".into(),
+                    raw: None,
                 },
                 Filter {
                     field: "price".into(),
-                    condition: Condition::GE,
+                    operator: Operator::GE,
                     value: "200".into(),
+                    raw: None,
                 },
             ],
-            group: Some(String::from("orderId")),
-            sort: Some(Sort {
+            groups: vec![String::from("orderId")],
+            havings: vec![],
+            sorts: vec![Sort {
                 field: String::from("price"),
                 sort_by: SortBy::DESC,
-            }),
+                nulls: None,
+                raw: None,
+            }],
+            after: None,
+            cursor: None,
             limit_offset: (None, None),
+            default_limit: false,
+            page: None,
+            sparse_fields: HashMap::default(),
+            search: None,
+            ignored: HashMap::new(),
         };
 
         assert_eq!(parsed, expected);
@@ -202,9 +981,17 @@ mod tests {
         let expected = UrlQuery {
             params: HashSet::default(),
             filters: vec![],
-            group: None,
-            sort: None,
+            groups: vec![],
+            havings: vec![],
+            sorts: vec![],
+            after: None,
+            cursor: None,
             limit_offset: (None, None),
+            default_limit: false,
+            page: None,
+            sparse_fields: HashMap::default(),
+            search: None,
+            ignored: HashMap::new(),
         };
 
         assert_eq!(parsed, expected);
@@ -219,9 +1006,17 @@ mod tests {
         let expected = UrlQuery {
             params: HashSet::default(),
             filters: vec![],
-            group: None,
-            sort: None,
-            limit_offset: (Some("10".into()), Some("0".into())),
+            groups: vec![],
+            havings: vec![],
+            sorts: vec![],
+            after: None,
+            cursor: None,
+            limit_offset: (Some(10), Some(0)),
+            default_limit: false,
+            page: None,
+            sparse_fields: HashMap::default(),
+            search: None,
+            ignored: HashMap::new(),
         };
 
         assert_eq!(parsed, expected);
@@ -249,4 +1044,852 @@ mod tests {
 
         assert_eq!(result, Err(ParseError::InvalidField))
     }
+
+    #[test]
+    fn test_to_query_string() {
+        let query =
+            "userId=bob&filter[]=orderId-eq-1&filter[]=price-ge-200&group=orderId&sort=price-desc&limit=10&offset=20";
+
+        let parsed = UrlQuery::new(query, ["userId", "orderId", "price"]).unwrap();
+
+        assert_eq!(parsed.to_query_string(), query);
+    }
+
+    #[test]
+    fn test_to_query_string_round_trip() {
+        let values = [
+            "bob",
+            "bob smith",
+            "a&b",
+            "a=b",
+            "a&b=c d",
+            "100%",
+            "%41",
+            "+1",
+            "caf\u{e9}",
+            "\u{65e5}\u{672c}",
+            "\u{1f600} &=",
+            "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e",
+            "",
+        ];
+
+        for value in values {
+            let mut query = UrlQuery::new("", ["userName", "price"]).unwrap();
+            query.params.insert("userName".into());
+            query
+                .filters
+                .push(Filter::from_key_value("userName", value, Operator::EQ));
+            query
+                .filters
+                .push(Filter::from_key_value("price", value, Operator::NE));
+            query.groups = vec!["price".into(), "userName".into()];
+            query
+                .havings
+                .push(Filter::from_key_value("price", value, Operator::GT));
+            query.sorts = vec![
+                Sort {
+                    field: "price".into(),
+                    sort_by: SortBy::ASC,
+                    nulls: None,
+                    raw: None,
+                },
+                Sort {
+                    field: "userName".into(),
+                    sort_by: SortBy::DESC,
+                    nulls: None,
+                    raw: None,
+                },
+            ];
+            query.after = Some(value.into());
+            query.cursor = Some(Cursor {
+                values: vec![value.into(), "1".into()],
+            });
+            query.limit_offset = (Some(10), Some(value.len() as u64));
+
+            let emitted = query.to_query_string();
+            let parsed = UrlQuery::new(&emitted, ["userName", "price"]).unwrap();
+
+            assert_eq!(parsed, query, "round trip failed for {:?}", value);
+            assert_eq!(parsed.to_query_string(), emitted);
+        }
+    }
+
+    #[test]
+    fn test_parse_query_percent_encoded() {
+        let query = "userName=bob%20smith&filter%5B%5D=price-ge-2%26";
+
+        let parsed = UrlQuery::new(query, ["userName", "price"]).unwrap();
+
+        assert_eq!(parsed.filters[0].value, "bob smith");
+        assert_eq!(parsed.filters[1].value, "2&");
+    }
+
+    #[test]
+    fn test_parse_query_having() {
+        let query = "filter[]=status-ne-cancelled&having=status-ge-2&group=status";
+
+        let parsed = UrlQuery::new(query, ["status"]).unwrap();
+
+        assert_eq!(parsed.filters.len(), 1);
+        assert_eq!(
+            parsed.havings,
+            vec![Filter {
+                field: "status".into(),
+                operator: Operator::GE,
+                value: "2".into(),
+                raw: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_query_having_not_grouped() {
+        let query = "group=status&having=price-ge-2";
+        let result = UrlQuery::new(query, ["status", "price"]);
+        assert_eq!(result, Err(ParseError::InvalidHaving));
+
+        let query = "having=status-ge-2";
+        let result = UrlQuery::new(query, ["status"]);
+        assert_eq!(result, Err(ParseError::InvalidHaving));
+    }
+
+    #[test]
+    fn test_next_and_prev_page() {
+        let query =
+            "userId=bob&filter[]=price-ge-200&group=price&sort=price-desc&limit=10&offset=15";
+
+        let parsed = UrlQuery::new(query, ["userId", "price"]).unwrap();
+
+        assert_eq!(
+            parsed.next_page().unwrap(),
+            "userId=bob&filter[]=price-ge-200&group=price&sort=price-desc&limit=10&offset=25"
+        );
+        assert_eq!(
+            parsed.prev_page().unwrap(),
+            "userId=bob&filter[]=price-ge-200&group=price&sort=price-desc&limit=10&offset=5"
+        );
+    }
+
+    #[test]
+    fn test_prev_page_clamped() {
+        let parsed = UrlQuery::new("limit=10&offset=5", []).unwrap();
+        assert_eq!(parsed.prev_page().unwrap(), "limit=10&offset=0");
+
+        let parsed = UrlQuery::new("limit=10", []).unwrap();
+        assert_eq!(parsed.prev_page(), None);
+        assert_eq!(parsed.next_page().unwrap(), "limit=10&offset=10");
+    }
+
+    #[test]
+    fn test_next_page_without_limit() {
+        let parsed = UrlQuery::new("offset=10", []).unwrap();
+
+        assert_eq!(parsed.next_page(), None);
+        assert_eq!(parsed.prev_page(), None);
+    }
+
+    #[test]
+    fn test_next_page_with_total() {
+        let parsed = UrlQuery::new("limit=10&offset=10", []).unwrap();
+
+        assert_eq!(
+            parsed.next_page_with_total(21).unwrap(),
+            "limit=10&offset=20"
+        );
+        assert_eq!(parsed.next_page_with_total(20), None);
+    }
+
+    #[test]
+    fn test_next_page_overflow() {
+        let parsed = UrlQuery::new("limit=18446744073709551615&offset=1", []).unwrap();
+
+        assert_eq!(parsed.next_page(), None);
+        assert_eq!(parsed.next_page_with_total(u64::MAX), None);
+    }
+
+    #[test]
+    fn test_parse_query_page() {
+        let parsed = UrlQuery::new("page=3&per_page=25", []).unwrap();
+
+        assert_eq!(parsed.check_limit_and_offset(), Ok((25, 50)));
+        assert_eq!(parsed.page(), Some(3));
+        assert_eq!(parsed.to_query_string(), "limit=25&page=3");
+        assert_eq!(
+            UrlQuery::new(&parsed.to_query_string(), []).unwrap(),
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_parse_query_page_precedence() {
+        let parsed = UrlQuery::new("page=3&per_page=25&limit=10", []).unwrap();
+        assert_eq!(parsed.check_limit_and_offset(), Ok((10, 20)));
+
+        let parsed = UrlQuery::new("page=3&per_page=25&offset=5", []).unwrap();
+        assert_eq!(parsed.check_limit_and_offset(), Ok((25, 5)));
+        assert_eq!(parsed.page(), None);
+    }
+
+    #[test]
+    fn test_parse_query_invalid_page() {
+        for query in [
+            "page=0&per_page=10",
+            "page=a&per_page=10",
+            "page=1&per_page=0",
+            "page=2",
+        ] {
+            assert_eq!(UrlQuery::new(query, []), Err(ParseError::InvalidPage));
+        }
+    }
+
+    #[test]
+    fn test_options_default_limit() {
+        let options = Options {
+            default_limit: Some(25),
+            ..Default::default()
+        };
+
+        let parsed = UrlQuery::with_options("", [], options.clone()).unwrap();
+        assert_eq!(parsed.check_limit(), Ok(25));
+
+        let parsed = UrlQuery::with_options("limit=10", [], options).unwrap();
+        assert_eq!(parsed.check_limit(), Ok(10));
+    }
+
+    #[test]
+    fn test_options_max_limit_clamp() {
+        let options = Options {
+            max_limit: Some(100),
+            ..Default::default()
+        };
+
+        let parsed = UrlQuery::with_options("limit=1000000", [], options.clone()).unwrap();
+        assert_eq!(parsed.check_limit(), Ok(100));
+
+        let parsed = UrlQuery::with_options("limit=100", [], options.clone()).unwrap();
+        assert_eq!(parsed.check_limit(), Ok(100));
+
+        let parsed = UrlQuery::with_options("", [], options).unwrap();
+        assert!(parsed.check_limit().is_err());
+    }
+
+    #[test]
+    fn test_options_max_limit_reject() {
+        let options = Options {
+            default_limit: Some(25),
+            max_limit: Some(100),
+            limit_overflow: LimitOverflow::Reject,
+            ..Default::default()
+        };
+
+        let result = UrlQuery::with_options("limit=101", [], options.clone());
+        assert_eq!(result, Err(ParseError::LimitExceeded));
+
+        let parsed = UrlQuery::with_options("limit=100", [], options.clone()).unwrap();
+        assert_eq!(parsed.check_limit(), Ok(100));
+
+        let parsed = UrlQuery::with_options("page=2", [], options.clone()).unwrap();
+        assert_eq!(parsed.check_limit_and_offset(), Ok((25, 25)));
+
+        let result = UrlQuery::with_options("limit=ten", [], options);
+        assert_eq!(result, Err(ParseError::InvalidLimit));
+    }
+
+    #[test]
+    fn test_parse_query_sparse_fields() {
+        let options = Options {
+            selectable_fields: HashMap::from([
+                ("orders".into(), vec!["id".into(), "status".into()]),
+                ("users".into(), vec!["email".into()]),
+            ]),
+            ..Default::default()
+        };
+
+        let query = "fields[users]=email&fields[orders]=id,status";
+        let parsed = UrlQuery::with_options(query, [], options.clone()).unwrap();
+
+        assert_eq!(
+            parsed.sparse_fields("orders"),
+            Some(&["id".to_owned(), "status".to_owned()][..])
+        );
+        assert_eq!(
+            parsed.sparse_fields("users"),
+            Some(&["email".to_owned()][..])
+        );
+        assert_eq!(parsed.sparse_fields("items"), None);
+        assert_eq!(
+            parsed.to_query_string(),
+            "fields[orders]=id,status&fields[users]=email"
+        );
+
+        let result = UrlQuery::with_options("fields[orders]=id,total", [], options.clone());
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidSparseField {
+                resource: "orders".into(),
+                field: "total".into()
+            })
+        );
+
+        let result = UrlQuery::with_options("fields[items]=id", [], options);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidSparseField {
+                resource: "items".into(),
+                field: "id".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_query_invalid_limit_offset() {
+        for query in ["limit=10;DROP TABLE orders", "limit=-1", "limit="] {
+            assert_eq!(UrlQuery::new(query, []), Err(ParseError::InvalidLimit));
+        }
+
+        for query in ["offset=1 OR 1=1", "offset=1.5"] {
+            assert_eq!(UrlQuery::new(query, []), Err(ParseError::InvalidOffset));
+        }
+    }
+
+    #[test]
+    fn test_parse_query_after() {
+        let query = "sort=createdAt-desc&after=2024-05-01T10:00:00Z&limit=20";
+        let parsed = UrlQuery::new(query, ["createdAt"]).unwrap();
+        assert_eq!(parsed.after.as_deref(), Some("2024-05-01T10:00:00Z"));
+
+        let result = UrlQuery::new("after=2024-05-01T10:00:00Z&limit=20", ["createdAt"]);
+        assert_eq!(result, Err(ParseError::InvalidAfter));
+    }
+
+    #[test]
+    fn test_parse_query_cursor() {
+        let token = Cursor::encode(["2024-05-01T10:00:00Z", "42"]);
+
+        let query = format!("sort=createdAt-desc&cursor={}&limit=20", token);
+        let parsed = UrlQuery::new(&query, ["createdAt"]).unwrap();
+        assert_eq!(
+            parsed.cursor.unwrap().values,
+            vec!["2024-05-01T10:00:00Z", "42"]
+        );
+
+        let query = format!("cursor={}&limit=20", token);
+        let result = UrlQuery::new(&query, ["createdAt"]);
+        assert_eq!(result, Err(ParseError::InvalidCursor));
+
+        let query = format!("sort=createdAt-desc&cursor={}&limit=20", &token[..5]);
+        let result = UrlQuery::new(&query, ["createdAt"]);
+        assert_eq!(result, Err(ParseError::InvalidCursor));
+    }
+
+    #[test]
+    fn test_parse_query_multiple_sorts() {
+        let query = "sort=price-desc,createdAt-asc";
+
+        let parsed = UrlQuery::new(query, ["price", "createdAt"]).unwrap();
+
+        assert_eq!(
+            parsed.sorts,
+            vec![
+                Sort {
+                    field: "price".into(),
+                    sort_by: SortBy::DESC,
+                    nulls: None,
+                    raw: None,
+                },
+                Sort {
+                    field: "createdAt".into(),
+                    sort_by: SortBy::ASC,
+                    nulls: None,
+                    raw: None,
+                }
+            ]
+        );
+        assert_eq!(parsed.sort().unwrap().field, "price");
+        assert_eq!(parsed.to_query_string(), query);
+
+        // Repeated sort parameters add up.
+        let repeated = UrlQuery::new("sort=price-desc&sort=createdAt-asc", ["price", "createdAt"]);
+        assert_eq!(repeated.unwrap().sorts, parsed.sorts);
+
+        let result = UrlQuery::new("sort=price-desc,createdAt-asc", ["price"]);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidSortField("createdAt".into()))
+        );
+    }
+
+    #[test]
+    fn test_parse_query_empty_sort_segment() {
+        for query in [
+            "sort=price-desc,",
+            "sort=,price-desc",
+            "sort=price-desc,,id-asc",
+        ] {
+            let result = UrlQuery::new(query, ["price", "id"]);
+            assert_eq!(result, Err(ParseError::InvalidSort), "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_parse_query_raw() {
+        let query = "userName=bob%20smith&filter[]=price-ge-200&sort=price-desc,id-asc";
+
+        let parsed = UrlQuery::new(query, ["userName", "price", "id"]).unwrap();
+
+        let raws: Vec<_> = parsed.filters.iter().map(|f| f.raw()).collect();
+        assert_eq!(
+            raws,
+            vec![Some("userName=bob smith"), Some("filter[]=price-ge-200")]
+        );
+        let raws: Vec<_> = parsed.sorts.iter().map(|s| s.raw()).collect();
+        assert_eq!(raws, vec![Some("sort=price-desc"), Some("sort=id-asc")]);
+
+        let options = Options {
+            discard_raw: true,
+            ..Default::default()
+        };
+        let discarded =
+            UrlQuery::with_options(query, ["userName", "price", "id"], options).unwrap();
+
+        assert!(discarded.filters.iter().all(|f| f.raw().is_none()));
+        assert!(discarded.sorts.iter().all(|s| s.raw().is_none()));
+        assert_eq!(parsed, discarded);
+
+        let query = "filter[]=price-cont-1";
+        let err = UrlQuery::new(query, ["price"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "operator cont is not allowed on field price in filter[]=price-cont-1"
+        );
+
+        let options = Options {
+            discard_raw: true,
+            ..Default::default()
+        };
+        let err = UrlQuery::with_options(query, ["price"], options).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "operator cont is not allowed on field price"
+        );
+    }
+
+    #[test]
+    fn test_mixed_case_sort_round_trip() {
+        let parsed = UrlQuery::new("sort=price-DESC,-createdAt", ["price", "createdAt"]).unwrap();
+
+        assert_eq!(parsed.to_query_string(), "sort=price-desc,createdAt-desc");
+        assert_eq!(
+            UrlQuery::new(&parsed.to_query_string(), ["price", "createdAt"]).unwrap(),
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_parse_query_multiple_groups() {
+        let query = "group=status,region,status&having=region-ne-eu";
+
+        let parsed = UrlQuery::new(query, ["status", "region"]).unwrap();
+
+        assert_eq!(parsed.groups, vec!["status", "region"]);
+        assert_eq!(parsed.group(), Some("status"));
+        assert_eq!(
+            parsed.to_query_string(),
+            "group=status,region&having=region-ne-eu"
+        );
+
+        let result = UrlQuery::new("group=status,", ["status"]);
+        assert_eq!(result, Err(ParseError::InvalidIdentifier("".into())));
+    }
+
+    #[test]
+    fn test_parse_query_having_aggregate() {
+        let options = Options {
+            aggregates: vec!["count".into()],
+            ..Default::default()
+        };
+        let query = "group=status&having[]=count-ge-10";
+
+        let parsed = UrlQuery::with_options(query, ["status"], options.clone()).unwrap();
+
+        assert_eq!(
+            parsed.havings,
+            vec![Filter::from_key_value("count", "10", Operator::GE)]
+        );
+
+        let result = UrlQuery::with_options("having[]=count-ge-10", ["status"], options);
+        assert_eq!(result, Err(ParseError::InvalidHaving));
+
+        let result = UrlQuery::new(query, ["status"]);
+        assert_eq!(result, Err(ParseError::InvalidField));
+    }
+
+    #[test]
+    fn test_parse_query_malicious_identifiers() {
+        let allowed = ["price", "orders.price", "price; DROP TABLE x--"];
+
+        for query in [
+            "sort=price;%20DROP%20TABLE%20x--desc",
+            "sort=price%3B%20DROP%20TABLE%20x--desc",
+            "group=price)%20OR%201=1",
+            "group=price%20--",
+            "filter[]=price%3Bx-eq-1",
+            "price%3B%20DROP%20TABLE%20x--=1",
+        ] {
+            let result = UrlQuery::new(query, allowed);
+            assert!(
+                matches!(result, Err(ParseError::InvalidIdentifier(_))),
+                "{}",
+                query
+            );
+        }
+
+        let result = UrlQuery::new("group=price)%20OR%201=1", allowed);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidIdentifier("price) OR 1=1".into()))
+        );
+
+        let result = UrlQuery::new("sort=quantity-desc", allowed);
+        assert_eq!(result, Err(ParseError::InvalidSortField("quantity".into())));
+        let result = UrlQuery::new("group=price,quantity", allowed);
+        assert_eq!(
+            result,
+            Err(ParseError::InvalidGroupField("quantity".into()))
+        );
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "grouping by quantity is not allowed"
+        );
+
+        let parsed = UrlQuery::new("sort=orders.price-desc&group=orders.price", allowed).unwrap();
+        assert_eq!(parsed.groups, vec!["orders.price"]);
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let options = Options {
+            json_fields: vec!["metadata".into()],
+            ..Default::default()
+        };
+        let query = "metadata.color=red&filter[]=metadata.size.width-ge-10&sort=metadata.rank-asc";
+
+        let parsed = UrlQuery::with_options(query, ["id"], options.clone()).unwrap();
+
+        assert_eq!(
+            parsed.filters,
+            vec![
+                Filter::from_key_value("metadata.color", "red", Operator::EQ),
+                Filter::from_key_value("metadata.size.width", "10", Operator::GE),
+            ]
+        );
+
+        for query in ["settings.color=red", "metadata=red", "metadata..color=red"] {
+            let result = UrlQuery::with_options(query, ["id"], options.clone());
+            assert_eq!(result, Err(ParseError::InvalidField), "{}", query);
+        }
+
+        let result = UrlQuery::new("metadata.color=red", ["metadata"]);
+        assert_eq!(result, Err(ParseError::InvalidField));
+    }
+
+    #[test]
+    fn test_json_contains() {
+        let options = Options {
+            json_fields: vec!["attributes".into()],
+            ..Default::default()
+        };
+        let query = "filter[]=attributes-cont-%7B%22size%22%3A%7B%22eu%22%3A44%7D%7D";
+
+        let parsed = UrlQuery::with_options(query, ["attributes", "name"], options.clone());
+
+        assert_eq!(
+            parsed.unwrap().filters,
+            vec![Filter::from_key_value(
+                "attributes",
+                r#"{"size":{"eu":44}}"#,
+                Operator::CONT
+            )]
+        );
+
+        let result = UrlQuery::with_options("filter[]=name-cont-1", ["name"], options.clone());
+        assert_eq!(
+            result,
+            Err(ParseError::OperatorNotAllowed {
+                field: "name".to_owned(),
+                operator: Operator::CONT,
+                raw: Some("filter[]=name-cont-1".to_owned()),
+            })
+        );
+
+        #[cfg(feature = "serde_json")]
+        assert_eq!(
+            UrlQuery::with_options("filter[]=attributes-cont-%7Bsize", ["attributes"], options),
+            Err(ParseError::InvalidFormat {
+                field: "attributes".to_owned(),
+                value: "{size".to_owned(),
+                format: "JSON",
+                raw: Some("filter[]=attributes-cont-{size".to_owned()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_get() {
+        let query = "userId=123&filter[]=price-ge-10&filter[]=status-eq-paid&name=";
+        let parsed = UrlQuery::new(query, ["userId", "price", "status", "name"]).unwrap();
+
+        assert_eq!(parsed.get_str("userId"), Some("123"));
+        assert_eq!(parsed.get_str("price"), None);
+        assert_eq!(parsed.get_str("name"), Some(""));
+        // Only params are read, not `filter[]`.
+        assert_eq!(parsed.get_str("status"), None);
+
+        assert_eq!(parsed.get::<i64>("userId"), Ok(Some(123)));
+        assert_eq!(parsed.get::<i64>("price"), Ok(None));
+        assert_eq!(
+            parsed.get::<i64>("name"),
+            Err(ParseError::InvalidValue {
+                field: "name".to_owned(),
+                value: "".to_owned(),
+                raw: None,
+            })
+        );
+
+        // A param repeated with the same value is still one value.
+        let parsed = UrlQuery::new("userId=1&filter[]=userId-eq-1", ["userId"]).unwrap();
+        assert_eq!(parsed.get::<i64>("userId"), Ok(Some(1)));
+
+        // Other filters on the param would let the SQL match them too.
+        for query in [
+            "userId=1&filter[]=userId-eq-2",
+            "userId=1&filter[]=userId-ge-0",
+        ] {
+            let parsed = UrlQuery::new(query, ["userId"]).unwrap();
+            assert_eq!(parsed.get_str("userId"), None, "{}", query);
+            assert_eq!(
+                parsed.get::<i64>("userId"),
+                Err(ParseError::AmbiguousParam("userId".to_owned())),
+                "{}",
+                query
+            );
+        }
+    }
+
+    #[test]
+    fn test_require() {
+        let query = "userId=1&filter[]=from-ge-2024-01-01&sort=to";
+        let parsed = UrlQuery::new(query, ["userId", "from", "to", "region"]).unwrap();
+
+        assert_eq!(parsed.require(&["userId", "from"]), Ok(()));
+        assert_eq!(
+            parsed.require(&["from", "to", "region"]),
+            Err(ParseError::MissingParams(vec![
+                "to".to_owned(),
+                "region".to_owned()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_with_defaults() {
+        let defaults = [("status", "active"), ("visibility", "public")];
+        let allowed = ["status", "visibility"];
+
+        let parsed = UrlQuery::new("", allowed).unwrap().with_defaults(defaults);
+        assert_eq!(
+            parsed.filters,
+            vec![
+                Filter::new("status-eq-active").unwrap(),
+                Filter::new("visibility-eq-public").unwrap(),
+            ]
+        );
+
+        let parsed = UrlQuery::new("visibility=private", allowed)
+            .unwrap()
+            .with_defaults(defaults);
+        assert_eq!(
+            parsed.filters,
+            vec![
+                Filter::new("visibility-eq-private").unwrap(),
+                Filter::new("status-eq-active").unwrap(),
+            ]
+        );
+
+        let parsed = UrlQuery::new("filter[]=status-ne-draft", allowed)
+            .unwrap()
+            .with_defaults(defaults);
+        assert_eq!(
+            parsed.filters,
+            vec![
+                Filter::new("status-ne-draft").unwrap(),
+                Filter::new("visibility-eq-public").unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignored() {
+        let options = Options {
+            ignored: vec!["apiKey".into(), "utm_*".into()],
+            ..Default::default()
+        };
+        let query =
+            "apiKey=secret&userId=1&utm_source=mail&utm_campaign=spring&sort=userId&limit=5";
+        let parsed = UrlQuery::with_options(query, ["userId"], options.clone()).unwrap();
+
+        assert_eq!(parsed.filters, vec![Filter::new("userId-eq-1").unwrap()]);
+        assert_eq!(parsed.sorts.len(), 1);
+        assert_eq!(parsed.limit_offset, (Some(5), None));
+        assert_eq!(parsed.params, HashSet::from(["userId".to_owned()]));
+        assert_eq!(
+            parsed.ignored,
+            HashMap::from([
+                ("apiKey".to_owned(), "secret".to_owned()),
+                ("utm_source".to_owned(), "mail".to_owned()),
+                ("utm_campaign".to_owned(), "spring".to_owned()),
+            ])
+        );
+
+        // Keys which are neither allowed nor ignored still fail.
+        assert_eq!(
+            UrlQuery::with_options("apiKeys=secret", ["userId"], options),
+            Err(ParseError::InvalidField)
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let options = Options {
+            ignored: vec!["utm_*".into()],
+            strict: true,
+            ..Default::default()
+        };
+        let allowed = ["userId", "price"];
+
+        let query = "sart=price-desc&userId=1&filter[]=pricee-ge-10&utm_source=mail&sart=price";
+        assert_eq!(
+            UrlQuery::with_options(query, allowed, options.clone()),
+            Err(ParseError::UnknownParameter(vec![
+                "sart".to_owned(),
+                "pricee".to_owned()
+            ]))
+        );
+
+        let query = "userId=1&filter[]=price-ge-10&sort=price-desc&utm_source=mail&limit=5";
+        assert!(UrlQuery::with_options(query, allowed, options).is_ok());
+
+        // Without strict mode, parsing fails on the first one.
+        assert_eq!(
+            UrlQuery::new("sart=price-desc&filter[]=pricee-ge-10", allowed),
+            Err(ParseError::InvalidField)
+        );
+    }
+
+    #[test]
+    fn test_mutations() {
+        let query =
+            "userId=1&filter[]=tenantId-eq-2&filter[]=price-ge-200&sort=price&page=3&limit=50";
+        let mut parsed = UrlQuery::new(query, ["userId", "tenantId", "price"]).unwrap();
+
+        let removed = parsed.remove_filters("tenantId");
+        assert_eq!(removed, vec![Filter::new("tenantId-eq-2").unwrap()]);
+
+        parsed.add_filter(Filter::new("tenantId-eq-7").unwrap());
+        parsed.replace_filter("price", Filter::new("price-le-100").unwrap());
+        parsed.set_sort(Sort::new("userId-desc").unwrap());
+        parsed.set_limit(10);
+        assert_eq!(parsed.limit_offset, (Some(10), Some(20)));
+
+        let (sql, args) = QueryBuilder::from_str("SELECT * FROM orders", parsed.clone())
+            .convert_case(NameCase::Snake)
+            .build();
+        assert_eq!(
+            sql,
+            "SELECT * FROM orders WHERE user_id = $1 AND price <= $2 AND tenant_id = $3 \
+            ORDER BY user_id DESC LIMIT 10 OFFSET 20"
+        );
+        assert_eq!(
+            into_tuples(args),
+            vec![
+                ("userId".to_owned(), "1".to_owned()),
+                ("price".to_owned(), "100".to_owned()),
+                ("tenantId".to_owned(), "7".to_owned()),
+            ]
+        );
+        assert_eq!(
+            parsed.to_query_string(),
+            "userId=1&filter[]=price-le-100&filter[]=tenantId-eq-7&sort=userId-desc&limit=10&page=3"
+        );
+
+        parsed.set_offset(5);
+        parsed.remove_filters("userId");
+        assert_eq!(
+            parsed.to_query_string(),
+            "filter[]=price-le-100&filter[]=tenantId-eq-7&sort=userId-desc&limit=10&offset=5"
+        );
+    }
+
+    #[test]
+    fn test_inspection() {
+        let allowed = ["orderId", "price"];
+
+        let parsed = UrlQuery::new(
+            "orderId=1&filter[]=price-ge-10&filter[]=price-le-50",
+            allowed,
+        )
+        .unwrap();
+        assert!(parsed.has_filter("orderId"));
+        assert!(parsed.has_filter("price"));
+        assert!(!parsed.has_filter("status"));
+        assert_eq!(
+            parsed.filters_on("price").collect::<Vec<_>>(),
+            vec![
+                &Filter::new("price-ge-10").unwrap(),
+                &Filter::new("price-le-50").unwrap()
+            ]
+        );
+        assert!(!parsed.has_pagination());
+        assert!(!parsed.is_empty());
+
+        let parsed = UrlQuery::new("", allowed).unwrap();
+        assert!(parsed.is_empty());
+        assert_eq!(parsed.sort_field(), None);
+
+        let parsed = UrlQuery::new("sort=-price,orderId", allowed).unwrap();
+        assert_eq!(parsed.sort_field(), Some("price"));
+        assert!(!parsed.is_empty());
+
+        for query in ["limit=10", "offset=10", "page=2&per_page=10"] {
+            let parsed = UrlQuery::new(query, allowed).unwrap();
+            assert!(parsed.has_pagination());
+            assert!(!parsed.is_empty());
+        }
+
+        let options = || Options {
+            default_limit: Some(25),
+            ..Default::default()
+        };
+        let parsed = UrlQuery::with_options("", allowed, options()).unwrap();
+        assert_eq!(parsed.limit_offset, (Some(25), None));
+        assert!(!parsed.has_pagination());
+        assert!(parsed.is_empty());
+
+        for query in ["limit=25", "offset=10", "page=2"] {
+            let parsed = UrlQuery::with_options(query, allowed, options()).unwrap();
+            assert!(parsed.has_pagination());
+            assert!(!parsed.is_empty());
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_trace_parse_error() {
+        let events = crate::capture::events(|| {
+            assert!(UrlQuery::new("id=1&limit=ten", ["id"]).is_err());
+        });
+        assert_eq!(events.len(), 1);
+        assert!(events[0].starts_with("WARN message=failed to parse url query error="));
+        assert!(events[0].ends_with("segment=\"limit=ten\""));
+    }
 }
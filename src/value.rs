@@ -0,0 +1,469 @@
+use crate::ParseError;
+
+/// The type of the values of a field, see `QueryBuilder::field_types`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldType {
+    Int,
+    Float,
+    /// `true` or `false`, or another spelling recognised by `parse_bool`.
+    Bool,
+    /// A hyphenated UUID, eg `8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e`. With the `uuid` feature, any
+    /// form accepted by `uuid::Uuid::parse_str`, canonicalised to hyphenated lowercase.
+    Uuid,
+    /// A date, eg `2024-05-01`.
+    Date,
+    /// An RFC 3339 timestamp, eg `2024-05-01T10:00:00+02:00`, or a date standing for its
+    /// midnight. With the `chrono` feature, the value is canonicalised to UTC, eg
+    /// `2024-05-01T08:00:00Z`.
+    DateTime,
+    Text,
+}
+
+/// A value parsed with the `FieldType` of its field.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Uuid(String),
+    Date(String),
+    DateTime(String),
+    Text(String),
+    /// The elements of the list of an `overlaps` filter, to bind as an array.
+    Array(Vec<ArgValue>),
+}
+
+impl std::fmt::Display for ArgValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ArgValue::Int(value) => write!(f, "{}", value),
+            ArgValue::Float(value) => write!(f, "{}", value),
+            ArgValue::Bool(value) => write!(f, "{}", value),
+            ArgValue::Uuid(value)
+            | ArgValue::Date(value)
+            | ArgValue::DateTime(value)
+            | ArgValue::Text(value) => write!(f, "{}", value),
+            ArgValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+                write!(f, "{}", values.join(","))
+            }
+        }
+    }
+}
+
+impl FieldType {
+    /// Returns the value parsed as this type, or `None` if it isn't valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use query::value::{ArgValue, FieldType};
+    ///
+    /// assert_eq!(FieldType::Int.parse("42"), Some(ArgValue::Int(42)));
+    /// assert_eq!(FieldType::Int.parse("4x2"), None);
+    /// ```
+    pub fn parse(&self, value: &str) -> Option<ArgValue> {
+        match self {
+            FieldType::Int => value.parse().ok().map(ArgValue::Int),
+            FieldType::Float => value
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(ArgValue::Float),
+            FieldType::Bool => parse_bool(value).map(ArgValue::Bool),
+            FieldType::Uuid => parse_uuid(value).map(ArgValue::Uuid),
+            FieldType::Date => parse_date(value).map(ArgValue::Date),
+            FieldType::DateTime => parse_datetime(value).map(ArgValue::DateTime),
+            FieldType::Text => Some(ArgValue::Text(value.to_owned())),
+        }
+    }
+
+    /// Returns the format expected for the values of dates and timestamps.
+    pub fn format(&self) -> Option<&'static str> {
+        match self {
+            FieldType::Date => Some("YYYY-MM-DD"),
+            FieldType::DateTime => Some("RFC 3339"),
+            _ => None,
+        }
+    }
+
+    /// Returns the error for a value of `field` which isn't valid for this type:
+    /// `ParseError::InvalidFormat` for dates and timestamps, `ParseError::InvalidValue`
+    /// otherwise.
+    pub fn invalid(&self, field: &str, value: &str) -> ParseError {
+        match self.format() {
+            Some(format) => ParseError::InvalidFormat {
+                field: field.to_owned(),
+                value: value.to_owned(),
+                format,
+                raw: None,
+            },
+            None => ParseError::InvalidValue {
+                field: field.to_owned(),
+                value: value.to_owned(),
+                raw: None,
+            },
+        }
+    }
+}
+
+/// Returns the boolean spelled `true`/`false`, `1`/`0` or `yes`/`no`, ignoring case.
+///
+/// # Examples
+///
+/// ```
+/// use query::value::parse_bool;
+///
+/// assert_eq!(parse_bool("Yes"), Some(true));
+/// assert_eq!(parse_bool("0"), Some(false));
+/// assert_eq!(parse_bool("maybe"), None);
+/// ```
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the date, which must be valid.
+#[cfg(not(feature = "chrono"))]
+fn parse_date(value: &str) -> Option<String> {
+    is_date(value).then(|| value.to_owned())
+}
+
+/// Returns the date, in canonical form.
+#[cfg(feature = "chrono")]
+fn parse_date(value: &str) -> Option<String> {
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+
+    Some(date.format("%Y-%m-%d").to_string())
+}
+
+/// Returns the timestamp, which must be valid, or the date.
+#[cfg(not(feature = "chrono"))]
+fn parse_datetime(value: &str) -> Option<String> {
+    is_datetime(value).then(|| value.to_owned())
+}
+
+/// Returns the timestamp in UTC, or the midnight of a date.
+#[cfg(feature = "chrono")]
+fn parse_datetime(value: &str) -> Option<String> {
+    use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+
+    let datetime = match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0)?.and_utc(),
+        Err(_) => DateTime::parse_from_rfc3339(value)
+            .ok()?
+            .with_timezone(&Utc),
+    };
+
+    Some(datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+/// Returns the midnights starting the day of a `YYYY-MM-DD` date and the next, in UTC.
+///
+/// # Examples
+///
+/// ```
+/// use query::value::day_range;
+///
+/// assert_eq!(
+///     day_range("2024-02-29"),
+///     Some(("2024-02-29T00:00:00Z".to_owned(), "2024-03-01T00:00:00Z".to_owned()))
+/// );
+/// assert_eq!(day_range("2024-02-29T10:00:00Z"), None);
+/// ```
+#[cfg(feature = "chrono")]
+pub fn day_range(value: &str) -> Option<(String, String)> {
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let midnight = |date: chrono::NaiveDate| date.format("%Y-%m-%dT00:00:00Z").to_string();
+
+    Some((midnight(date), midnight(date.succ_opt()?)))
+}
+
+/// Returns the midnight a relative date keyword stands for, in the timezone of `now`:
+///
+/// - `today` and `yesterday`: the start of the day, or of the day before;
+/// - `last7days` and `last30days`: the start of the day 7 or 30 days before today;
+/// - `startOfMonth` and `startOfYear`: the start of the first day of the month or year.
+///
+/// # Examples
+///
+/// ```
+/// use chrono::DateTime;
+/// use query::value::relative_date;
+///
+/// let now = DateTime::parse_from_rfc3339("2024-05-15T12:00:00+02:00").unwrap();
+///
+/// let start = relative_date("startOfMonth", now).unwrap();
+/// assert_eq!(start.to_rfc3339(), "2024-05-01T00:00:00+02:00");
+/// assert_eq!(relative_date("tomorrow", now), None);
+/// ```
+#[cfg(feature = "chrono")]
+pub fn relative_date(
+    keyword: &str,
+    now: chrono::DateTime<chrono::FixedOffset>,
+) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    use chrono::{Datelike, Days};
+
+    let today = now.date_naive();
+    let date = match keyword {
+        "today" => today,
+        "yesterday" => today.checked_sub_days(Days::new(1))?,
+        "last7days" => today.checked_sub_days(Days::new(7))?,
+        "last30days" => today.checked_sub_days(Days::new(30))?,
+        "startOfMonth" => today.with_day(1)?,
+        "startOfYear" => today.with_ordinal(1)?,
+        _ => return None,
+    };
+
+    date.and_hms_opt(0, 0, 0)?
+        .and_local_timezone(*now.offset())
+        .single()
+}
+
+/// Returns the UUID, which must be hyphenated.
+#[cfg(not(feature = "uuid"))]
+fn parse_uuid(value: &str) -> Option<String> {
+    is_uuid(value).then(|| value.to_owned())
+}
+
+/// Returns the UUID, hyphenated and in lowercase.
+#[cfg(feature = "uuid")]
+fn parse_uuid(value: &str) -> Option<String> {
+    let uuid = uuid::Uuid::parse_str(value).ok()?;
+
+    Some(uuid.hyphenated().to_string())
+}
+
+/// Returns whether the value is a hyphenated UUID.
+#[cfg(not(feature = "uuid"))]
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    let lens = [8, 4, 4, 4, 12];
+
+    groups.len() == lens.len()
+        && groups
+            .iter()
+            .zip(lens)
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Returns whether the value is a `YYYY-MM-DD` date.
+#[cfg(not(feature = "chrono"))]
+fn is_date(value: &str) -> bool {
+    if value.len() != 10 {
+        return false;
+    }
+
+    let mut parts = value.splitn(3, '-');
+    let mut number = |len: usize| {
+        let part = parts.next()?;
+        match part.len() == len && part.chars().all(|c| c.is_ascii_digit()) {
+            true => part.parse::<u32>().ok(),
+            false => None,
+        }
+    };
+    let (year, month, day) = match (number(4), number(2), number(2)) {
+        (Some(year), Some(month), Some(day)) => (year, month, day),
+        _ => return false,
+    };
+
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+
+    (1..=days).contains(&day)
+}
+
+/// Returns whether the value is an RFC 3339 timestamp, eg `2024-05-01T10:00:00.5+02:00`, or a
+/// `YYYY-MM-DD` date, accepting the same values as `chrono::DateTime::parse_from_rfc3339`.
+#[cfg(not(feature = "chrono"))]
+fn is_datetime(value: &str) -> bool {
+    let (date, time) = match value.get(..10) {
+        Some(date) => (date, &value[10..]),
+        None => return false,
+    };
+    if !is_date(date) {
+        return false;
+    }
+    if time.is_empty() {
+        return true;
+    }
+
+    // The number made of the first `len` bytes of `rest`, if they are all digits.
+    let number = |rest: &str, len: usize| {
+        let digits = rest.get(..len)?;
+        match digits.chars().all(|c| c.is_ascii_digit()) {
+            true => digits.parse::<u32>().ok(),
+            false => None,
+        }
+    };
+
+    // `T10:00:00`, with a leap second allowed.
+    let time_valid = match time.as_bytes().get(..9) {
+        Some([b'T' | b't' | b' ', _, _, b':', _, _, b':', _, _]) => matches!(
+            (number(&time[1..], 2), number(&time[4..], 2), number(&time[7..], 2)),
+            (Some(hour), Some(minute), Some(second)) if hour < 24 && minute < 60 && second <= 60
+        ),
+        _ => false,
+    };
+    if !time_valid {
+        return false;
+    }
+
+    let mut offset = &time[9..];
+    if let Some(fraction) = offset.strip_prefix('.') {
+        let digits = fraction.len()
+            - fraction
+                .trim_start_matches(|c: char| c.is_ascii_digit())
+                .len();
+        if digits == 0 {
+            return false;
+        }
+        offset = &fraction[digits..];
+    }
+
+    match offset.as_bytes() {
+        [b'Z' | b'z'] => true,
+        [b'+' | b'-', _, _, b':', _, _] => match (number(&offset[1..], 2), number(&offset[4..], 2))
+        {
+            (Some(hours), Some(minutes)) => hours < 24 && minutes < 60,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArgValue, FieldType};
+
+    #[test]
+    fn test_parse() {
+        let cases = [
+            (FieldType::Int, "-7", Some(ArgValue::Int(-7))),
+            (FieldType::Float, "1.5", Some(ArgValue::Float(1.5))),
+            (FieldType::Float, "NaN", None),
+            (FieldType::Bool, "true", Some(ArgValue::Bool(true))),
+            (FieldType::Bool, "NO", Some(ArgValue::Bool(false))),
+            (FieldType::Bool, "maybe", None),
+            (
+                FieldType::Uuid,
+                "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e",
+                Some(ArgValue::Uuid(
+                    "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e".to_owned(),
+                )),
+            ),
+            (FieldType::Uuid, "8bd8a6fb-e2b2-47ab-b3db", None),
+            (
+                FieldType::Date,
+                "2024-02-29",
+                Some(ArgValue::Date("2024-02-29".to_owned())),
+            ),
+            (
+                FieldType::DateTime,
+                "2024-02-29T10:00:00Z",
+                Some(ArgValue::DateTime("2024-02-29T10:00:00Z".to_owned())),
+            ),
+            (FieldType::Date, "2023-02-29", None),
+            (FieldType::Date, "2024-05-01x", None),
+            (FieldType::Date, "2024-05-01T10:00:00Z", None),
+            (FieldType::DateTime, "2024-05-01 10:00", None),
+            (FieldType::DateTime, "2024-05-01T25:00:00Z", None),
+            (FieldType::DateTime, "2024-05-01T10:00:00+0200", None),
+            (FieldType::DateTime, "2024-05-01T10:00:00.Z", None),
+            (FieldType::DateTime, "2024-05-01T10:00:00", None),
+            (FieldType::Text, "", Some(ArgValue::Text(String::new()))),
+        ];
+
+        for (field_type, value, expected) in cases {
+            assert_eq!(field_type.parse(value), expected, "{}", value);
+        }
+    }
+    #[test]
+    fn test_parse_datetime_formats() {
+        let valid = [
+            "2024-05-01",
+            "2024-05-01T10:00:00Z",
+            "2024-05-01t10:00:00.5z",
+            "2024-05-01 10:00:00-00:00",
+            "2023-12-31T23:59:60+02:00",
+        ];
+        for value in valid {
+            assert!(FieldType::DateTime.parse(value).is_some(), "{}", value);
+        }
+
+        let invalid = [
+            "2024-05-01T10:00Z",
+            "2024-05-01T10:00:00+24:00",
+            "2024-05-01T10:00:00+02:60",
+            "2024-05-01Z",
+        ];
+        for value in invalid {
+            assert_eq!(FieldType::DateTime.parse(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_parse_datetime() {
+        let cases = [
+            ("2024-05-01T10:00:00+02:00", Some("2024-05-01T08:00:00Z")),
+            ("2024-05-01T10:00:00.5Z", Some("2024-05-01T10:00:00.500Z")),
+            ("2024-05-01", Some("2024-05-01T00:00:00Z")),
+            ("2024-05-01T25:00:00Z", None),
+            ("yesterday", None),
+        ];
+
+        for (value, expected) in cases {
+            let expected = expected.map(|e| ArgValue::DateTime(e.to_owned()));
+            assert_eq!(FieldType::DateTime.parse(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "uuid")]
+    fn test_parse_uuid() {
+        let uuid = "8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e";
+        let cases = [
+            ("8BD8A6FB-E2B2-47AB-B3DB-4F47C067BA5E", Some(uuid)),
+            ("8bd8a6fbe2b247abb3db4f47c067ba5e", Some(uuid)),
+            ("urn:uuid:8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5e", Some(uuid)),
+            ("8bd8a6fb-e2b2-47ab-b3db-4f47c067ba5g", None),
+            ("abc", None),
+        ];
+
+        for (value, expected) in cases {
+            let expected = expected.map(|e| ArgValue::Uuid(e.to_owned()));
+            assert_eq!(FieldType::Uuid.parse(value), expected, "{}", value);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "chrono")]
+    fn test_relative_date() {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-03-01T01:00:00+02:00").unwrap();
+        let cases = [
+            ("today", Some("2024-03-01T00:00:00+02:00")),
+            ("yesterday", Some("2024-02-29T00:00:00+02:00")),
+            ("last7days", Some("2024-02-23T00:00:00+02:00")),
+            ("last30days", Some("2024-01-31T00:00:00+02:00")),
+            ("startOfMonth", Some("2024-03-01T00:00:00+02:00")),
+            ("startOfYear", Some("2024-01-01T00:00:00+02:00")),
+            ("Today", None),
+            ("tomorrow", None),
+        ];
+
+        for (keyword, expected) in cases {
+            let date = super::relative_date(keyword, now).map(|date| date.to_rfc3339());
+            assert_eq!(date.as_deref(), expected, "{}", keyword);
+        }
+    }
+}